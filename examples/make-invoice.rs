@@ -1,11 +1,12 @@
-use glalby_bindings::{new_blocking_greenlight_alby_client, recover, MakeInvoiceRequest};
+use glalby_bindings::{new_blocking_greenlight_alby_client, recover, MakeInvoiceRequest, Network};
 
 fn main() {
     let mnemonic = std::env::var("MNEMONIC").unwrap();
 
-    let credentials = recover(mnemonic.clone()).unwrap();
+    let credentials = recover(mnemonic.clone(), Network::Bitcoin).unwrap();
 
-    let client = new_blocking_greenlight_alby_client(mnemonic, credentials).unwrap();
+    let client =
+        new_blocking_greenlight_alby_client(mnemonic, Network::Bitcoin, credentials).unwrap();
     let result = client
         .make_invoice(MakeInvoiceRequest {
             amount_msat: 1000,
@@ -16,6 +17,7 @@ fn main() {
             fallbacks: None,
             preimage: None,
             deschashonly: None,
+            truncate_description: false,
         })
         .unwrap();
 
@@ -0,0 +1,56 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use gl_client::pb::cln;
+
+const DATASTORE_KEY: [&str; 2] = ["glalby", "address"];
+
+/// Remembers an address handed out by `new_address`, with the time it was
+/// created. CLN has no way to list the addresses it generated, so the
+/// history is kept in the node's datastore.
+pub(crate) async fn record(
+    mut node: gl_client::node::ClnClient,
+    address: &str,
+) -> anyhow::Result<()> {
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    node.datastore(cln::DatastoreRequest {
+        key: DATASTORE_KEY
+            .iter()
+            .map(|k| k.to_string())
+            .chain(std::iter::once(address.to_string()))
+            .collect(),
+        string: Some(created_at.to_string()),
+        ..Default::default()
+    })
+    .await
+    .context("failed to store address")?;
+    Ok(())
+}
+
+/// All recorded addresses with their creation time, oldest first.
+pub(crate) async fn load(
+    mut node: gl_client::node::ClnClient,
+) -> anyhow::Result<Vec<(String, u64)>> {
+    let datastore = node
+        .list_datastore(cln::ListdatastoreRequest {
+            key: DATASTORE_KEY.iter().map(|k| k.to_string()).collect(),
+        })
+        .await
+        .context("failed to load addresses")?
+        .into_inner()
+        .datastore;
+
+    let mut addresses: Vec<_> = datastore
+        .into_iter()
+        .filter_map(|entry| {
+            let created_at = entry.string?.parse().ok()?;
+            Some((entry.key.last()?.clone(), created_at))
+        })
+        .collect();
+    addresses.sort_by_key(|(_, created_at)| *created_at);
+    Ok(addresses)
+}
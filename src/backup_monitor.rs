@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+use crate::features;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How well the node's channel state is backed up with its peers. CLN hands
+/// its latest state to every connected peer that provides peer storage, so a
+/// backup is only as fresh as the last time such a peer was connected.
+#[derive(Clone, Debug)]
+pub struct BackupStatus {
+    pub channel_peers: u32,
+    pub storage_peers: u32,
+    pub connected_storage_peers: u32,
+    /// When a peer storing our state was last seen connected, if the
+    /// monitor has seen one.
+    pub last_backup_at: Option<u64>,
+}
+
+/// Remembers when a storage peer was last connected and whether the backup
+/// is currently reported as stale.
+#[derive(Default)]
+pub(crate) struct BackupMonitor {
+    last_backup_at: Mutex<Option<u64>>,
+    stale: Mutex<bool>,
+}
+
+impl BackupMonitor {
+    pub async fn status(&self, node: gl_client::node::ClnClient) -> anyhow::Result<BackupStatus> {
+        let mut status = status(node).await?;
+        let mut last_backup_at = self.last_backup_at.lock().unwrap();
+        if status.connected_storage_peers > 0 {
+            *last_backup_at = Some(now());
+        }
+        status.last_backup_at = *last_backup_at;
+        Ok(status)
+    }
+}
+
+/// Emits `BackupStale` once no peer has been able to store our state for
+/// `max_age`, and again after every recovery.
+pub(crate) async fn watch(
+    monitor: Arc<BackupMonitor>,
+    node: gl_client::node::ClnClient,
+    max_age: Duration,
+    events: Arc<EventDispatcher>,
+) {
+    let started_at = now();
+    loop {
+        match monitor.status(node.clone()).await {
+            Ok(status) => {
+                let since = status.last_backup_at.unwrap_or(started_at);
+                let is_stale =
+                    status.channel_peers > 0 && now().saturating_sub(since) > max_age.as_secs();
+
+                let mut stale = monitor.stale.lock().unwrap();
+                if is_stale && !*stale {
+                    events.emit(ClientEvent::BackupStale {
+                        last_backup_at: status.last_backup_at,
+                        storage_peers: status.storage_peers,
+                    });
+                }
+                *stale = is_stale;
+            }
+            Err(e) => eprintln!("Backup monitor failed to read peers: {:?}", e),
+        }
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn status(mut node: gl_client::node::ClnClient) -> anyhow::Result<BackupStatus> {
+    let channel_peers: HashSet<Vec<u8>> = node
+        .list_peer_channels(cln::ListpeerchannelsRequest::default())
+        .await
+        .context("failed to list peer channels")?
+        .into_inner()
+        .channels
+        .into_iter()
+        .filter_map(|c| c.peer_id)
+        .collect();
+
+    let storage_peers: Vec<_> = node
+        .list_peers(cln::ListpeersRequest::default())
+        .await
+        .context("failed to list peers")?
+        .into_inner()
+        .peers
+        .into_iter()
+        .filter(|p| channel_peers.contains(&p.id))
+        .filter(|p| {
+            p.features.as_deref().is_some_and(|f| {
+                features::feature_names(f)
+                    .iter()
+                    .any(|name| name == "option_provide_storage")
+            })
+        })
+        .collect();
+
+    Ok(BackupStatus {
+        channel_peers: channel_peers.len() as u32,
+        storage_peers: storage_peers.len() as u32,
+        connected_storage_peers: storage_peers.iter().filter(|p| p.connected).count() as u32,
+        last_backup_at: None,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
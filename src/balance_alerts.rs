@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// CLN's default `min-emergency-msat`: on-chain funds it refuses to spend
+/// while anchor channels are open, so their anchor outputs can always be
+/// fee-bumped. Greenlight doesn't change the default.
+const EMERGENCY_RESERVE_MSAT: u64 = 25_000_000;
+
+/// Balances below which a `LowBalance` event is emitted. Unset thresholds
+/// are not checked.
+#[derive(Clone, Debug)]
+pub struct BalanceAlertThresholds {
+    pub min_spendable_msat: Option<u64>,
+    pub min_receivable_msat: Option<u64>,
+    pub min_onchain_sat: Option<u64>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BalanceKind {
+    /// What can be sent over active channels.
+    Spendable,
+    /// What can be received over active channels.
+    Receivable,
+    /// Confirmed on-chain funds.
+    Onchain,
+}
+
+/// Tracks which thresholds are currently crossed, so every drop below a
+/// threshold is reported once instead of on every check.
+pub(crate) struct BalanceAlerts {
+    thresholds: BalanceAlertThresholds,
+    triggered: Mutex<HashSet<BalanceKind>>,
+}
+
+impl BalanceAlerts {
+    pub fn new(thresholds: BalanceAlertThresholds) -> Self {
+        BalanceAlerts {
+            thresholds,
+            triggered: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub async fn check(&self, node: gl_client::node::ClnClient, events: &EventDispatcher) {
+        let balances = match summary(node).await {
+            Ok(balances) => balances,
+            Err(e) => {
+                eprintln!("Balance alerts failed to read balances: {:?}", e);
+                return;
+            }
+        };
+
+        let checks = [
+            (
+                BalanceKind::Spendable,
+                balances.spendable_msat,
+                self.thresholds.min_spendable_msat,
+            ),
+            (
+                BalanceKind::Receivable,
+                balances.receivable_msat,
+                self.thresholds.min_receivable_msat,
+            ),
+            (
+                BalanceKind::Onchain,
+                balances.onchain_msat,
+                self.thresholds.min_onchain_sat.map(|sat| sat * 1_000),
+            ),
+        ];
+
+        for (kind, balance_msat, threshold_msat) in checks {
+            let Some(threshold_msat) = threshold_msat else {
+                continue;
+            };
+
+            let newly_triggered = {
+                let mut triggered = self.triggered.lock().unwrap();
+                if balance_msat < threshold_msat {
+                    triggered.insert(kind)
+                } else {
+                    triggered.remove(&kind);
+                    false
+                }
+            };
+
+            if newly_triggered {
+                events.emit(ClientEvent::LowBalance {
+                    kind,
+                    balance_msat,
+                    threshold_msat,
+                });
+            }
+        }
+    }
+}
+
+/// Re-checks the thresholds periodically, which catches changes the client
+/// didn't cause itself (incoming payments, channel closes, fees).
+pub(crate) async fn watch(
+    alerts: Arc<BalanceAlerts>,
+    node: gl_client::node::ClnClient,
+    events: Arc<EventDispatcher>,
+) {
+    loop {
+        alerts.check(node.clone(), &events).await;
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Lightning and on-chain balances. Channel balances already account for
+/// commitment fees and channel reserves; `onchain_reserve_msat` is what must
+/// stay on-chain to fee-bump anchor channels when they are force closed.
+#[derive(Clone, Debug)]
+pub struct BalanceSummary {
+    pub spendable_msat: u64,
+    pub receivable_msat: u64,
+    /// Confirmed on-chain funds.
+    pub onchain_msat: u64,
+    pub onchain_reserve_msat: u64,
+    /// Confirmed on-chain funds that can be withdrawn or used to open
+    /// channels without eating into the reserve.
+    pub onchain_spendable_msat: u64,
+}
+
+pub(crate) async fn summary(
+    mut node: gl_client::node::ClnClient,
+) -> anyhow::Result<BalanceSummary> {
+    let channels = node
+        .list_peer_channels(cln::ListpeerchannelsRequest::default())
+        .await
+        .context("failed to list peer channels")?
+        .into_inner()
+        .channels;
+    let outputs = node
+        .list_funds(cln::ListfundsRequest::default())
+        .await
+        .context("failed to list funds")?
+        .into_inner()
+        .outputs;
+
+    let active: Vec<_> = channels
+        .iter()
+        .filter(|c| c.state == Some(cln::ChannelState::ChanneldNormal as i32))
+        .collect();
+
+    let onchain_msat = outputs
+        .iter()
+        .filter(|o| o.status == cln::listfunds_outputs::ListfundsOutputsStatus::Confirmed as i32)
+        .filter_map(|o| o.amount_msat.as_ref())
+        .map(|a| a.msat)
+        .sum();
+    let onchain_reserve_msat = onchain_reserve_msat(&channels, onchain_msat);
+
+    Ok(BalanceSummary {
+        spendable_msat: active
+            .iter()
+            .filter_map(|c| c.spendable_msat.as_ref())
+            .map(|a| a.msat)
+            .sum(),
+        receivable_msat: active
+            .iter()
+            .filter_map(|c| c.receivable_msat.as_ref())
+            .map(|a| a.msat)
+            .sum(),
+        onchain_msat,
+        onchain_reserve_msat,
+        onchain_spendable_msat: onchain_msat - onchain_reserve_msat,
+    })
+}
+
+/// The emergency reserve CLN holds back from `onchain_msat`. Channels without
+/// anchors are force closed with fees already paid by the commitment
+/// transaction, so the reserve only applies while an anchor channel is open.
+fn onchain_reserve_msat(channels: &[cln::ListpeerchannelsChannels], onchain_msat: u64) -> u64 {
+    let anchor_channels = channels
+        .iter()
+        .filter(|c| {
+            c.state != Some(cln::ChannelState::Onchain as i32)
+                && c.state != Some(cln::ChannelState::ClosingdComplete as i32)
+        })
+        .filter(|c| {
+            c.features
+                .iter()
+                .any(|f| f == "option_anchors_zero_fee_htlc_tx" || f == "option_anchor_outputs")
+        })
+        .count();
+
+    if anchor_channels > 0 {
+        EMERGENCY_RESERVE_MSAT.min(onchain_msat)
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(state: cln::ChannelState, features: &[&str]) -> cln::ListpeerchannelsChannels {
+        cln::ListpeerchannelsChannels {
+            state: Some(state as i32),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reserve_is_held_only_for_open_anchor_channels() {
+        use cln::ChannelState;
+
+        let static_remotekey = channel(ChannelState::ChanneldNormal, &["option_static_remotekey"]);
+        let anchors = channel(
+            ChannelState::ChanneldNormal,
+            &["option_static_remotekey", "option_anchors_zero_fee_htlc_tx"],
+        );
+        let closed_anchors = channel(ChannelState::Onchain, &["option_anchors_zero_fee_htlc_tx"]);
+
+        assert_eq!(onchain_reserve_msat(&[], 100_000_000), 0);
+        assert_eq!(
+            onchain_reserve_msat(&[static_remotekey.clone()], 100_000_000),
+            0
+        );
+        assert_eq!(onchain_reserve_msat(&[closed_anchors], 100_000_000), 0);
+        assert_eq!(
+            onchain_reserve_msat(&[static_remotekey, anchors.clone()], 100_000_000),
+            EMERGENCY_RESERVE_MSAT
+        );
+        // Never more than what is on-chain.
+        assert_eq!(onchain_reserve_msat(&[anchors], 10_000_000), 10_000_000);
+    }
+}
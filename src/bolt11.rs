@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use lightning_invoice::{Currency, Invoice, InvoiceDescription};
+
+use crate::features;
+use crate::greenlight_alby_client::{Network, RouteHint, RouteHintHop};
+
+/// A BOLT11 invoice decoded without the node, e.g. while it is still being
+/// scheduled. The signature is checked against the payee locally.
+#[derive(Clone, Debug)]
+pub struct DecodedInvoice {
+    /// `None` for a currency prefix this crate doesn't know.
+    pub network: Option<Network>,
+    pub amount_msat: Option<u64>,
+    pub description: Option<String>,
+    pub description_hash: Option<String>,
+    pub payee: String,
+    pub created_at: u64,
+    /// Seconds after `created_at`.
+    pub expiry: u64,
+    pub payment_hash: String,
+    pub payment_secret: Option<String>,
+    pub min_final_cltv_expiry: u32,
+    pub features: Vec<String>,
+    pub routes: Vec<RouteHint>,
+}
+
+pub(crate) fn decode(bolt11: &str) -> anyhow::Result<DecodedInvoice> {
+    let bolt11 = bolt11.trim();
+    let bolt11 = bolt11
+        .strip_prefix("lightning:")
+        .or_else(|| bolt11.strip_prefix("LIGHTNING:"))
+        .unwrap_or(bolt11)
+        .to_lowercase();
+
+    let invoice = Invoice::from_str(&bolt11).map_err(|e| anyhow!("invalid invoice: {}", e))?;
+    // An `n` field names the payee instead of letting it be recovered from
+    // the signature, so it must be checked against the signature too.
+    invoice
+        .check_signature()
+        .map_err(|e| anyhow!("invoice is not signed by its payee: {}", e))?;
+
+    let (description, description_hash) = match invoice.description() {
+        InvoiceDescription::Direct(description) => (Some(description.clone().into_inner()), None),
+        InvoiceDescription::Hash(hash) => (None, Some(hex::encode(hash.0))),
+    };
+    let payee = invoice
+        .payee_pub_key()
+        .cloned()
+        .unwrap_or_else(|| invoice.recover_payee_pub_key());
+
+    Ok(DecodedInvoice {
+        network: match invoice.currency() {
+            Currency::Bitcoin => Some(Network::Bitcoin),
+            Currency::BitcoinTestnet => Some(Network::Testnet),
+            Currency::Signet => Some(Network::Signet),
+            Currency::Regtest => Some(Network::Regtest),
+            Currency::Simnet => None,
+        },
+        amount_msat: invoice.amount_milli_satoshis(),
+        description,
+        description_hash,
+        payee: hex::encode(payee.serialize()),
+        created_at: invoice.duration_since_epoch().as_secs(),
+        expiry: invoice.expiry_time().as_secs(),
+        payment_hash: hex::encode(invoice.payment_hash()),
+        payment_secret: Some(hex::encode(invoice.payment_secret().0)),
+        min_final_cltv_expiry: invoice.min_final_cltv_expiry_delta() as u32,
+        features: invoice
+            .features()
+            .map(|f| {
+                // `le_flags` is little-endian; the rest of the crate uses
+                // big-endian bitmaps.
+                let mut bitmap = f.le_flags().to_vec();
+                bitmap.reverse();
+                features::feature_names(&bitmap)
+            })
+            .unwrap_or_default(),
+        routes: invoice
+            .route_hints()
+            .into_iter()
+            .map(|route| RouteHint {
+                hops: route
+                    .0
+                    .iter()
+                    .map(|hop| RouteHintHop {
+                        id: hex::encode(hop.src_node_id.serialize()),
+                        short_channel_id: format!(
+                            "{}x{}x{}",
+                            hop.short_channel_id >> 40,
+                            (hop.short_channel_id >> 16) & 0xffffff,
+                            hop.short_channel_id & 0xffff
+                        ),
+                        fee_base_msat: hop.fees.base_msat as u64,
+                        fee_proportional_millionths: hop.fees.proportional_millionths,
+                        cltv_expiry_delta: hop.cltv_expiry_delta as u32,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+use crate::greenlight_alby_client::ChannelCloseReason;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Emits `ChannelClosed` once for every channel that starts closing. Channels
+/// already closing when the task starts are not reported.
+pub(crate) async fn watch(node: gl_client::node::ClnClient, events: Arc<EventDispatcher>) {
+    let mut reported = None;
+    loop {
+        if let Err(e) = poll(node.clone(), &mut reported, &events).await {
+            eprintln!("Channel close watcher failed to list channels: {:?}", e);
+        }
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Whether the channel is past the point where it could still be used.
+fn is_closing(state: Option<i32>) -> bool {
+    use cln::ChannelState;
+
+    matches!(
+        state.and_then(ChannelState::from_i32),
+        Some(
+            ChannelState::ClosingdComplete
+                | ChannelState::AwaitingUnilateral
+                | ChannelState::FundingSpendSeen
+                | ChannelState::Onchain
+        )
+    )
+}
+
+/// Emits events for channels that started closing since the last poll. The
+/// first poll only records the channels that are already closing.
+async fn poll(
+    mut node: gl_client::node::ClnClient,
+    reported: &mut Option<HashSet<Vec<u8>>>,
+    events: &EventDispatcher,
+) -> anyhow::Result<()> {
+    let closing: Vec<_> = node
+        .list_peer_channels(cln::ListpeerchannelsRequest::default())
+        .await
+        .context("failed to list peer channels")?
+        .into_inner()
+        .channels
+        .into_iter()
+        .filter(|c| is_closing(c.state))
+        .collect();
+
+    let Some(reported) = reported.as_mut() else {
+        *reported = Some(closing.into_iter().filter_map(|c| c.channel_id).collect());
+        return Ok(());
+    };
+
+    for channel in closing {
+        let Some(channel_id) = channel.channel_id else {
+            continue;
+        };
+        if !reported.insert(channel_id.clone()) {
+            continue;
+        }
+
+        let reason = ChannelCloseReason::from_status(channel.state, &channel.status);
+        events.emit(ClientEvent::ChannelClosed {
+            channel_id: hex::encode(channel_id),
+            peer_id: channel.peer_id.map(hex::encode),
+            short_channel_id: channel.short_channel_id,
+            reason,
+            // CLN's latest status line is more specific than the generic
+            // explanation, e.g. it names the error the peer sent.
+            message: channel
+                .status
+                .last()
+                .cloned()
+                .unwrap_or_else(|| reason.message().to_string()),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Rules for inbound channel opens. Greenlight nodes don't let clients
+/// install an `openchannel` hook, so channels opened by peers are checked as
+/// soon as they show up and closed again if they violate the policy.
+#[derive(Clone, Debug)]
+pub struct ChannelOpenPolicy {
+    pub min_capacity_msat: Option<u64>,
+    pub allowed_peers: Option<Vec<String>>,
+    pub allow_zero_conf: bool,
+    pub max_channels: Option<u32>,
+}
+
+impl ChannelOpenPolicy {
+    fn violation(
+        &self,
+        channel: &cln::ListpeerchannelsChannels,
+        open_channels: usize,
+    ) -> Option<String> {
+        let peer_id = channel
+            .peer_id
+            .as_ref()
+            .map(hex::encode)
+            .unwrap_or_default();
+
+        if let Some(allowed_peers) = &self.allowed_peers {
+            if !allowed_peers.contains(&peer_id) {
+                return Some(format!("peer {} is not allowed", peer_id));
+            }
+        }
+
+        if let Some(min_capacity_msat) = self.min_capacity_msat {
+            let capacity_msat = channel.total_msat.as_ref().map(|a| a.msat).unwrap_or(0);
+            if capacity_msat < min_capacity_msat {
+                return Some(format!(
+                    "capacity {} msat is below minimum of {} msat",
+                    capacity_msat, min_capacity_msat
+                ));
+            }
+        }
+
+        if !self.allow_zero_conf && channel.features.iter().any(|f| f == "option_zeroconf") {
+            return Some(String::from("zero-conf channels are not allowed"));
+        }
+
+        if let Some(max_channels) = self.max_channels {
+            if open_channels > max_channels as usize {
+                return Some(format!("maximum of {} channels reached", max_channels));
+            }
+        }
+
+        None
+    }
+}
+
+/// Watches for channels opened by peers and closes the ones the policy
+/// rejects. Channels that already exist when the task starts are left alone.
+pub(crate) async fn enforce(
+    node: gl_client::node::ClnClient,
+    policy: ChannelOpenPolicy,
+    events: Arc<EventDispatcher>,
+) {
+    let mut seen: Option<HashSet<Vec<u8>>> = None;
+
+    loop {
+        match node
+            .clone()
+            .list_peer_channels(cln::ListpeerchannelsRequest::default())
+            .await
+        {
+            Ok(response) => {
+                let channels: Vec<_> = response
+                    .into_inner()
+                    .channels
+                    .into_iter()
+                    .filter(|c| is_open(c.state))
+                    .collect();
+
+                match seen.as_mut() {
+                    None => {
+                        seen = Some(
+                            channels
+                                .iter()
+                                .filter_map(|c| c.channel_id.clone())
+                                .collect(),
+                        );
+                    }
+                    Some(seen) => {
+                        for channel in &channels {
+                            let Some(channel_id) = &channel.channel_id else {
+                                continue;
+                            };
+                            if !seen.insert(channel_id.clone())
+                                || channel.opener != cln::ChannelSide::Remote as i32
+                            {
+                                continue;
+                            }
+
+                            if let Some(reason) = policy.violation(channel, channels.len()) {
+                                reject(&node, channel, reason, &events);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("Channel policy failed to list channels: {:?}", e),
+        }
+
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn reject(
+    node: &gl_client::node::ClnClient,
+    channel: &cln::ListpeerchannelsChannels,
+    reason: String,
+    events: &EventDispatcher,
+) {
+    let channel_id = channel.channel_id.as_ref().map(hex::encode);
+
+    if let Some(id) = channel_id.clone() {
+        let mut node = node.clone();
+        // Closing waits for the close to complete, don't block the watcher.
+        tokio::spawn(async move {
+            if let Err(e) = node
+                .close(cln::CloseRequest {
+                    id,
+                    ..Default::default()
+                })
+                .await
+            {
+                eprintln!("Failed to close rejected channel: {:?}", e);
+            }
+        });
+    }
+
+    events.emit(ClientEvent::ChannelOpenRejected {
+        peer_id: channel
+            .peer_id
+            .as_ref()
+            .map(hex::encode)
+            .unwrap_or_default(),
+        channel_id,
+        reason,
+    });
+}
+
+fn is_open(state: Option<i32>) -> bool {
+    use cln::ChannelState;
+
+    [
+        ChannelState::Openingd,
+        ChannelState::ChanneldAwaitingLockin,
+        ChannelState::ChanneldNormal,
+        ChannelState::DualopendOpenInit,
+        ChannelState::DualopendAwaitingLockin,
+    ]
+    .iter()
+    .any(|s| state == Some(*s as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ChannelOpenPolicy {
+        ChannelOpenPolicy {
+            min_capacity_msat: None,
+            allowed_peers: None,
+            allow_zero_conf: true,
+            max_channels: None,
+        }
+    }
+
+    fn channel(peer: u8, capacity_msat: u64, features: &[&str]) -> cln::ListpeerchannelsChannels {
+        cln::ListpeerchannelsChannels {
+            peer_id: Some(vec![peer; 33]),
+            total_msat: Some(cln::Amount {
+                msat: capacity_msat,
+            }),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn anything_goes_without_rules() {
+        assert_eq!(
+            policy().violation(&channel(2, 0, &["option_zeroconf"]), 100),
+            None
+        );
+    }
+
+    #[test]
+    fn only_allowed_peers_may_open() {
+        let policy = ChannelOpenPolicy {
+            allowed_peers: Some(vec![hex::encode([2; 33])]),
+            ..policy()
+        };
+
+        assert_eq!(policy.violation(&channel(2, 1_000, &[]), 1), None);
+        assert_eq!(
+            policy.violation(&channel(3, 1_000, &[]), 1),
+            Some(format!("peer {} is not allowed", hex::encode([3; 33])))
+        );
+    }
+
+    #[test]
+    fn channels_must_reach_the_minimum_capacity() {
+        let policy = ChannelOpenPolicy {
+            min_capacity_msat: Some(1_000_000),
+            ..policy()
+        };
+
+        assert_eq!(policy.violation(&channel(2, 1_000_000, &[]), 1), None);
+        assert_eq!(
+            policy.violation(&channel(2, 999_999, &[]), 1),
+            Some(String::from(
+                "capacity 999999 msat is below minimum of 1000000 msat"
+            ))
+        );
+    }
+
+    #[test]
+    fn zero_conf_channels_can_be_refused() {
+        let policy = ChannelOpenPolicy {
+            allow_zero_conf: false,
+            ..policy()
+        };
+
+        assert_eq!(
+            policy.violation(&channel(2, 1_000, &["option_anchors"]), 1),
+            None
+        );
+        assert_eq!(
+            policy.violation(
+                &channel(2, 1_000, &["option_anchors", "option_zeroconf"]),
+                1
+            ),
+            Some(String::from("zero-conf channels are not allowed"))
+        );
+    }
+
+    #[test]
+    fn max_channels_counts_the_new_channel() {
+        let policy = ChannelOpenPolicy {
+            max_channels: Some(2),
+            ..policy()
+        };
+
+        assert_eq!(policy.violation(&channel(2, 1_000, &[]), 1), None);
+        assert_eq!(policy.violation(&channel(2, 1_000, &[]), 2), None);
+        assert_eq!(
+            policy.violation(&channel(2, 1_000, &[]), 3),
+            Some(String::from("maximum of 2 channels reached"))
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Emits `AddressDeposit` when an on-chain deposit to one of the node's
+/// addresses shows up and again whenever it gains a confirmation, until it
+/// has `target_confirmations`. Change from the node's own transactions is not
+/// a deposit and is skipped. Deposits already confirmed when the task starts
+/// are not reported.
+pub(crate) async fn watch(
+    node: gl_client::node::ClnClient,
+    target_confirmations: u32,
+    events: Arc<EventDispatcher>,
+) {
+    let mut reported = None;
+    loop {
+        if let Err(e) = poll(node.clone(), target_confirmations, &mut reported, &events).await {
+            eprintln!("Deposit watcher failed to list funds: {:?}", e);
+        }
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Confirmations reported so far, per outpoint.
+type Reported = HashMap<(Vec<u8>, u32), u32>;
+
+/// Emits events for deposits that are new or gained confirmations since the
+/// last poll. The first poll only records what is already confirmed.
+async fn poll(
+    mut node: gl_client::node::ClnClient,
+    target_confirmations: u32,
+    reported: &mut Option<Reported>,
+    events: &EventDispatcher,
+) -> anyhow::Result<()> {
+    let block_height = node
+        .getinfo(cln::GetinfoRequest::default())
+        .await
+        .context("failed to get block height")?
+        .into_inner()
+        .blockheight;
+
+    let outputs = node
+        .list_funds(cln::ListfundsRequest::default())
+        .await
+        .context("failed to list funds")?
+        .into_inner()
+        .outputs;
+
+    let confirmations_of = |output: &cln::ListfundsOutputs| {
+        output
+            .blockheight
+            .map_or(0, |height| block_height.saturating_sub(height) + 1)
+    };
+
+    let Some(reported) = reported.as_mut() else {
+        *reported = Some(
+            outputs
+                .iter()
+                .filter(|o| confirmations_of(o) >= target_confirmations)
+                .map(|o| ((o.txid.clone(), o.output), target_confirmations))
+                .collect(),
+        );
+        return Ok(());
+    };
+
+    let pending: Vec<_> = outputs
+        .into_iter()
+        .filter(|o| {
+            let previous = reported.get(&(o.txid.clone(), o.output));
+            previous.map_or(true, |c| *c < confirmations_of(o).min(target_confirmations))
+        })
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let own = own_transactions(node).await?;
+    for output in pending {
+        let confirmations = confirmations_of(&output).min(target_confirmations);
+        reported.insert((output.txid.clone(), output.output), confirmations);
+        if own.contains(&output.txid) {
+            continue;
+        }
+
+        events.emit(ClientEvent::AddressDeposit {
+            txid: hex::encode(&output.txid),
+            output: output.output,
+            address: output.address,
+            amount_msat: output.amount_msat.map(|a| a.msat),
+            confirmations,
+        });
+    }
+    Ok(())
+}
+
+/// Ids of the wallet transactions that spend the node's own outputs, i.e.
+/// the ones whose outputs are change rather than deposits.
+async fn own_transactions(
+    mut node: gl_client::node::ClnClient,
+) -> anyhow::Result<HashSet<Vec<u8>>> {
+    let transactions = node
+        .list_transactions(cln::ListtransactionsRequest::default())
+        .await
+        .context("failed to list transactions")?
+        .into_inner()
+        .transactions;
+
+    let txids: HashSet<Vec<u8>> = transactions.iter().map(|tx| tx.hash.clone()).collect();
+    Ok(transactions
+        .into_iter()
+        .filter(|tx| tx.inputs.iter().any(|input| txids.contains(&input.txid)))
+        .map(|tx| tx.hash)
+        .collect())
+}
@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use crate::balance_alerts::BalanceKind;
+use crate::greenlight_alby_client::{ChannelCloseReason, ListInvoicesInvoice, ListPaymentsPayment};
+
+#[derive(Clone, Debug)]
+pub enum ClientEvent {
+    ChannelOpenRejected {
+        peer_id: String,
+        channel_id: Option<String>,
+        reason: String,
+    },
+    KeysendReceived {
+        label: String,
+        payment_hash: String,
+        amount_msat: Option<u64>,
+        message: Option<String>,
+    },
+    RequestCompleted {
+        request_id: String,
+        method: String,
+        duration_ms: u64,
+        error: Option<String>,
+    },
+    SignerRestarted {
+        attempt: u32,
+        delay_ms: u64,
+        error: String,
+    },
+    SignerFailed {
+        error: String,
+    },
+    InvoicePaid {
+        invoice: ListInvoicesInvoice,
+        updated_index: u64,
+    },
+    QueuedPaymentSucceeded {
+        id: u64,
+        bolt11: String,
+        preimage: String,
+    },
+    QueuedPaymentFailed {
+        id: u64,
+        bolt11: String,
+        error: String,
+    },
+    BackupStale {
+        last_backup_at: Option<u64>,
+        storage_peers: u32,
+    },
+    LowBalance {
+        kind: BalanceKind,
+        balance_msat: u64,
+        threshold_msat: u64,
+    },
+    /// The node paid the invoice a holder answered our invoice request
+    /// `invreq_id` with, i.e. a BOLT12 refund went out.
+    RefundPaid {
+        invreq_id: String,
+        label: Option<String>,
+        payment: ListPaymentsPayment,
+    },
+    /// An on-chain deposit to one of the node's addresses appeared or gained
+    /// a confirmation.
+    AddressDeposit {
+        txid: String,
+        output: u32,
+        address: Option<String>,
+        amount_msat: Option<u64>,
+        confirmations: u32,
+    },
+    /// A channel started closing. `message` explains `reason` to users.
+    ChannelClosed {
+        channel_id: String,
+        peer_id: Option<String>,
+        short_channel_id: Option<String>,
+        reason: ChannelCloseReason,
+        message: String,
+    },
+    /// An LSP sent a request or notification over LSPS0. `params` is JSON.
+    Lsps0Notification {
+        peer_id: String,
+        method: String,
+        params: String,
+    },
+    /// Greenlight moved the node to a new CLN version since the last session.
+    NodeUpgraded {
+        previous_version: String,
+        version: String,
+    },
+}
+
+/// Host-implemented receiver for events emitted by the client's background
+/// tasks. Called from the client's runtime, so implementations should return
+/// quickly.
+pub trait EventListener: Send + Sync {
+    fn on_event(&self, event: ClientEvent);
+}
+
+#[derive(Default)]
+pub(crate) struct EventDispatcher {
+    listeners: Mutex<Vec<Arc<dyn EventListener>>>,
+}
+
+impl EventDispatcher {
+    pub fn subscribe(&self, listener: Box<dyn EventListener>) {
+        self.listeners.lock().unwrap().push(Arc::from(listener));
+    }
+
+    pub fn emit(&self, event: ClientEvent) {
+        // Don't hold the lock while calling into the host.
+        let listeners = self.listeners.lock().unwrap().clone();
+        for listener in listeners {
+            listener.on_event(event.clone());
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// Names of the feature pairs defined in BOLT 9. Bit `2n` of a pair means the
+/// feature is required, bit `2n + 1` that it is supported.
+const KNOWN_FEATURES: &[(u32, &str)] = &[
+    (0, "option_data_loss_protect"),
+    (2, "initial_routing_sync"),
+    (4, "option_upfront_shutdown_script"),
+    (6, "gossip_queries"),
+    (8, "var_onion_optin"),
+    (10, "gossip_queries_ex"),
+    (12, "option_static_remotekey"),
+    (14, "payment_secret"),
+    (16, "basic_mpp"),
+    (18, "option_support_large_channel"),
+    (20, "option_anchor_outputs"),
+    (22, "option_anchors_zero_fee_htlc_tx"),
+    (24, "option_route_blinding"),
+    (26, "option_shutdown_anysegwit"),
+    (28, "option_dual_fund"),
+    (34, "option_quiesce"),
+    (38, "option_onion_messages"),
+    (42, "option_provide_storage"),
+    (44, "option_channel_type"),
+    (46, "option_scid_alias"),
+    (48, "option_payment_metadata"),
+    (50, "option_zeroconf"),
+    (62, "option_splice"),
+];
+
+/// Decodes a big-endian feature bitmap into the names of the features that
+/// are set, in bit order. Bits without a known name are reported as
+/// `unknown_<bit>`.
+pub(crate) fn feature_names(bitmap: &[u8]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (i, byte) in bitmap.iter().rev().enumerate() {
+        for shift in 0..8 {
+            if byte & (1 << shift) == 0 {
+                continue;
+            }
+            let bit = (i * 8 + shift) as u32;
+            let name = KNOWN_FEATURES
+                .iter()
+                .find(|(pair, _)| bit & !1 == *pair)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format!("unknown_{}", bit));
+            // Both bits of a pair may be set; report the feature once.
+            if names.last() != Some(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Capabilities hosts may want to gate UI on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Splicing,
+    Bolt12,
+    Renepay,
+    Askrene,
+}
+
+/// Whether a node running CLN `release` (e.g. `24.11`) with the given node
+/// feature bitmap supports `feature`. Protocol features are read from the
+/// bitmap, plugins are assumed present from the release that shipped them.
+pub(crate) fn supports(feature: Feature, release: &str, node_features: &[u8]) -> bool {
+    let names = feature_names(node_features);
+    let has = |name: &str| names.iter().any(|n| n == name);
+    match feature {
+        Feature::Splicing => has("option_splice"),
+        Feature::Bolt12 => has("option_onion_messages"),
+        Feature::Renepay => release_at_least(release, (23, 8)),
+        Feature::Askrene => release_at_least(release, (24, 11)),
+    }
+}
+
+fn release_at_least(release: &str, min: (u32, u32)) -> bool {
+    let mut parts = release
+        .split('.')
+        .map(|p| p.parse::<u32>().unwrap_or_default());
+    let major = parts.next().unwrap_or_default();
+    let minor = parts.next().unwrap_or_default();
+    (major, minor) >= min
+}
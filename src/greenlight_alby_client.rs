@@ -1,46 +1,370 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 use bip39::Mnemonic;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 use tokio::sync::mpsc::Sender;
-use tokio::task::JoinHandle;
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time;
+use zeroize::Zeroizing;
 
-use gl_client::bitcoin::Network;
-use gl_client::credentials::Nobody;
+use gl_client::bitcoin;
+use gl_client::credentials::{Device, Nobody};
+use gl_client::node::Node;
 use gl_client::pb::cln;
 use gl_client::scheduler::Scheduler;
 use gl_client::signer::model::greenlight::scheduler;
 use gl_client::signer::Signer;
 
+use crate::address_history;
+use crate::backup_monitor::{self, BackupMonitor, BackupStatus};
+use crate::balance_alerts::{self, BalanceAlertThresholds, BalanceAlerts, BalanceSummary};
+use crate::bolt11::{self, DecodedInvoice};
+use crate::channel_closes;
+use crate::channel_policy::{self, ChannelOpenPolicy};
+use crate::deposits;
+use crate::events::{ClientEvent, EventDispatcher, EventListener};
+use crate::features::{self, Feature};
+use crate::invoice_events::{self, InvoiceListener};
+use crate::journal::{Journal, JournalOperation, OperationResolution, PendingOperation};
+use crate::keysend_messages;
+use crate::lsps0::{self, Lsps0Transport};
+use crate::mnemonic::{self, MnemonicValidation};
+use crate::node_version;
+use crate::payment_queue::{self, PaymentQueue, QueuedPayment};
+use crate::recovery::{CancellationToken, RecoverProgress, RecoverStage};
+use crate::refunds::{self, Refund};
+use crate::session_cache;
+use crate::signer_supervisor;
+use crate::snapshot_cache::{self, CachedSnapshot, SnapshotStore};
+use crate::state_export::{self, DatastoreEntry, WalletState};
+use crate::storage::{MemoryStorage, Storage, StorageSnapshotStore};
+use crate::tls;
+
 #[derive(Error, Clone, Debug)]
 pub enum SdkError {
-    #[error("invalid argument: {0}")]
-    InvalidArgument(String),
-
-    #[error("greenlight API error: {0}")]
-    GreenlightApi(String),
+    #[error("invalid argument: {message}")]
+    InvalidArgument {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("greenlight API error: {message}")]
+    GreenlightApi {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("incompatible signer version {signer_version}, node is running {node_version}")]
+    IncompatibleVersion {
+        signer_version: String,
+        node_version: String,
+        request_id: Option<String>,
+    },
+
+    #[error("storage error: {message}")]
+    Storage {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("amount of {amount_msat} msat exceeds the {max_sendable_msat} msat a single channel can send")]
+    ExceedsMaxSendable {
+        amount_msat: u64,
+        max_sendable_msat: u64,
+        request_id: Option<String>,
+    },
+
+    #[error("node not found: {message}")]
+    NodeNotFound {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("rate limited by the scheduler: {message}")]
+    RateLimited {
+        message: String,
+        retry_after_secs: Option<u64>,
+        request_id: Option<String>,
+    },
+
+    #[error("Greenlight is under maintenance: {message}")]
+    Maintenance {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("invalid invite code: {message}")]
+    InviteInvalid {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("certificate expired: {message}")]
+    CertificateExpired {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    #[error("cancelled")]
+    Cancelled { request_id: Option<String> },
+
+    #[error("wallet is locked")]
+    WalletLocked { request_id: Option<String> },
+
+    #[error("payment {payment_hash} is still pending")]
+    PaymentPending {
+        payment_hash: String,
+        request_id: Option<String>,
+    },
+
+    #[error("invoice amount of {amount_msat} msat is below the minimum of {min_amount_msat} msat")]
+    InvoiceAmountBelowMinimum {
+        amount_msat: u64,
+        min_amount_msat: u64,
+        request_id: Option<String>,
+    },
+
+    #[error("invoice amount of {amount_msat} msat is above the maximum of {max_amount_msat} msat")]
+    InvoiceAmountAboveMaximum {
+        amount_msat: u64,
+        max_amount_msat: u64,
+        request_id: Option<String>,
+    },
+
+    #[error(
+        "invoice expiry of {expiry_secs} seconds is above the maximum of {max_expiry_secs} seconds"
+    )]
+    InvoiceExpiryTooLong {
+        expiry_secs: u64,
+        max_expiry_secs: u64,
+        request_id: Option<String>,
+    },
+
+    #[error("insufficient funds: {message}")]
+    InsufficientFunds {
+        message: String,
+        amount_msat: Option<u64>,
+        available_msat: Option<u64>,
+        request_id: Option<String>,
+    },
+
+    #[error("amount is below the minimum HTLC: {message}")]
+    AmountBelowMinimumHtlc {
+        message: String,
+        amount_msat: Option<u64>,
+        min_htlc_msat: Option<u64>,
+        request_id: Option<String>,
+    },
+
+    #[error("amount exceeds the channel capacity: {message}")]
+    ExceedsChannelCapacity {
+        message: String,
+        amount_msat: Option<u64>,
+        max_msat: Option<u64>,
+        request_id: Option<String>,
+    },
+
+    #[error("fee budget exceeded: {message}")]
+    FeeBudgetExceeded {
+        message: String,
+        fee_msat: Option<u64>,
+        max_fee_msat: Option<u64>,
+        request_id: Option<String>,
+    },
     // #[error("other error: {0}")]
     // Other(String),
 }
 
 impl SdkError {
     fn invalid_arg(e: anyhow::Error) -> Self {
-        SdkError::InvalidArgument(Self::format_anyhow_error(e))
+        SdkError::InvalidArgument {
+            message: Self::format_anyhow_error(e),
+            request_id: None,
+        }
     }
 
     fn greenlight_api(e: anyhow::Error) -> Self {
-        SdkError::GreenlightApi(Self::format_anyhow_error(e))
+        SdkError::GreenlightApi {
+            message: Self::format_anyhow_error(e),
+            request_id: None,
+        }
+    }
+
+    fn storage(e: anyhow::Error) -> Self {
+        SdkError::Storage {
+            message: Self::format_anyhow_error(e),
+            request_id: None,
+        }
+    }
+
+    /// Classifies a failed scheduler call by the gRPC status somewhere in
+    /// its error chain; anything without a recognised status stays a
+    /// `GreenlightApi` error.
+    fn scheduler(e: anyhow::Error) -> Self {
+        let Some((code, retry_after_secs, from_server)) = grpc_status(&e) else {
+            return Self::greenlight_api(e);
+        };
+        let message = Self::format_anyhow_error(e);
+
+        match code {
+            tonic::Code::ResourceExhausted => SdkError::RateLimited {
+                message,
+                retry_after_secs,
+                request_id: None,
+            },
+            tonic::Code::NotFound => SdkError::NodeNotFound {
+                message,
+                request_id: None,
+            },
+            tonic::Code::Unauthenticated => SdkError::CertificateExpired {
+                message,
+                request_id: None,
+            },
+            // A transport failure is reported as `Unavailable` too; only the
+            // scheduler itself saying so means maintenance.
+            tonic::Code::Unavailable if from_server => SdkError::Maintenance {
+                message,
+                request_id: None,
+            },
+            _ => SdkError::GreenlightApi {
+                message,
+                request_id: None,
+            },
+        }
+    }
+
+    /// Like `scheduler`, but the scheduler rejecting the request itself
+    /// means the invite code was not accepted.
+    fn registration(e: anyhow::Error) -> Self {
+        match grpc_status(&e) {
+            Some((
+                tonic::Code::InvalidArgument
+                | tonic::Code::PermissionDenied
+                | tonic::Code::FailedPrecondition,
+                _,
+                _,
+            )) => SdkError::InviteInvalid {
+                message: Self::format_anyhow_error(e),
+                request_id: None,
+            },
+            _ => Self::scheduler(e),
+        }
+    }
+
+    /// Classifies a failed pay, withdraw or fundchannel call by the node's
+    /// error message. Each message CLN sends for these failures has its own
+    /// shape, so the amounts are read from the position that message puts
+    /// them in; `amount_msat` is what the caller asked for, used when the
+    /// message doesn't say.
+    fn funds(e: anyhow::Error, amount_msat: Option<u64>) -> Self {
+        let message = Self::format_anyhow_error(e);
+        let lowercase = message.to_lowercase();
+
+        if lowercase.contains("could not afford") {
+            // "Could not afford 100000sat using all 1 available UTXOs: 99830sat short"
+            // "Could not afford 100000sat using UTXOs totalling 170sat with weight 272 at feerate 253"
+            let amount_msat = amount_after(&lowercase, "could not afford ").or(amount_msat);
+            let available_msat = match amount_after(&lowercase, "available utxos: ") {
+                Some(short_msat) => amount_msat.and_then(|amount| amount.checked_sub(short_msat)),
+                None => amount_after(&lowercase, "utxos totalling "),
+            };
+            SdkError::InsufficientFunds {
+                message,
+                amount_msat,
+                available_msat,
+                request_id: None,
+            }
+        } else if lowercase.contains("insufficient funds") || lowercase.contains("not enough funds")
+        {
+            SdkError::InsufficientFunds {
+                message,
+                amount_msat,
+                available_msat: None,
+                request_id: None,
+            }
+        } else if lowercase.contains("fee exceeds our fee budget") {
+            // "Fee exceeds our fee budget: 1200msat > 1000msat, discarding route"
+            SdkError::FeeBudgetExceeded {
+                message,
+                fee_msat: amount_after(&lowercase, "fee budget: "),
+                max_fee_msat: amount_after(&lowercase, " > "),
+                request_id: None,
+            }
+        } else if lowercase.contains("htlc too small") {
+            // "HTLC too small (1000msat minimum)"
+            SdkError::AmountBelowMinimumHtlc {
+                message,
+                amount_msat,
+                min_htlc_msat: amount_after(&lowercase, "htlc too small ("),
+                request_id: None,
+            }
+        } else if lowercase.contains("amount exceeded") {
+            // "Amount exceeded 16777215sat"
+            SdkError::ExceedsChannelCapacity {
+                message,
+                amount_msat,
+                max_msat: amount_after(&lowercase, "amount exceeded "),
+                request_id: None,
+            }
+        } else if lowercase.contains("capacity exceeded")
+            || lowercase.contains("max htlc value exceeded")
+        {
+            SdkError::ExceedsChannelCapacity {
+                message,
+                amount_msat,
+                max_msat: None,
+                request_id: None,
+            }
+        } else {
+            SdkError::GreenlightApi {
+                message,
+                request_id: None,
+            }
+        }
     }
 
     // fn other(e: anyhow::Error) -> Self {
     //     SdkError::Other(Self::format_anyhow_error(e))
     // }
 
+    fn with_request_id(mut self, request_id: &str) -> Self {
+        match &mut self {
+            SdkError::InvalidArgument { request_id: id, .. }
+            | SdkError::GreenlightApi { request_id: id, .. }
+            | SdkError::IncompatibleVersion { request_id: id, .. }
+            | SdkError::Storage { request_id: id, .. }
+            | SdkError::ExceedsMaxSendable { request_id: id, .. }
+            | SdkError::NodeNotFound { request_id: id, .. }
+            | SdkError::RateLimited { request_id: id, .. }
+            | SdkError::Maintenance { request_id: id, .. }
+            | SdkError::InviteInvalid { request_id: id, .. }
+            | SdkError::CertificateExpired { request_id: id, .. }
+            | SdkError::Cancelled { request_id: id }
+            | SdkError::WalletLocked { request_id: id }
+            | SdkError::PaymentPending { request_id: id, .. }
+            | SdkError::InvoiceAmountBelowMinimum { request_id: id, .. }
+            | SdkError::InvoiceAmountAboveMaximum { request_id: id, .. }
+            | SdkError::InvoiceExpiryTooLong { request_id: id, .. }
+            | SdkError::InsufficientFunds { request_id: id, .. }
+            | SdkError::AmountBelowMinimumHtlc { request_id: id, .. }
+            | SdkError::ExceedsChannelCapacity { request_id: id, .. }
+            | SdkError::FeeBudgetExceeded { request_id: id, .. } => {
+                *id = Some(request_id.to_string())
+            }
+        }
+        self
+    }
+
     fn format_anyhow_error(e: anyhow::Error) -> String {
         // Use alternate format (:#) to get the full error chain.
         format!("{:#}", e)
@@ -49,6 +373,178 @@ impl SdkError {
 
 pub type Result<T> = std::result::Result<T, SdkError>;
 
+/// The code of the gRPC status in `e`'s chain, the `retry-after` delay it
+/// carries and whether the status came from the server rather than from a
+/// failed transport.
+fn grpc_status(e: &anyhow::Error) -> Option<(tonic::Code, Option<u64>, bool)> {
+    let status = e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<tonic::Status>())?;
+    let retry_after_secs = status
+        .metadata()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+    let from_server = std::error::Error::source(status).is_none();
+    Some((status.code(), retry_after_secs, from_server))
+}
+
+/// The JSON-RPC error a node call failed with.
+#[derive(Deserialize)]
+struct RpcError {
+    code: Option<i64>,
+    data: Option<serde_json::Value>,
+}
+
+/// The JSON-RPC error in `e`'s chain. The node returns the error object in
+/// the status details, or as JSON in the status message when the details
+/// are empty.
+fn rpc_error(e: &anyhow::Error) -> Option<RpcError> {
+    let status = e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<tonic::Status>())?;
+    if status.details().is_empty() {
+        let message = status.message();
+        serde_json::Deserializer::from_str(&message[message.find('{')?..])
+            .into_iter()
+            .next()?
+            .ok()
+    } else {
+        serde_json::from_slice(status.details()).ok()
+    }
+}
+
+/// CLN's error code for `waitanyinvoice` and `waitinvoice` running into
+/// their timeout.
+const INVOICE_WAIT_TIMED_OUT: i64 = 904;
+
+/// Whether `e` is a wait for invoices that timed out, either on the node or
+/// at the gRPC deadline.
+pub(crate) fn is_wait_timeout(e: &anyhow::Error) -> bool {
+    grpc_status(e).is_some_and(|(code, ..)| code == tonic::Code::DeadlineExceeded)
+        || rpc_error(e).and_then(|error| error.code) == Some(INVOICE_WAIT_TIMED_OUT)
+}
+
+/// The `<n>msat` or `<n>sat` amount right after `marker` in a node error
+/// message, in msat.
+fn amount_after(message: &str, marker: &str) -> Option<u64> {
+    let rest = &message[message.find(marker)? + marker.len()..];
+    let digits_end = rest.find(|c: char| !c.is_ascii_digit())?;
+    let value: u64 = rest[..digits_end].parse().ok()?;
+    let unit = &rest[digits_end..];
+    if unit.starts_with("msat") {
+        Some(value)
+    } else if unit.starts_with("sat") {
+        value.checked_mul(1000)
+    } else {
+        None
+    }
+}
+
+tokio::task_local! {
+    /// The id of the call being traced, see `GreenlightAlbyClient::traced`.
+    static REQUEST_ID: String;
+}
+
+/// Metadata key the request id is sent to Greenlight under.
+const REQUEST_ID_HEADER: &str = "glalby-request-id";
+
+fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Wraps an RPC message so the node sees the id of the call it belongs to.
+fn traced_request<T>(message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    if let Some(id) = current_request_id().and_then(|id| id.parse().ok()) {
+        request.metadata_mut().insert(REQUEST_ID_HEADER, id);
+    }
+    request
+}
+
+/// A response that carries the id of the call it answers, set by
+/// `GreenlightAlbyClient::traced_response`.
+pub(crate) trait TracedResponse {
+    fn request_id(&mut self) -> &mut Option<String>;
+}
+
+impl TracedResponse for MakeInvoiceResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for PayResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for KeySendResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for FundChannelResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for MultiFundChannelResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for WithdrawResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for CloseResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+impl TracedResponse for SendPayResponse {
+    fn request_id(&mut self) -> &mut Option<String> {
+        &mut self.request_id
+    }
+}
+
+/// Stands in for invoice labels in privacy mode; labels can't be omitted.
+const REDACTED: &str = "redacted";
+
+/// How long `resume_payment` waits for a pending payment to resolve before
+/// reporting it as still pending.
+const RESUME_TIMEOUT: Duration = Duration::from_secs(60);
+const RESUME_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The Bitcoin network a Greenlight node runs on. Credentials don't record
+/// it, so the same network has to be passed to every constructor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl From<Network> for bitcoin::Network {
+    fn from(network: Network) -> Self {
+        match network {
+            Network::Bitcoin => bitcoin::Network::Bitcoin,
+            Network::Testnet => bitcoin::Network::Testnet,
+            Network::Signet => bitcoin::Network::Signet,
+            Network::Regtest => bitcoin::Network::Regtest,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GreenlightCredentials {
     pub gl_creds: String,
@@ -70,7 +566,14 @@ impl From<scheduler::RegistrationResponse> for GreenlightCredentials {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Host-provided persistence for Greenlight credentials. The library saves
+/// credentials whenever it creates or rotates them.
+pub trait CredentialStore: Send + Sync {
+    fn save(&self, credentials: GreenlightCredentials);
+    fn load(&self) -> Option<GreenlightCredentials>;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetInfoResponse {
     pub pubkey: String,
     pub alias: String,
@@ -93,8 +596,19 @@ impl From<cln::GetinfoResponse> for GetInfoResponse {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+pub enum ShutdownOutcome {
+    /// The signer stopped by itself after being asked to.
+    CleanShutdown,
+    /// The signer didn't stop in time and its task was aborted.
+    Aborted,
+}
+
 #[derive(Clone, Debug)]
-pub struct ShutdownResponse {}
+pub struct ShutdownResponse {
+    pub outcome: ShutdownOutcome,
+    pub duration_ms: u64,
+}
 
 #[derive(Clone, Debug)]
 pub struct MakeInvoiceRequest {
@@ -106,12 +620,148 @@ pub struct MakeInvoiceRequest {
     pub preimage: Option<String>,
     pub cltv: Option<u32>,
     pub deschashonly: Option<bool>,
+    /// Cut a description that doesn't fit in a bolt11 invoice short instead
+    /// of failing.
+    pub truncate_description: bool,
+}
+
+/// A bolt11 tagged field holds at most 1023 5-bit words.
+const MAX_BOLT11_DESCRIPTION_BYTES: usize = 639;
+const ELLIPSIS: &str = "\u{2026}";
+
+impl MakeInvoiceRequest {
+    /// Strips control characters from a description that goes into the
+    /// invoice and, if allowed, truncates it to fit. Returns a warning when
+    /// the description was truncated.
+    fn fit_description(&mut self) -> Option<String> {
+        if self.deschashonly == Some(true) {
+            return None;
+        }
+
+        self.description.retain(|c| c == '\n' || !c.is_control());
+        if !self.truncate_description || self.description.len() <= MAX_BOLT11_DESCRIPTION_BYTES {
+            return None;
+        }
+
+        let original_len = self.description.len();
+        let mut end = MAX_BOLT11_DESCRIPTION_BYTES - ELLIPSIS.len();
+        while !self.description.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Back off to the start of the character cluster being cut, so an
+        // emoji sequence or accented letter isn't left half there.
+        while let Some(before) = self.description[..end].chars().next_back() {
+            let after = self.description[end..].chars().next();
+            if !joins_next(before) && !after.is_some_and(joins_previous) {
+                break;
+            }
+            end -= before.len_utf8();
+        }
+
+        self.description.truncate(end);
+        self.description.push_str(ELLIPSIS);
+        Some(format!(
+            "description truncated from {} to {} bytes to fit the invoice",
+            original_len,
+            self.description.len()
+        ))
+    }
+}
+
+fn joins_next(c: char) -> bool {
+    c == '\u{200d}'
+}
+
+fn joins_previous(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200d}'
+            | '\u{fe00}'..='\u{fe0f}'
+            | '\u{0300}'..='\u{036f}'
+            | '\u{1f3fb}'..='\u{1f3ff}'
+            | '\u{e0020}'..='\u{e007f}'
+    )
+}
+
+/// Business limits applied to every invoice and offer the client creates,
+/// so they hold across all apps sharing a node. Amountless invoices and
+/// offers leave the amount to the payer and aren't bound by the amount
+/// limits.
+#[derive(Clone, Debug)]
+pub struct InvoiceLimits {
+    pub min_amount_msat: Option<u64>,
+    pub max_amount_msat: Option<u64>,
+    /// Also caps the expiry of invoices and offers that don't set one, which
+    /// would get CLN's default of a week, or never expire for offers,
+    /// otherwise.
+    pub max_expiry_secs: Option<u64>,
+}
+
+/// What CLN uses for invoices without an expiry.
+const DEFAULT_INVOICE_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+
+impl InvoiceLimits {
+    /// Checks `amount_msat` and `expiry_secs`, the seconds until the invoice
+    /// or offer expires, and caps an unset expiry whose default, `None` for
+    /// never, would exceed the limit.
+    fn apply(
+        &self,
+        amount_msat: Option<u64>,
+        expiry_secs: &mut Option<u64>,
+        default_expiry_secs: Option<u64>,
+    ) -> Result<()> {
+        if let (Some(amount_msat), Some(min_amount_msat)) = (amount_msat, self.min_amount_msat) {
+            if amount_msat < min_amount_msat {
+                return Err(SdkError::InvoiceAmountBelowMinimum {
+                    amount_msat,
+                    min_amount_msat,
+                    request_id: None,
+                });
+            }
+        }
+        if let (Some(amount_msat), Some(max_amount_msat)) = (amount_msat, self.max_amount_msat) {
+            if amount_msat > max_amount_msat {
+                return Err(SdkError::InvoiceAmountAboveMaximum {
+                    amount_msat,
+                    max_amount_msat,
+                    request_id: None,
+                });
+            }
+        }
+        if let Some(max_expiry_secs) = self.max_expiry_secs {
+            match *expiry_secs {
+                Some(expiry) if expiry > max_expiry_secs => {
+                    return Err(SdkError::InvoiceExpiryTooLong {
+                        expiry_secs: expiry,
+                        max_expiry_secs,
+                        request_id: None,
+                    })
+                }
+                None if default_expiry_secs.map_or(true, |d| d > max_expiry_secs) => {
+                    *expiry_secs = Some(max_expiry_secs)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<MakeInvoiceRequest> for cln::InvoiceRequest {
     type Error = SdkError;
 
     fn try_from(req: MakeInvoiceRequest) -> Result<Self> {
+        if req.deschashonly != Some(true) && req.description.len() > MAX_BOLT11_DESCRIPTION_BYTES {
+            return Err(SdkError::InvalidArgument {
+                message: format!(
+                    "description is {} bytes but an invoice fits at most {}",
+                    req.description.len(),
+                    MAX_BOLT11_DESCRIPTION_BYTES
+                ),
+                request_id: None,
+            });
+        }
+
         Ok(cln::InvoiceRequest {
             label: req.label,
             amount_msat: Some(cln::AmountOrAny {
@@ -134,6 +784,25 @@ impl TryFrom<MakeInvoiceRequest> for cln::InvoiceRequest {
     }
 }
 
+/// Why the node thinks an invoice may be hard to pay, with its explanation,
+/// or how the invoice differs from what was asked for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvoiceWarning {
+    /// Not enough incoming capacity for the amount.
+    Capacity { message: String },
+    /// Enough capacity, but only with peers that are offline.
+    Offline { message: String },
+    /// Enough capacity, but only with peers that have no other channels.
+    DeadEnds { message: String },
+    /// Enough capacity, but only through private channels left out of the
+    /// route hints.
+    PrivateUnused { message: String },
+    /// Only payable by splitting it into several parts.
+    Mpp { message: String },
+    /// The description was cut short to fit the invoice.
+    Truncated { message: String },
+}
+
 #[derive(Clone, Debug)]
 pub struct MakeInvoiceResponse {
     pub bolt11: String,
@@ -141,11 +810,8 @@ pub struct MakeInvoiceResponse {
     pub payment_secret: String,
     pub expires_at: u64,
     pub created_index: Option<u64>,
-    pub warning_capacity: Option<String>,
-    pub warning_offline: Option<String>,
-    pub warning_deadends: Option<String>,
-    pub warning_private_unused: Option<String>,
-    pub warning_mpp: Option<String>,
+    pub warnings: Vec<InvoiceWarning>,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::InvoiceResponse> for MakeInvoiceResponse {
@@ -156,38 +822,149 @@ impl From<cln::InvoiceResponse> for MakeInvoiceResponse {
             payment_secret: hex::encode(invoice.payment_secret),
             expires_at: invoice.expires_at,
             created_index: invoice.created_index,
-            warning_capacity: invoice.warning_capacity,
-            warning_offline: invoice.warning_offline,
-            warning_deadends: invoice.warning_deadends,
-            warning_private_unused: invoice.warning_private_unused,
-            warning_mpp: invoice.warning_mpp,
+            warnings: [
+                invoice
+                    .warning_capacity
+                    .map(|message| InvoiceWarning::Capacity { message }),
+                invoice
+                    .warning_offline
+                    .map(|message| InvoiceWarning::Offline { message }),
+                invoice
+                    .warning_deadends
+                    .map(|message| InvoiceWarning::DeadEnds { message }),
+                invoice
+                    .warning_private_unused
+                    .map(|message| InvoiceWarning::PrivateUnused { message }),
+                invoice
+                    .warning_mpp
+                    .map(|message| InvoiceWarning::Mpp { message }),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            request_id: None,
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct PayRequest {
+    /// A bolt11 invoice, or a bolt12 invoice from `fetch_invoice`.
     pub bolt11: String,
+    /// The full description of an invoice that only commits to its hash,
+    /// e.g. the LNURL metadata and payer data of a LUD-18 invoice. The
+    /// invoice's `payment_metadata` is sent by the node without this.
+    pub description: Option<String>,
+    /// Only for invoices that don't specify an amount.
+    pub amount_msat: Option<u64>,
+    /// Upper bound on the fee. Can't be combined with `maxfeepercent`.
+    pub maxfee_msat: Option<u64>,
+    pub maxfeepercent: Option<f64>,
+    /// Fees below this are accepted regardless of `maxfeepercent`.
+    pub exemptfee_msat: Option<u64>,
+    /// Seconds to keep retrying before giving up.
+    pub retry_for: Option<u32>,
+    /// Upper bound on the CLTV delay, in blocks.
+    pub maxdelay: Option<u32>,
+    pub label: Option<String>,
 }
 
 impl From<PayRequest> for cln::PayRequest {
     fn from(req: PayRequest) -> Self {
         cln::PayRequest {
             bolt11: req.bolt11,
+            description: req.description,
+            amount_msat: req.amount_msat.map(|msat| cln::Amount { msat }),
+            maxfee: req.maxfee_msat.map(|msat| cln::Amount { msat }),
+            maxfeepercent: req.maxfeepercent,
+            exemptfee: req.exemptfee_msat.map(|msat| cln::Amount { msat }),
+            retry_for: req.retry_for,
+            maxdelay: req.maxdelay,
+            label: req.label,
             ..Default::default()
         }
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PayStatus {
+    Complete,
+    Pending,
+    Failed,
+}
+
+impl PayStatus {
+    fn from_i32(status: i32) -> Option<Self> {
+        use cln::pay_response::PayStatus as ClnPayStatus;
+
+        match ClnPayStatus::from_i32(status)? {
+            ClnPayStatus::Complete => Some(PayStatus::Complete),
+            ClnPayStatus::Pending => Some(PayStatus::Pending),
+            ClnPayStatus::Failed => Some(PayStatus::Failed),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PayResponse {
     pub preimage: String,
+    pub payment_hash: String,
+    pub status: Option<PayStatus>,
+    pub destination: Option<String>,
+    /// What the payee receives.
+    pub amount_msat: Option<u64>,
+    /// What left the node, fees included.
+    pub amount_sent_msat: Option<u64>,
+    /// Number of HTLCs the payment was split into.
+    pub parts: u32,
+    pub created_at: u64,
+    pub invreq_payer_note: Option<String>,
+    pub offer_description: Option<String>,
+    pub offer_issuer: Option<String>,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::PayResponse> for PayResponse {
     fn from(pay: cln::PayResponse) -> Self {
         PayResponse {
             preimage: hex::encode(pay.payment_preimage),
+            payment_hash: hex::encode(pay.payment_hash),
+            status: PayStatus::from_i32(pay.status),
+            destination: pay.destination.map(hex::encode),
+            amount_msat: pay.amount_msat.map(|a| a.msat),
+            amount_sent_msat: pay.amount_sent_msat.map(|a| a.msat),
+            parts: pay.parts,
+            created_at: pay.created_at as u64,
+            invreq_payer_note: None,
+            offer_description: None,
+            offer_issuer: None,
+            request_id: None,
+        }
+    }
+}
+
+impl From<cln::ListpaysPays> for PayResponse {
+    fn from(pay: cln::ListpaysPays) -> Self {
+        use cln::listpays_pays::ListpaysPaysStatus;
+
+        PayResponse {
+            preimage: pay.preimage.map(hex::encode).unwrap_or_default(),
+            payment_hash: hex::encode(pay.payment_hash),
+            status: match ListpaysPaysStatus::from_i32(pay.status) {
+                Some(ListpaysPaysStatus::Complete) => Some(PayStatus::Complete),
+                Some(ListpaysPaysStatus::Pending) => Some(PayStatus::Pending),
+                Some(ListpaysPaysStatus::Failed) => Some(PayStatus::Failed),
+                None => None,
+            },
+            destination: pay.destination.map(hex::encode),
+            amount_msat: pay.amount_msat.map(|a| a.msat),
+            amount_sent_msat: pay.amount_sent_msat.map(|a| a.msat),
+            parts: pay.number_of_parts.unwrap_or(1) as u32,
+            created_at: pay.created_at,
+            invreq_payer_note: None,
+            offer_description: None,
+            offer_issuer: None,
+            request_id: None,
         }
     }
 }
@@ -211,12 +988,66 @@ impl TryFrom<TlvEntry> for cln::TlvEntry {
     }
 }
 
+/// One hop of a route hint: the channel from node `id` towards the payee
+/// and what forwarding over it costs.
+#[derive(Clone, Debug)]
+pub struct RouteHintHop {
+    pub id: String,
+    pub short_channel_id: String,
+    pub fee_base_msat: u64,
+    pub fee_proportional_millionths: u32,
+    pub cltv_expiry_delta: u32,
+}
+
+impl TryFrom<RouteHintHop> for cln::RouteHop {
+    type Error = SdkError;
+
+    fn try_from(hop: RouteHintHop) -> Result<Self> {
+        Ok(cln::RouteHop {
+            id: hex::decode(hop.id)
+                .context("route hint hop id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            short_channel_id: hop.short_channel_id,
+            feebase: Some(cln::Amount {
+                msat: hop.fee_base_msat,
+            }),
+            feeprop: hop.fee_proportional_millionths,
+            expirydelta: hop.cltv_expiry_delta,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RouteHint {
+    pub hops: Vec<RouteHintHop>,
+}
+
+impl TryFrom<RouteHint> for cln::Routehint {
+    type Error = SdkError;
+
+    fn try_from(hint: RouteHint) -> Result<Self> {
+        Ok(cln::Routehint {
+            hops: hint
+                .hops
+                .into_iter()
+                .map(cln::RouteHop::try_from)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct KeySendRequest {
     pub destination: String,
     pub amount_msat: Option<u64>,
     pub label: Option<String>,
     pub extra_tlvs: Option<Vec<TlvEntry>>,
+    pub route_hints: Option<Vec<RouteHint>>,
+    pub maxfee_msat: Option<u64>,
+    pub maxfeepercent: Option<f64>,
+    pub exemptfee_msat: Option<u64>,
+    pub retry_for: Option<u32>,
+    pub maxdelay: Option<u32>,
 }
 
 impl TryFrom<KeySendRequest> for cln::KeysendRequest {
@@ -238,7 +1069,21 @@ impl TryFrom<KeySendRequest> for cln::KeysendRequest {
                 })
                 .transpose()?
                 .map(|tlvs| cln::TlvStream { entries: tlvs }),
-            ..Default::default()
+            routehints: req
+                .route_hints
+                .map(|hints| {
+                    hints
+                        .into_iter()
+                        .map(cln::Routehint::try_from)
+                        .collect::<Result<_>>()
+                })
+                .transpose()?
+                .map(|hints| cln::RoutehintList { hints }),
+            maxfee: req.maxfee_msat.map(|a| cln::Amount { msat: a }),
+            maxfeepercent: req.maxfeepercent,
+            exemptfee: req.exemptfee_msat.map(|a| cln::Amount { msat: a }),
+            retry_for: req.retry_for,
+            maxdelay: req.maxdelay,
         })
     }
 }
@@ -246,12 +1091,14 @@ impl TryFrom<KeySendRequest> for cln::KeysendRequest {
 #[derive(Clone, Debug)]
 pub struct KeySendResponse {
     pub payment_preimage: String,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::KeysendResponse> for KeySendResponse {
     fn from(pay: cln::KeysendResponse) -> Self {
         KeySendResponse {
             payment_preimage: hex::encode(pay.payment_preimage),
+            request_id: None,
         }
     }
 }
@@ -259,6 +1106,8 @@ impl From<cln::KeysendResponse> for KeySendResponse {
 #[derive(Clone, Debug)]
 pub struct ListFundsRequest {
     pub spent: Option<bool>,
+    /// Look up the alias of every channel peer.
+    pub enrich: bool,
 }
 
 impl From<ListFundsRequest> for cln::ListfundsRequest {
@@ -267,6 +1116,27 @@ impl From<ListFundsRequest> for cln::ListfundsRequest {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ListFundsOutputStatus {
+    Unconfirmed,
+    Confirmed,
+    Spent,
+    Immature,
+}
+
+impl ListFundsOutputStatus {
+    fn from_i32(status: i32) -> Option<Self> {
+        use cln::listfunds_outputs::ListfundsOutputsStatus;
+
+        match ListfundsOutputsStatus::from_i32(status)? {
+            ListfundsOutputsStatus::Unconfirmed => Some(ListFundsOutputStatus::Unconfirmed),
+            ListfundsOutputsStatus::Confirmed => Some(ListFundsOutputStatus::Confirmed),
+            ListfundsOutputsStatus::Spent => Some(ListFundsOutputStatus::Spent),
+            ListfundsOutputsStatus::Immature => Some(ListFundsOutputStatus::Immature),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ListFundsOutput {
     pub txid: String,
@@ -275,9 +1145,12 @@ pub struct ListFundsOutput {
     pub scriptpubkey: String,
     pub address: Option<String>,
     pub redeemscript: Option<String>,
-    pub status: i32,
+    pub status: Option<ListFundsOutputStatus>,
     pub reserved: bool,
     pub blockheight: Option<u32>,
+    /// The transaction that spent a spent output, if the node's wallet has
+    /// it.
+    pub spent_by: Option<String>,
 }
 
 impl From<cln::ListfundsOutputs> for ListFundsOutput {
@@ -289,9 +1162,10 @@ impl From<cln::ListfundsOutputs> for ListFundsOutput {
             scriptpubkey: hex::encode(output.scriptpubkey),
             address: output.address,
             redeemscript: output.redeemscript.map(hex::encode),
-            status: output.status,
+            status: ListFundsOutputStatus::from_i32(output.status),
             reserved: output.reserved,
             blockheight: output.blockheight,
+            spent_by: None,
         }
     }
 }
@@ -307,6 +1181,8 @@ pub struct ListFundsChannel {
     pub state: i32,
     pub channel_id: Option<String>,
     pub short_channel_id: Option<String>,
+    /// Only set when the request asked to `enrich` the channels.
+    pub peer_alias: Option<String>,
 }
 
 impl From<cln::ListfundsChannels> for ListFundsChannel {
@@ -321,6 +1197,7 @@ impl From<cln::ListfundsChannels> for ListFundsChannel {
             state: channel.state,
             channel_id: channel.channel_id.map(hex::encode),
             short_channel_id: channel.short_channel_id,
+            peer_alias: None,
         }
     }
 }
@@ -384,6 +1261,28 @@ pub struct FundChannelRequest {
     pub amount_msat: Option<u64>,
     pub announce: Option<bool>,
     pub minconf: Option<u32>,
+    /// Fund the channel from exactly these outputs instead of letting the
+    /// node pick. Change always goes back to the node's own wallet.
+    pub utxos: Vec<Outpoint>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Outpoint {
+    pub txid: String,
+    pub outnum: u32,
+}
+
+impl TryFrom<Outpoint> for cln::Outpoint {
+    type Error = SdkError;
+
+    fn try_from(outpoint: Outpoint) -> Result<Self> {
+        Ok(cln::Outpoint {
+            txid: hex::decode(outpoint.txid)
+                .context("txid contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            outnum: outpoint.outnum,
+        })
+    }
 }
 
 impl TryFrom<FundChannelRequest> for cln::FundchannelRequest {
@@ -399,6 +1298,11 @@ impl TryFrom<FundChannelRequest> for cln::FundchannelRequest {
             }),
             announce: req.announce,
             minconf: req.minconf,
+            utxos: req
+                .utxos
+                .into_iter()
+                .map(cln::Outpoint::try_from)
+                .collect::<Result<_>>()?,
             ..Default::default()
         })
     }
@@ -407,12 +1311,14 @@ impl TryFrom<FundChannelRequest> for cln::FundchannelRequest {
 #[derive(Clone, Debug)]
 pub struct FundChannelResponse {
     pub txid: String,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::FundchannelResponse> for FundChannelResponse {
     fn from(response: cln::FundchannelResponse) -> Self {
         FundChannelResponse {
             txid: hex::encode(response.txid),
+            request_id: None,
         }
     }
 }
@@ -489,6 +1395,10 @@ pub struct ListInvoicesRequest {
     pub index: Option<ListInvoicesIndex>,
     pub start: Option<u64>,
     pub limit: Option<u32>,
+    pub created_after: Option<u64>,
+    pub paid_after: Option<u64>,
+    /// Return labels, descriptions and payer notes even in privacy mode.
+    pub include_sensitive: bool,
 }
 
 impl TryFrom<ListInvoicesRequest> for cln::ListinvoicesRequest {
@@ -549,10 +1459,29 @@ pub struct ListInvoicesInvoice {
     pub paid_at: Option<u64>,
     pub paid_outpoint: Option<ListInvoicesInvoicePaidOutpoint>,
     pub payment_preimage: Option<String>,
+    /// Set instead of `payment_preimage` when byte fields are enabled.
+    pub payment_preimage_bytes: Option<Vec<u8>>,
+}
+
+impl ListInvoicesInvoice {
+    fn redact(&mut self) {
+        self.label = String::from(REDACTED);
+        self.description = None;
+        self.invreq_payer_note = None;
+    }
 }
 
 impl From<cln::ListinvoicesInvoices> for ListInvoicesInvoice {
     fn from(invoice: cln::ListinvoicesInvoices) -> Self {
+        ListInvoicesInvoice::new(invoice, false)
+    }
+}
+
+impl ListInvoicesInvoice {
+    fn new(invoice: cln::ListinvoicesInvoices, byte_fields: bool) -> Self {
+        let (payment_preimage, payment_preimage_bytes) =
+            hex_or_bytes(invoice.payment_preimage, byte_fields);
+
         ListInvoicesInvoice {
             label: invoice.label,
             description: invoice.description,
@@ -572,7 +1501,27 @@ impl From<cln::ListinvoicesInvoices> for ListInvoicesInvoice {
             paid_outpoint: invoice
                 .paid_outpoint
                 .map(ListInvoicesInvoicePaidOutpoint::from),
-            payment_preimage: invoice.payment_preimage.map(hex::encode),
+            payment_preimage,
+            payment_preimage_bytes,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ListInvoicesStatus {
+    Unpaid,
+    Paid,
+    Expired,
+}
+
+impl From<ListInvoicesStatus> for cln::listinvoices_invoices::ListinvoicesInvoicesStatus {
+    fn from(s: ListInvoicesStatus) -> Self {
+        use cln::listinvoices_invoices::ListinvoicesInvoicesStatus;
+
+        match s {
+            ListInvoicesStatus::Unpaid => ListinvoicesInvoicesStatus::Unpaid,
+            ListInvoicesStatus::Paid => ListinvoicesInvoicesStatus::Paid,
+            ListInvoicesStatus::Expired => ListinvoicesInvoicesStatus::Expired,
         }
     }
 }
@@ -584,11 +1533,17 @@ pub struct ListInvoicesResponse {
 
 impl From<cln::ListinvoicesResponse> for ListInvoicesResponse {
     fn from(response: cln::ListinvoicesResponse) -> Self {
+        ListInvoicesResponse::new(response, false)
+    }
+}
+
+impl ListInvoicesResponse {
+    fn new(response: cln::ListinvoicesResponse, byte_fields: bool) -> Self {
         ListInvoicesResponse {
             invoices: response
                 .invoices
                 .into_iter()
-                .map(ListInvoicesInvoice::from)
+                .map(|invoice| ListInvoicesInvoice::new(invoice, byte_fields))
                 .collect(),
         }
     }
@@ -616,6 +1571,8 @@ pub struct ListPaymentsRequest {
     pub bolt11: Option<String>,
     pub payment_hash: Option<String>,
     pub status: Option<ListPaymentsStatus>,
+    /// Return labels, descriptions and payer notes even in privacy mode.
+    pub include_sensitive: bool,
 }
 
 impl TryFrom<ListPaymentsRequest> for cln::ListpaysRequest {
@@ -654,10 +1611,35 @@ pub struct ListPaymentsPayment {
     pub preimage: Option<String>,
     pub number_of_parts: Option<u64>,
     pub erroronion: Option<String>,
+    /// Set instead of `preimage` when byte fields are enabled.
+    pub preimage_bytes: Option<Vec<u8>>,
+    /// Set instead of `erroronion` when byte fields are enabled.
+    pub erroronion_bytes: Option<Vec<u8>>,
+    pub invreq_payer_note: Option<String>,
+    pub offer_description: Option<String>,
+    pub offer_issuer: Option<String>,
+}
+
+impl ListPaymentsPayment {
+    fn redact(&mut self) {
+        self.label = None;
+        self.description = None;
+        self.invreq_payer_note = None;
+        self.offer_description = None;
+    }
 }
 
 impl From<cln::ListpaysPays> for ListPaymentsPayment {
     fn from(payment: cln::ListpaysPays) -> Self {
+        ListPaymentsPayment::new(payment, false)
+    }
+}
+
+impl ListPaymentsPayment {
+    fn new(payment: cln::ListpaysPays, byte_fields: bool) -> Self {
+        let (preimage, preimage_bytes) = hex_or_bytes(payment.preimage, byte_fields);
+        let (erroronion, erroronion_bytes) = hex_or_bytes(payment.erroronion, byte_fields);
+
         ListPaymentsPayment {
             payment_hash: hex::encode(payment.payment_hash),
             status: payment.status,
@@ -670,9 +1652,14 @@ impl From<cln::ListpaysPays> for ListPaymentsPayment {
             bolt12: payment.bolt12,
             amount_msat: payment.amount_msat.map(|a| a.msat),
             amount_sent_msat: payment.amount_sent_msat.map(|a| a.msat),
-            preimage: payment.preimage.map(hex::encode),
+            preimage,
             number_of_parts: payment.number_of_parts,
-            erroronion: payment.erroronion.map(hex::encode),
+            erroronion,
+            preimage_bytes,
+            erroronion_bytes,
+            invreq_payer_note: None,
+            offer_description: None,
+            offer_issuer: None,
         }
     }
 }
@@ -684,16 +1671,171 @@ pub struct ListPaymentsResponse {
 
 impl From<cln::ListpaysResponse> for ListPaymentsResponse {
     fn from(response: cln::ListpaysResponse) -> Self {
+        ListPaymentsResponse::new(response, false)
+    }
+}
+
+impl ListPaymentsResponse {
+    fn new(response: cln::ListpaysResponse, byte_fields: bool) -> Self {
         ListPaymentsResponse {
             payments: response
                 .pays
                 .into_iter()
-                .map(ListPaymentsPayment::from)
+                .map(|payment| ListPaymentsPayment::new(payment, byte_fields))
+                .collect(),
+        }
+    }
+}
+
+/// Hex-encodes `bytes`, or with `byte_fields` hands them over as they are,
+/// which saves allocating a string twice their size for hosts that would
+/// decode it again anyway.
+fn hex_or_bytes(bytes: Option<Vec<u8>>, byte_fields: bool) -> (Option<String>, Option<Vec<u8>>) {
+    if byte_fields {
+        (None, bytes)
+    } else {
+        (bytes.map(hex::encode), None)
+    }
+}
+
+/// Offer fields carried by a BOLT12 invoice, which listpays and pay don't
+/// return themselves.
+#[derive(Clone, Debug, Default)]
+struct Bolt12Metadata {
+    invreq_payer_note: Option<String>,
+    offer_description: Option<String>,
+    offer_issuer: Option<String>,
+}
+
+impl From<cln::DecodeResponse> for Bolt12Metadata {
+    fn from(decoded: cln::DecodeResponse) -> Self {
+        Bolt12Metadata {
+            invreq_payer_note: decoded.invreq_payer_note,
+            offer_description: decoded.offer_description,
+            offer_issuer: decoded.offer_issuer,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvstringKind {
+    Bolt11Invoice,
+    Bolt12Offer,
+    Bolt12InvoiceRequest,
+    Bolt12Invoice,
+}
+
+impl InvstringKind {
+    fn from_i32(kind: i32) -> Option<Self> {
+        use cln::decode_response::DecodeType;
+
+        match DecodeType::from_i32(kind)? {
+            DecodeType::Bolt11Invoice => Some(InvstringKind::Bolt11Invoice),
+            DecodeType::Bolt12Offer => Some(InvstringKind::Bolt12Offer),
+            DecodeType::Bolt12InvoiceRequest => Some(InvstringKind::Bolt12InvoiceRequest),
+            DecodeType::Bolt12Invoice => Some(InvstringKind::Bolt12Invoice),
+            _ => None,
+        }
+    }
+}
+
+impl From<cln::DecodeRoutehint> for RouteHint {
+    fn from(hint: cln::DecodeRoutehint) -> Self {
+        RouteHint {
+            hops: hint
+                .hops
+                .into_iter()
+                .map(|hop| RouteHintHop {
+                    id: hex::encode(hop.pubkey),
+                    short_channel_id: hop.short_channel_id,
+                    fee_base_msat: hop.fee_base_msat.map(|a| a.msat).unwrap_or_default(),
+                    fee_proportional_millionths: hop.fee_proportional_millionths,
+                    cltv_expiry_delta: hop.cltv_expiry_delta,
+                })
                 .collect(),
         }
     }
 }
 
+/// What an invoice or offer asks for, for showing it to the user before
+/// paying. BOLT11 and BOLT12 fields are merged where they mean the same
+/// thing; `kind` tells which one was decoded.
+#[derive(Clone, Debug)]
+pub struct DecodeResponse {
+    /// `None` for strings that decode but aren't payable, e.g. runes.
+    pub kind: Option<InvstringKind>,
+    pub valid: bool,
+    pub amount_msat: Option<u64>,
+    pub description: Option<String>,
+    pub description_hash: Option<String>,
+    pub payee: Option<String>,
+    pub created_at: Option<u64>,
+    /// Seconds after `created_at`.
+    pub expiry: Option<u64>,
+    pub payment_hash: Option<String>,
+    pub payment_secret: Option<String>,
+    pub min_final_cltv_expiry: Option<u32>,
+    pub features: Vec<String>,
+    pub routes: Vec<RouteHint>,
+    /// Number of blinded paths to the offer or invoice node.
+    pub blinded_paths: u32,
+    pub offer_id: Option<String>,
+    pub offer_issuer: Option<String>,
+    pub offer_quantity_max: Option<u64>,
+    pub offer_absolute_expiry: Option<u64>,
+    pub invreq_payer_note: Option<String>,
+}
+
+impl From<cln::DecodeResponse> for DecodeResponse {
+    fn from(decoded: cln::DecodeResponse) -> Self {
+        let features = decoded
+            .features
+            .as_deref()
+            .or(decoded.invoice_features.as_deref())
+            .or(decoded.offer_features.as_deref())
+            .map(features::feature_names)
+            .unwrap_or_default();
+
+        DecodeResponse {
+            kind: InvstringKind::from_i32(decoded.item_type),
+            valid: decoded.valid,
+            amount_msat: decoded
+                .amount_msat
+                .or(decoded.invoice_amount_msat)
+                .or(decoded.offer_amount_msat)
+                .map(|a| a.msat),
+            description: decoded.description.or(decoded.offer_description),
+            description_hash: decoded.description_hash.map(hex::encode),
+            payee: decoded
+                .payee
+                .or(decoded.invoice_node_id)
+                .or(decoded.offer_node_id)
+                .map(hex::encode),
+            created_at: decoded.created_at.or(decoded.invoice_created_at),
+            expiry: decoded
+                .expiry
+                .or(decoded.invoice_relative_expiry.map(u64::from)),
+            payment_hash: decoded
+                .payment_hash
+                .or(decoded.invoice_payment_hash)
+                .map(hex::encode),
+            payment_secret: decoded.payment_secret.map(hex::encode),
+            min_final_cltv_expiry: decoded.min_final_cltv_expiry,
+            features,
+            routes: decoded
+                .routes
+                .map(|routes| routes.hints.into_iter().map(RouteHint::from).collect())
+                .unwrap_or_default(),
+            blinded_paths: (decoded.offer_paths.len() + decoded.invoice_paths.len()) as u32,
+            offer_id: decoded.offer_id.map(hex::encode),
+            offer_issuer: decoded.offer_issuer,
+            offer_quantity_max: decoded.offer_quantity_max,
+            offer_absolute_expiry: decoded.offer_absolute_expiry,
+            invreq_payer_note: decoded.invreq_payer_note,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SignMessageRequest {
     pub message: String,
@@ -748,6 +1890,8 @@ pub struct WithdrawRequest {
     pub destination: String,
     pub amount: Option<AmountOrAll>,
     pub minconf: Option<u32>,
+    /// Defaults to the node's normal feerate, see `fee_rates`.
+    pub feerate_perkw: Option<u32>,
 }
 
 impl From<WithdrawRequest> for cln::WithdrawRequest {
@@ -756,6 +1900,9 @@ impl From<WithdrawRequest> for cln::WithdrawRequest {
             destination: req.destination,
             satoshi: req.amount.map(AmountOrAll::into),
             minconf: req.minconf,
+            feerate: req.feerate_perkw.map(|feerate| cln::Feerate {
+                style: Some(cln::feerate::Style::Perkw(feerate)),
+            }),
             ..Default::default()
         }
     }
@@ -766,6 +1913,7 @@ pub struct WithdrawResponse {
     pub tx: String,
     pub txid: String,
     pub psbt: String,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::WithdrawResponse> for WithdrawResponse {
@@ -774,29 +1922,98 @@ impl From<cln::WithdrawResponse> for WithdrawResponse {
             tx: hex::encode(response.tx),
             txid: hex::encode(response.txid),
             psbt: response.psbt,
+            request_id: None,
         }
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct CloseRequest {
+pub struct SetPsbtVersionRequest {
+    /// Base64 encoded.
+    pub psbt: String,
+    /// 0 or 2.
+    pub version: u32,
+}
+
+impl TryFrom<SetPsbtVersionRequest> for cln::SetpsbtversionRequest {
+    type Error = SdkError;
+
+    fn try_from(req: SetPsbtVersionRequest) -> Result<Self> {
+        if req.version != 0 && req.version != 2 {
+            return Err(SdkError::InvalidArgument {
+                message: format!("unsupported PSBT version {}, expected 0 or 2", req.version),
+                request_id: None,
+            });
+        }
+
+        Ok(cln::SetpsbtversionRequest {
+            psbt: req.psbt,
+            version: req.version,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SetPsbtVersionResponse {
+    pub psbt: String,
+}
+
+impl From<cln::SetpsbtversionResponse> for SetPsbtVersionResponse {
+    fn from(response: cln::SetpsbtversionResponse) -> Self {
+        SetPsbtVersionResponse {
+            psbt: response.psbt,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FeeNegotiationStep {
+    Sats { sat: u64 },
+    Percent { percent: u8 },
+}
+
+impl TryFrom<FeeNegotiationStep> for String {
+    type Error = SdkError;
+
+    fn try_from(step: FeeNegotiationStep) -> Result<Self> {
+        match step {
+            FeeNegotiationStep::Sats { sat } if sat > 0 => Ok(sat.to_string()),
+            FeeNegotiationStep::Percent { percent } if (1..=100).contains(&percent) => {
+                Ok(format!("{}%", percent))
+            }
+            FeeNegotiationStep::Sats { .. } => Err(SdkError::InvalidArgument {
+                message: String::from("fee negotiation step must be at least 1 sat"),
+                request_id: None,
+            }),
+            FeeNegotiationStep::Percent { .. } => Err(SdkError::InvalidArgument {
+                message: String::from("fee negotiation step percentage must be between 1 and 100"),
+                request_id: None,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CloseRequest {
     pub id: String,
     pub unilateral_timeout: Option<u32>,
     pub destination: Option<String>,
-    pub fee_negotiation_step: Option<String>,
+    pub fee_negotiation_step: Option<FeeNegotiationStep>,
     pub force_lease_closed: Option<bool>,
 }
 
-impl From<CloseRequest> for cln::CloseRequest {
-    fn from(req: CloseRequest) -> Self {
-        cln::CloseRequest {
+impl TryFrom<CloseRequest> for cln::CloseRequest {
+    type Error = SdkError;
+
+    fn try_from(req: CloseRequest) -> Result<Self> {
+        Ok(cln::CloseRequest {
             id: req.id,
             unilateraltimeout: req.unilateral_timeout,
             destination: req.destination,
-            fee_negotiation_step: req.fee_negotiation_step,
+            fee_negotiation_step: req.fee_negotiation_step.map(String::try_from).transpose()?,
             force_lease_closed: req.force_lease_closed,
             ..Default::default()
-        }
+        })
     }
 }
 
@@ -805,6 +2022,7 @@ pub struct CloseResponse {
     pub item_type: i32,
     pub tx: Option<String>,
     pub txid: Option<String>,
+    pub request_id: Option<String>,
 }
 
 impl From<cln::CloseResponse> for CloseResponse {
@@ -813,264 +2031,7005 @@ impl From<cln::CloseResponse> for CloseResponse {
             item_type: response.item_type,
             tx: response.tx.map(hex::encode),
             txid: response.txid.map(hex::encode),
+            request_id: None,
         }
     }
 }
 
-pub struct GreenlightAlbyClient {
-    node: gl_client::node::ClnClient,
-    shutdown: Sender<()>,
-    signer_handle: JoinHandle<()>,
+#[derive(Clone, Debug)]
+pub struct CreateOfferRequest {
+    /// `None` lets the payer choose the amount.
+    pub amount_msat: Option<u64>,
+    pub description: String,
+    pub issuer: Option<String>,
+    pub label: Option<String>,
+    pub quantity_max: Option<u64>,
+    pub absolute_expiry: Option<u64>,
+    pub single_use: Option<bool>,
 }
 
-pub async fn recover(mnemonic: String) -> Result<GreenlightCredentials> {
-    let mnemonic = Mnemonic::from_str(&mnemonic)
-        .context("failed to parse mnemonic")
-        .map_err(SdkError::invalid_arg)?;
+impl From<CreateOfferRequest> for cln::OfferRequest {
+    fn from(req: CreateOfferRequest) -> Self {
+        cln::OfferRequest {
+            amount: req
+                .amount_msat
+                .map_or_else(|| String::from("any"), |msat| format!("{}msat", msat)),
+            description: req.description,
+            issuer: req.issuer,
+            label: req.label,
+            quantity_max: req.quantity_max,
+            absolute_expiry: req.absolute_expiry,
+            single_use: req.single_use,
+            ..Default::default()
+        }
+    }
+}
 
-    let secret = mnemonic.to_seed("")[0..32].to_vec(); // Only need the first 32 bytes
+#[derive(Clone, Debug)]
+pub struct CreateOfferResponse {
+    pub offer_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    /// False if an identical offer already existed and was returned instead.
+    pub created: bool,
+    pub label: Option<String>,
+}
 
-    let creds = Nobody::new();
+impl From<cln::OfferResponse> for CreateOfferResponse {
+    fn from(response: cln::OfferResponse) -> Self {
+        CreateOfferResponse {
+            offer_id: hex::encode(response.offer_id),
+            active: response.active,
+            single_use: response.single_use,
+            bolt12: response.bolt12,
+            used: response.used,
+            created: response.created,
+            label: response.label,
+        }
+    }
+}
 
-    let signer = Signer::new(secret, Network::Bitcoin, creds.clone())
-        .context("failed to create signer")
-        .map_err(SdkError::greenlight_api)?;
+#[derive(Clone, Debug)]
+pub struct ListOffersRequest {
+    pub offer_id: Option<String>,
+    pub active_only: Option<bool>,
+}
 
-    let scheduler = Scheduler::new(signer.node_id(), Network::Bitcoin, creds)
-        .await
-        .context("failed to create scheduler")
-        .map_err(SdkError::greenlight_api)?;
+impl TryFrom<ListOffersRequest> for cln::ListoffersRequest {
+    type Error = SdkError;
 
-    Ok(scheduler
-        .recover(&signer)
-        .await
-        .context("failed to recover credentials")
-        .map_err(SdkError::greenlight_api)?
-        .into())
+    fn try_from(req: ListOffersRequest) -> Result<Self> {
+        Ok(cln::ListoffersRequest {
+            offer_id: req
+                .offer_id
+                .map(hex::decode)
+                .transpose()
+                .context("offer id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            active_only: req.active_only,
+        })
+    }
 }
 
-pub async fn register(mnemonic: String, invite_code: String) -> Result<GreenlightCredentials> {
-    let mnemonic = Mnemonic::from_str(&mnemonic)
-        .context("failed to parse mnemonic")
-        .map_err(SdkError::invalid_arg)?;
-
-    let secret = mnemonic.to_seed("")[0..32].to_vec(); // Only need the first 32 bytes
+#[derive(Clone, Debug)]
+pub struct ListOffersOffer {
+    pub offer_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    pub label: Option<String>,
+}
 
-    let creds = Nobody::new();
+impl From<cln::ListoffersOffers> for ListOffersOffer {
+    fn from(offer: cln::ListoffersOffers) -> Self {
+        ListOffersOffer {
+            offer_id: hex::encode(offer.offer_id),
+            active: offer.active,
+            single_use: offer.single_use,
+            bolt12: offer.bolt12,
+            used: offer.used,
+            label: offer.label,
+        }
+    }
+}
 
-    let signer = Signer::new(secret, Network::Bitcoin, creds.clone())
-        .context("failed to create signer")
-        .map_err(SdkError::greenlight_api)?;
+#[derive(Clone, Debug)]
+pub struct ListOffersResponse {
+    pub offers: Vec<ListOffersOffer>,
+}
 
-    let scheduler = Scheduler::new(signer.node_id(), Network::Bitcoin, creds)
-        .await
-        .context("failed to create scheduler")
-        .map_err(SdkError::greenlight_api)?;
+impl From<cln::ListoffersResponse> for ListOffersResponse {
+    fn from(response: cln::ListoffersResponse) -> Self {
+        ListOffersResponse {
+            offers: response
+                .offers
+                .into_iter()
+                .map(ListOffersOffer::from)
+                .collect(),
+        }
+    }
+}
 
-    Ok(scheduler
-        .register(&signer, Some(invite_code))
-        .await
-        .context("failed to register node")
-        .map_err(SdkError::greenlight_api)?
-        .into())
+#[derive(Clone, Debug)]
+pub struct DisableOfferRequest {
+    pub offer_id: String,
 }
 
-pub async fn new_greenlight_alby_client(
-    mnemonic: String,
-    credentials: GreenlightCredentials,
-) -> Result<Arc<GreenlightAlbyClient>> {
-    let cred_bytes = hex::decode(&credentials.gl_creds)
-        .context("failed to decode credentials")
-        .map_err(SdkError::invalid_arg)?;
+impl TryFrom<DisableOfferRequest> for cln::DisableofferRequest {
+    type Error = SdkError;
 
-    let creds = gl_client::credentials::Device::from_bytes(&cred_bytes);
+    fn try_from(req: DisableOfferRequest) -> Result<Self> {
+        Ok(cln::DisableofferRequest {
+            offer_id: hex::decode(req.offer_id)
+                .context("offer id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+        })
+    }
+}
 
-    let mnemonic = Mnemonic::from_str(&mnemonic)
-        .context("failed to parse mnemonic")
-        .map_err(SdkError::invalid_arg)?;
+#[derive(Clone, Debug)]
+pub struct DisableOfferResponse {
+    pub offer_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    pub label: Option<String>,
+}
 
-    let secret = mnemonic.to_seed("")[0..32].to_vec(); // Only need the first 32 bytes
+impl From<cln::DisableofferResponse> for DisableOfferResponse {
+    fn from(response: cln::DisableofferResponse) -> Self {
+        DisableOfferResponse {
+            offer_id: hex::encode(response.offer_id),
+            active: response.active,
+            single_use: response.single_use,
+            bolt12: response.bolt12,
+            used: response.used,
+            label: response.label,
+        }
+    }
+}
 
-    let signer = Signer::new(secret, Network::Bitcoin, creds.clone())
-        .context("failed to create signer")
-        .map_err(SdkError::greenlight_api)?;
+/// Which end of a channel did something, e.g. opened or closed it.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ChannelSide {
+    Local,
+    Remote,
+}
 
-    let scheduler = Scheduler::new(signer.node_id(), Network::Bitcoin, creds)
-        .await
-        .context("failed to create scheduler")
-        .map_err(SdkError::greenlight_api)?;
+impl ChannelSide {
+    fn from_i32(side: i32) -> Option<Self> {
+        match cln::ChannelSide::from_i32(side)? {
+            cln::ChannelSide::Local => Some(ChannelSide::Local),
+            cln::ChannelSide::Remote => Some(ChannelSide::Remote),
+        }
+    }
+}
 
-    let node = scheduler
-        .node()
-        .await
-        .context("failed to create node")
-        .map_err(SdkError::greenlight_api)?;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListPeerChannelsChannel {
+    pub peer_id: Option<String>,
+    pub peer_connected: Option<bool>,
+    pub state: Option<i32>,
+    pub short_channel_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub funding_txid: Option<String>,
+    pub to_us_msat: Option<u64>,
+    pub total_msat: Option<u64>,
+    pub spendable_msat: Option<u64>,
+    pub receivable_msat: Option<u64>,
+    pub opener: Option<ChannelSide>,
+    /// Only set once the channel is closing.
+    pub closer: Option<ChannelSide>,
+    pub private: Option<bool>,
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub fee_base_msat: Option<u64>,
+    #[serde(default)]
+    pub fee_proportional_millionths: Option<u32>,
+    #[serde(default)]
+    pub our_reserve_msat: Option<u64>,
+    #[serde(default)]
+    pub their_reserve_msat: Option<u64>,
+    #[serde(default)]
+    pub minimum_htlc_out_msat: Option<u64>,
+    #[serde(default)]
+    pub maximum_htlc_out_msat: Option<u64>,
+    /// HTLCs currently in flight on the channel, in either direction.
+    #[serde(default)]
+    pub htlc_count: u32,
+    #[serde(default)]
+    pub max_accepted_htlcs: Option<u32>,
+    /// Only set when the request asked to `enrich` the channels.
+    #[serde(default)]
+    pub peer_alias: Option<String>,
+}
 
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
-    let signer_handle = tokio::spawn(async move {
-        println!("Run forever started");
-        if let Err(e) = signer.run_forever(rx).await {
-            eprintln!("Run forever error: {:?}", e);
+impl From<cln::ListpeerchannelsChannels> for ListPeerChannelsChannel {
+    fn from(channel: cln::ListpeerchannelsChannels) -> Self {
+        ListPeerChannelsChannel {
+            peer_id: channel.peer_id.map(hex::encode),
+            peer_connected: channel.peer_connected,
+            state: channel.state,
+            short_channel_id: channel.short_channel_id,
+            channel_id: channel.channel_id.map(hex::encode),
+            funding_txid: channel.funding_txid.map(hex::encode),
+            to_us_msat: channel.to_us_msat.map(|a| a.msat),
+            total_msat: channel.total_msat.map(|a| a.msat),
+            spendable_msat: channel.spendable_msat.map(|a| a.msat),
+            receivable_msat: channel.receivable_msat.map(|a| a.msat),
+            opener: channel.opener.and_then(ChannelSide::from_i32),
+            closer: channel.closer.and_then(ChannelSide::from_i32),
+            private: channel.private,
+            features: channel.features,
+            fee_base_msat: channel.fee_base_msat.map(|a| a.msat),
+            fee_proportional_millionths: channel.fee_proportional_millionths,
+            our_reserve_msat: channel.our_reserve_msat.map(|a| a.msat),
+            their_reserve_msat: channel.their_reserve_msat.map(|a| a.msat),
+            minimum_htlc_out_msat: channel.minimum_htlc_out_msat.map(|a| a.msat),
+            maximum_htlc_out_msat: channel.maximum_htlc_out_msat.map(|a| a.msat),
+            htlc_count: channel.htlcs.len() as u32,
+            max_accepted_htlcs: channel.max_accepted_htlcs,
+            peer_alias: None,
         }
-        println!("Run forever finished");
-    });
-
-    Ok(Arc::new(GreenlightAlbyClient {
-        node,
-        signer_handle,
-        shutdown: tx,
-    }))
+    }
 }
 
-impl GreenlightAlbyClient {
-    pub async fn shutdown(&self) -> Result<ShutdownResponse> {
-        println!("Sending shutdown message");
-        self.shutdown.send(()).await.unwrap();
+#[derive(Clone, Debug)]
+pub struct ListPeerChannelsRequest {
+    /// Only list the channels with this peer.
+    pub peer_id: Option<String>,
+    /// Fill in each channel's `peer_alias` from gossip.
+    pub enrich: bool,
+}
 
-        let mut tries = 0;
-        let max_tries = 2;
-        while !self.signer_handle.is_finished() && tries < max_tries {
-            println!("Waiting for signer to stop...");
-            time::sleep(Duration::from_millis(1000)).await;
-            tries += 1;
-        }
-        if tries == max_tries {
-            println!("Shutdown failed, aborting handle");
-            self.signer_handle.abort();
-            time::sleep(Duration::from_millis(1000)).await;
-        }
+impl TryFrom<ListPeerChannelsRequest> for cln::ListpeerchannelsRequest {
+    type Error = SdkError;
 
-        println!("Greenlight shutdown finished");
-        Ok(ShutdownResponse {})
+    fn try_from(req: ListPeerChannelsRequest) -> Result<Self> {
+        Ok(cln::ListpeerchannelsRequest {
+            id: req
+                .peer_id
+                .map(hex::decode)
+                .transpose()
+                .context("peer id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+        })
     }
+}
 
-    pub async fn get_info(&self) -> Result<GetInfoResponse> {
-        self.node
-            .clone()
-            .getinfo(cln::GetinfoRequest::default())
-            .await
-            .context("failed to get node info")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
-    }
+#[derive(Clone, Debug)]
+pub struct ListPeerChannelsResponse {
+    pub channels: Vec<ListPeerChannelsChannel>,
+}
 
-    pub async fn make_invoice(&self, req: MakeInvoiceRequest) -> Result<MakeInvoiceResponse> {
-        self.node
-            .clone()
-            .invoice(cln::InvoiceRequest::try_from(req)?)
-            .await
-            .context("failed to make invoice")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+impl From<cln::ListpeerchannelsResponse> for ListPeerChannelsResponse {
+    fn from(response: cln::ListpeerchannelsResponse) -> Self {
+        ListPeerChannelsResponse {
+            channels: response
+                .channels
+                .into_iter()
+                .map(ListPeerChannelsChannel::from)
+                .collect(),
+        }
     }
+}
 
-    pub async fn pay(&self, req: PayRequest) -> Result<PayResponse> {
-        self.node
-            .clone()
-            .pay(cln::PayRequest::from(req))
-            .await
-            .context("failed to pay invoice")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
-    }
+/// Why CLN closed a channel, as it records it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelCloseCause {
+    Unknown,
+    /// The node itself, e.g. on a protocol violation by the peer.
+    Local,
+    /// A `close` call.
+    User,
+    Remote,
+    Protocol,
+    /// A transaction spending the funding output was seen on chain.
+    Onchain,
+}
 
-    pub async fn key_send(&self, req: KeySendRequest) -> Result<KeySendResponse> {
-        self.node
-            .clone()
-            .key_send(cln::KeysendRequest::try_from(req)?)
-            .await
-            .context("failed to send keysend")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+impl ChannelCloseCause {
+    fn from_i32(cause: i32) -> Option<Self> {
+        use cln::listclosedchannels_closedchannels::ListclosedchannelsClosedchannelsCloseCause as Cause;
+
+        match Cause::from_i32(cause)? {
+            Cause::Unknown => Some(ChannelCloseCause::Unknown),
+            Cause::Local => Some(ChannelCloseCause::Local),
+            Cause::User => Some(ChannelCloseCause::User),
+            Cause::Remote => Some(ChannelCloseCause::Remote),
+            Cause::Protocol => Some(ChannelCloseCause::Protocol),
+            Cause::Onchain => Some(ChannelCloseCause::Onchain),
+        }
     }
+}
 
-    pub async fn list_funds(&self, req: ListFundsRequest) -> Result<ListFundsResponse> {
-        self.node
-            .clone()
-            .list_funds(cln::ListfundsRequest::from(req))
-            .await
-            .context("failed to list funds")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
-    }
+/// How a channel ended, for explaining to users why it disappeared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelCloseReason {
+    /// Both sides agreed on a closing transaction.
+    Mutual,
+    /// The node published its commitment transaction.
+    LocalForce,
+    /// The peer published its commitment transaction.
+    RemoteForce,
+    /// The node gave up on the channel after a protocol violation.
+    ProtocolError,
+    /// The peer published a revoked commitment transaction and was penalized.
+    OnchainBreach,
+    Unknown,
+}
 
-    pub async fn connect_peer(&self, req: ConnectPeerRequest) -> Result<ConnectPeerResponse> {
-        self.node
-            .clone()
-            .connect_peer(cln::ConnectRequest::from(req))
-            .await
-            .context("failed to connect peer")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+impl ChannelCloseReason {
+    /// From what CLN records for a closed channel. A `close` call and a
+    /// shutdown started by the peer are taken as mutual closes, and a spend
+    /// the node only noticed on chain as a force close by the peer. A close
+    /// that fell back to a force close after the mutual close timed out, or
+    /// a breach, can't be told apart once the channel is gone.
+    fn from_cause(cause: Option<ChannelCloseCause>) -> Self {
+        match cause {
+            Some(ChannelCloseCause::User | ChannelCloseCause::Remote) => ChannelCloseReason::Mutual,
+            Some(ChannelCloseCause::Local) => ChannelCloseReason::LocalForce,
+            Some(ChannelCloseCause::Onchain) => ChannelCloseReason::RemoteForce,
+            Some(ChannelCloseCause::Protocol) => ChannelCloseReason::ProtocolError,
+            Some(ChannelCloseCause::Unknown) | None => ChannelCloseReason::Unknown,
+        }
     }
 
-    pub async fn fund_channel(&self, req: FundChannelRequest) -> Result<FundChannelResponse> {
-        self.node
-            .clone()
-            .fund_channel(cln::FundchannelRequest::try_from(req)?)
-            .await
-            .context("failed to fund channel")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+    /// From the state and status lines of a channel `channel_closes`
+    /// considers closing. The status lines name the kind of close once
+    /// onchaind tracks the closing transaction.
+    pub(crate) fn from_status(state: Option<i32>, status: &[String]) -> Self {
+        use cln::ChannelState;
+
+        let mentions = |text: &str| status.iter().any(|s| s.to_lowercase().contains(text));
+        let state = state.and_then(ChannelState::from_i32);
+
+        if mentions("illegal close") || mentions("old unilateral") || mentions("cheat") {
+            ChannelCloseReason::OnchainBreach
+        } else if mentions("mutual close") || state == Some(ChannelState::ClosingdComplete) {
+            ChannelCloseReason::Mutual
+        } else if mentions("our own unilateral") || state == Some(ChannelState::AwaitingUnilateral)
+        {
+            ChannelCloseReason::LocalForce
+        } else if mentions("their unilateral") {
+            ChannelCloseReason::RemoteForce
+        } else {
+            ChannelCloseReason::Unknown
+        }
     }
 
-    pub async fn new_address(&self, req: NewAddressRequest) -> Result<NewAddressResponse> {
-        self.node
-            .clone()
-            .new_addr(cln::NewaddrRequest::from(req))
-            .await
-            .context("failed to request new address")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+    pub(crate) fn message(&self) -> &'static str {
+        match self {
+            ChannelCloseReason::Mutual => "The channel was closed cooperatively",
+            ChannelCloseReason::LocalForce => "The node force closed the channel",
+            ChannelCloseReason::RemoteForce => "The peer force closed the channel",
+            ChannelCloseReason::ProtocolError => {
+                "The channel was closed after the peer violated the protocol"
+            }
+            ChannelCloseReason::OnchainBreach => {
+                "The peer published an old channel state and its funds were claimed as a penalty"
+            }
+            ChannelCloseReason::Unknown => "The channel was closed for an unknown reason",
+        }
     }
+}
 
-    pub async fn list_invoices(&self, req: ListInvoicesRequest) -> Result<ListInvoicesResponse> {
-        self.node
-            .clone()
-            .list_invoices(cln::ListinvoicesRequest::try_from(req)?)
-            .await
-            .context("failed to list invoices")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
-    }
+#[derive(Clone, Debug)]
+pub struct ListClosedChannelsRequest {
+    /// Only list the closed channels with this peer.
+    pub peer_id: Option<String>,
+}
 
-    pub async fn list_payments(&self, req: ListPaymentsRequest) -> Result<ListPaymentsResponse> {
-        self.node
-            .clone()
-            .list_pays(cln::ListpaysRequest::try_from(req)?)
-            .await
-            .context("failed to list payments")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
-    }
+impl TryFrom<ListClosedChannelsRequest> for cln::ListclosedchannelsRequest {
+    type Error = SdkError;
 
-    pub async fn sign_message(&self, req: SignMessageRequest) -> Result<SignMessageResponse> {
-        self.node
-            .clone()
-            .sign_message(cln::SignmessageRequest::from(req))
-            .await
-            .context("failed to sign message")
-            .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+    fn try_from(req: ListClosedChannelsRequest) -> Result<Self> {
+        Ok(cln::ListclosedchannelsRequest {
+            id: req
+                .peer_id
+                .map(hex::decode)
+                .transpose()
+                .context("peer id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+        })
     }
+}
 
-    pub async fn withdraw(&self, req: WithdrawRequest) -> Result<WithdrawResponse> {
+#[derive(Clone, Debug)]
+pub struct ClosedChannel {
+    pub peer_id: Option<String>,
+    pub channel_id: String,
+    pub short_channel_id: Option<String>,
+    pub opener: Option<ChannelSide>,
+    pub closer: Option<ChannelSide>,
+    pub private: bool,
+    pub funding_txid: String,
+    pub total_msat: Option<u64>,
+    /// Our balance when the channel closed.
+    pub final_to_us_msat: Option<u64>,
+    pub last_commitment_txid: Option<String>,
+    pub last_commitment_fee_msat: Option<u64>,
+    pub close_cause: Option<ChannelCloseCause>,
+    pub close_reason: ChannelCloseReason,
+    /// Explains `close_reason` to users.
+    pub close_message: String,
+}
+
+impl From<cln::ListclosedchannelsClosedchannels> for ClosedChannel {
+    fn from(channel: cln::ListclosedchannelsClosedchannels) -> Self {
+        let close_cause = ChannelCloseCause::from_i32(channel.close_cause);
+        let close_reason = ChannelCloseReason::from_cause(close_cause);
+        ClosedChannel {
+            peer_id: channel.peer_id.map(hex::encode),
+            channel_id: hex::encode(channel.channel_id),
+            short_channel_id: channel.short_channel_id,
+            opener: ChannelSide::from_i32(channel.opener),
+            closer: channel.closer.and_then(ChannelSide::from_i32),
+            private: channel.private,
+            funding_txid: hex::encode(channel.funding_txid),
+            total_msat: channel.total_msat.map(|a| a.msat),
+            final_to_us_msat: channel.final_to_us_msat.map(|a| a.msat),
+            last_commitment_txid: channel.last_commitment_txid.map(hex::encode),
+            last_commitment_fee_msat: channel.last_commitment_fee_msat.map(|a| a.msat),
+            close_cause,
+            close_reason,
+            close_message: close_reason.message().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListClosedChannelsResponse {
+    pub channels: Vec<ClosedChannel>,
+}
+
+impl From<cln::ListclosedchannelsResponse> for ListClosedChannelsResponse {
+    fn from(response: cln::ListclosedchannelsResponse) -> Self {
+        ListClosedChannelsResponse {
+            channels: response
+                .closedchannels
+                .into_iter()
+                .map(ClosedChannel::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateInvoiceRequestRequest {
+    pub amount_msat: u64,
+    pub description: String,
+    pub issuer: Option<String>,
+    pub label: Option<String>,
+    pub absolute_expiry: Option<u64>,
+    pub single_use: Option<bool>,
+}
+
+impl From<CreateInvoiceRequestRequest> for cln::InvoicerequestRequest {
+    fn from(req: CreateInvoiceRequestRequest) -> Self {
+        cln::InvoicerequestRequest {
+            amount: Some(cln::Amount {
+                msat: req.amount_msat,
+            }),
+            description: req.description,
+            issuer: req.issuer,
+            label: req.label,
+            absolute_expiry: req.absolute_expiry,
+            single_use: req.single_use,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateInvoiceRequestResponse {
+    pub invreq_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    pub label: Option<String>,
+}
+
+impl From<cln::InvoicerequestResponse> for CreateInvoiceRequestResponse {
+    fn from(response: cln::InvoicerequestResponse) -> Self {
+        CreateInvoiceRequestResponse {
+            invreq_id: hex::encode(response.invreq_id),
+            active: response.active,
+            single_use: response.single_use,
+            bolt12: response.bolt12,
+            used: response.used,
+            label: response.label,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListInvoiceRequestsRequest {
+    pub invreq_id: Option<String>,
+    pub active_only: Option<bool>,
+}
+
+impl TryFrom<ListInvoiceRequestsRequest> for cln::ListinvoicerequestsRequest {
+    type Error = SdkError;
+
+    fn try_from(req: ListInvoiceRequestsRequest) -> Result<Self> {
+        Ok(cln::ListinvoicerequestsRequest {
+            invreq_id: req
+                .invreq_id
+                .map(hex::decode)
+                .transpose()
+                .context("invoice request id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            active_only: req.active_only,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListInvoiceRequestsInvoiceRequest {
+    pub invreq_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    pub label: Option<String>,
+}
+
+impl From<cln::ListinvoicerequestsInvoicerequests> for ListInvoiceRequestsInvoiceRequest {
+    fn from(invreq: cln::ListinvoicerequestsInvoicerequests) -> Self {
+        ListInvoiceRequestsInvoiceRequest {
+            invreq_id: hex::encode(invreq.invreq_id),
+            active: invreq.active,
+            single_use: invreq.single_use,
+            bolt12: invreq.bolt12,
+            used: invreq.used,
+            label: invreq.label,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListInvoiceRequestsResponse {
+    pub invoice_requests: Vec<ListInvoiceRequestsInvoiceRequest>,
+}
+
+impl From<cln::ListinvoicerequestsResponse> for ListInvoiceRequestsResponse {
+    fn from(response: cln::ListinvoicerequestsResponse) -> Self {
+        ListInvoiceRequestsResponse {
+            invoice_requests: response
+                .invoicerequests
+                .into_iter()
+                .map(ListInvoiceRequestsInvoiceRequest::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DisableInvoiceRequestRequest {
+    pub invreq_id: String,
+}
+
+impl From<DisableInvoiceRequestRequest> for cln::DisableinvoicerequestRequest {
+    fn from(req: DisableInvoiceRequestRequest) -> Self {
+        cln::DisableinvoicerequestRequest {
+            invreq_id: req.invreq_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DisableInvoiceRequestResponse {
+    pub invreq_id: String,
+    pub active: bool,
+    pub single_use: bool,
+    pub bolt12: String,
+    pub used: bool,
+    pub label: Option<String>,
+}
+
+impl From<cln::DisableinvoicerequestResponse> for DisableInvoiceRequestResponse {
+    fn from(response: cln::DisableinvoicerequestResponse) -> Self {
+        DisableInvoiceRequestResponse {
+            invreq_id: hex::encode(response.invreq_id),
+            active: response.active,
+            single_use: response.single_use,
+            bolt12: response.bolt12,
+            used: response.used,
+            label: response.label,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FunderPolicy {
+    Match,
+    Available,
+    Fixed,
+}
+
+impl From<FunderPolicy> for cln::funderupdate_request::FunderupdatePolicy {
+    fn from(p: FunderPolicy) -> Self {
+        match p {
+            FunderPolicy::Match => cln::funderupdate_request::FunderupdatePolicy::Match,
+            FunderPolicy::Available => cln::funderupdate_request::FunderupdatePolicy::Available,
+            FunderPolicy::Fixed => cln::funderupdate_request::FunderupdatePolicy::Fixed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunderUpdateRequest {
+    pub policy: Option<FunderPolicy>,
+    pub policy_mod: Option<u64>,
+    pub leases_only: Option<bool>,
+    pub min_their_funding_msat: Option<u64>,
+    pub max_their_funding_msat: Option<u64>,
+    pub per_channel_min_msat: Option<u64>,
+    pub per_channel_max_msat: Option<u64>,
+    pub reserve_tank_msat: Option<u64>,
+    pub fuzz_percent: Option<u32>,
+    pub fund_probability: Option<u32>,
+    pub lease_fee_base_msat: Option<u64>,
+    pub lease_fee_basis: Option<u32>,
+    pub funding_weight: Option<u32>,
+    pub channel_fee_max_base_msat: Option<u64>,
+    pub channel_fee_max_proportional_thousandths: Option<u32>,
+    pub compact_lease: Option<String>,
+}
+
+impl TryFrom<FunderUpdateRequest> for cln::FunderupdateRequest {
+    type Error = SdkError;
+
+    fn try_from(req: FunderUpdateRequest) -> Result<Self> {
+        Ok(cln::FunderupdateRequest {
+            policy: req
+                .policy
+                .map(cln::funderupdate_request::FunderupdatePolicy::from)
+                .map(|p| p as i32),
+            policy_mod: req.policy_mod,
+            leases_only: req.leases_only,
+            min_their_funding_msat: req.min_their_funding_msat.map(|a| cln::Amount { msat: a }),
+            max_their_funding_msat: req.max_their_funding_msat.map(|a| cln::Amount { msat: a }),
+            per_channel_min_msat: req.per_channel_min_msat.map(|a| cln::Amount { msat: a }),
+            per_channel_max_msat: req.per_channel_max_msat.map(|a| cln::Amount { msat: a }),
+            reserve_tank_msat: req.reserve_tank_msat.map(|a| cln::Amount { msat: a }),
+            fuzz_percent: req.fuzz_percent,
+            fund_probability: req.fund_probability,
+            lease_fee_base_msat: req.lease_fee_base_msat.map(|a| cln::Amount { msat: a }),
+            lease_fee_basis: req.lease_fee_basis,
+            funding_weight: req.funding_weight,
+            channel_fee_max_base_msat: req
+                .channel_fee_max_base_msat
+                .map(|a| cln::Amount { msat: a }),
+            channel_fee_max_proportional_thousandths: req.channel_fee_max_proportional_thousandths,
+            compact_lease: req
+                .compact_lease
+                .map(hex::decode)
+                .transpose()
+                .context("compact lease contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunderUpdateResponse {
+    pub summary: String,
+    pub policy: i32,
+    pub policy_mod: u32,
+    pub leases_only: bool,
+    pub min_their_funding_msat: Option<u64>,
+    pub max_their_funding_msat: Option<u64>,
+    pub per_channel_min_msat: Option<u64>,
+    pub per_channel_max_msat: Option<u64>,
+    pub reserve_tank_msat: Option<u64>,
+    pub fuzz_percent: u32,
+    pub fund_probability: u32,
+    pub lease_fee_base_msat: Option<u64>,
+    pub lease_fee_basis: Option<u32>,
+    pub funding_weight: Option<u32>,
+    pub channel_fee_max_base_msat: Option<u64>,
+    pub channel_fee_max_proportional_thousandths: Option<u32>,
+    pub compact_lease: Option<String>,
+}
+
+impl From<cln::FunderupdateResponse> for FunderUpdateResponse {
+    fn from(response: cln::FunderupdateResponse) -> Self {
+        FunderUpdateResponse {
+            summary: response.summary,
+            policy: response.policy,
+            policy_mod: response.policy_mod,
+            leases_only: response.leases_only,
+            min_their_funding_msat: response.min_their_funding_msat.map(|a| a.msat),
+            max_their_funding_msat: response.max_their_funding_msat.map(|a| a.msat),
+            per_channel_min_msat: response.per_channel_min_msat.map(|a| a.msat),
+            per_channel_max_msat: response.per_channel_max_msat.map(|a| a.msat),
+            reserve_tank_msat: response.reserve_tank_msat.map(|a| a.msat),
+            fuzz_percent: response.fuzz_percent,
+            fund_probability: response.fund_probability,
+            lease_fee_base_msat: response.lease_fee_base_msat.map(|a| a.msat),
+            lease_fee_basis: response.lease_fee_basis,
+            funding_weight: response.funding_weight,
+            channel_fee_max_base_msat: response.channel_fee_max_base_msat.map(|a| a.msat),
+            channel_fee_max_proportional_thousandths: response
+                .channel_fee_max_proportional_thousandths,
+            compact_lease: response.compact_lease.map(hex::encode),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LeaseChannelRequest {
+    pub node_id: String,
+    pub lease_amount_msat: u64,
+    pub max_lease_fee_msat: u64,
+    pub our_amount_msat: u64,
+    pub announce: Option<bool>,
+    pub minconf: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LeaseChannelResponse {
+    pub txid: String,
+    pub lease_fee_msat: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetAppearanceRequest {
+    pub alias: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchInvoiceRequest {
+    pub offer: String,
+    pub amount_msat: Option<u64>,
+    pub payer_note: Option<String>,
+    pub quantity: Option<u64>,
+}
+
+impl From<FetchInvoiceRequest> for cln::FetchinvoiceRequest {
+    fn from(req: FetchInvoiceRequest) -> Self {
+        cln::FetchinvoiceRequest {
+            offer: req.offer,
+            amount_msat: req.amount_msat.map(|a| cln::Amount { msat: a }),
+            payer_note: req.payer_note,
+            quantity: req.quantity,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchInvoiceChanges {
+    pub description_appended: Option<String>,
+    pub description: Option<String>,
+    pub vendor_removed: Option<String>,
+    pub vendor: Option<String>,
+    pub amount_msat: Option<u64>,
+}
+
+impl From<cln::FetchinvoiceChanges> for FetchInvoiceChanges {
+    fn from(changes: cln::FetchinvoiceChanges) -> Self {
+        FetchInvoiceChanges {
+            description_appended: changes.description_appended,
+            description: changes.description,
+            vendor_removed: changes.vendor_removed,
+            vendor: changes.vendor,
+            amount_msat: changes.amount_msat.map(|a| a.msat),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchInvoiceResponse {
+    pub invoice: String,
+    pub changes: Option<FetchInvoiceChanges>,
+    pub amount_msat: Option<u64>,
+    pub payment_hash: Option<String>,
+    pub description: Option<String>,
+    pub issuer: Option<String>,
+    pub payer_note: Option<String>,
+    pub quantity: Option<u64>,
+    pub node_id: Option<String>,
+    pub created_at: Option<u64>,
+    pub relative_expiry: Option<u32>,
+}
+
+impl FetchInvoiceResponse {
+    fn new(fetched: cln::FetchinvoiceResponse, decoded: cln::DecodeResponse) -> Self {
+        FetchInvoiceResponse {
+            invoice: fetched.invoice,
+            changes: fetched.changes.map(FetchInvoiceChanges::from),
+            amount_msat: decoded.invoice_amount_msat.map(|a| a.msat),
+            payment_hash: decoded.invoice_payment_hash.map(hex::encode),
+            description: decoded.offer_description,
+            issuer: decoded.offer_issuer,
+            payer_note: decoded.invreq_payer_note,
+            quantity: decoded.invreq_quantity,
+            node_id: decoded.invoice_node_id.map(hex::encode),
+            created_at: decoded.invoice_created_at,
+            relative_expiry: decoded.invoice_relative_expiry,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum PendingChannelState {
+    Opening,
+    AwaitingLockin,
+    DualFundingInit,
+    DualFundingAwaitingLockin,
+}
+
+impl PendingChannelState {
+    fn from_state(state: i32) -> Option<Self> {
+        use cln::ChannelState;
+
+        match ChannelState::from_i32(state)? {
+            ChannelState::Openingd => Some(PendingChannelState::Opening),
+            ChannelState::ChanneldAwaitingLockin => Some(PendingChannelState::AwaitingLockin),
+            ChannelState::DualopendOpenInit => Some(PendingChannelState::DualFundingInit),
+            ChannelState::DualopendAwaitingLockin => {
+                Some(PendingChannelState::DualFundingAwaitingLockin)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A channel that has been negotiated but can't be used yet.
+#[derive(Clone, Debug)]
+pub struct PendingChannel {
+    pub peer_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub funding_txid: Option<String>,
+    pub state: PendingChannelState,
+    pub total_msat: Option<u64>,
+    pub to_us_msat: Option<u64>,
+    /// Zero until the funding transaction is mined.
+    pub confirmations: u32,
+}
+
+impl PendingChannel {
+    fn new(channel: &ListPeerChannelsChannel, block_height: u32) -> Option<Self> {
+        let state = PendingChannelState::from_state(channel.state?)?;
+
+        // The short channel id is assigned once the funding transaction is
+        // mined and starts with the height of the block it is in.
+        let confirmations = channel
+            .short_channel_id
+            .as_deref()
+            .and_then(|scid| scid.split('x').next()?.parse::<u32>().ok())
+            .map(|funding_height| (block_height + 1).saturating_sub(funding_height))
+            .unwrap_or(0);
+
+        Some(PendingChannel {
+            peer_id: channel.peer_id.clone(),
+            channel_id: channel.channel_id.clone(),
+            funding_txid: channel.funding_txid.clone(),
+            state,
+            total_msat: channel.total_msat,
+            to_us_msat: channel.to_us_msat,
+            confirmations,
+        })
+    }
+}
+
+/// Which index a `Page` walks. Items are numbered from 1, in the order they
+/// were created or last updated.
+#[derive(Copy, Clone, Debug)]
+pub enum PageIndex {
+    Created,
+    Updated,
+}
+
+impl From<PageIndex> for ListInvoicesIndex {
+    fn from(index: PageIndex) -> Self {
+        match index {
+            PageIndex::Created => ListInvoicesIndex::Created,
+            PageIndex::Updated => ListInvoicesIndex::Updated,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Page {
+    pub index: Option<PageIndex>,
+    pub start: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+/// One page of a list API. Pass `next_start` as the `start` of the next
+/// `Page` to continue; it is `None` once there is nothing left.
+#[derive(Clone, Debug)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub next_start: Option<u64>,
+}
+
+impl<T> Paged<T> {
+    fn new(items: Vec<T>, limit: Option<u32>, last_index: impl Fn(&T) -> Option<u64>) -> Self {
+        let next_start = match (limit, items.last()) {
+            (Some(limit), Some(last)) if items.len() >= limit as usize => {
+                last_index(last).map(|i| i + 1)
+            }
+            _ => None,
+        };
+        Paged { items, next_start }
+    }
+}
+
+pub type PagedInvoices = Paged<ListInvoicesInvoice>;
+pub type PagedPayments = Paged<ListPaymentsPayment>;
+pub type PagedForwards = Paged<Forward>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ForwardStatus {
+    Offered,
+    Settled,
+    LocalFailed,
+    Failed,
+}
+
+impl From<ForwardStatus> for cln::listforwards_request::ListforwardsStatus {
+    fn from(s: ForwardStatus) -> Self {
+        use cln::listforwards_request::ListforwardsStatus;
+
+        match s {
+            ForwardStatus::Offered => ListforwardsStatus::Offered,
+            ForwardStatus::Settled => ListforwardsStatus::Settled,
+            ForwardStatus::LocalFailed => ListforwardsStatus::LocalFailed,
+            ForwardStatus::Failed => ListforwardsStatus::Failed,
+        }
+    }
+}
+
+impl ForwardStatus {
+    fn from_i32(status: i32) -> Option<Self> {
+        use cln::listforwards_forwards::ListforwardsForwardsStatus;
+
+        match ListforwardsForwardsStatus::from_i32(status)? {
+            ListforwardsForwardsStatus::Offered => Some(ForwardStatus::Offered),
+            ListforwardsForwardsStatus::Settled => Some(ForwardStatus::Settled),
+            ListforwardsForwardsStatus::LocalFailed => Some(ForwardStatus::LocalFailed),
+            ListforwardsForwardsStatus::Failed => Some(ForwardStatus::Failed),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListForwardsRequest {
+    pub status: Option<ForwardStatus>,
+    pub in_channel: Option<String>,
+    pub out_channel: Option<String>,
+}
+
+/// An HTLC the node was asked to forward. Times are unix seconds with
+/// sub-second precision.
+#[derive(Clone, Debug)]
+pub struct Forward {
+    pub in_channel: String,
+    pub in_htlc_id: Option<u64>,
+    pub out_channel: Option<String>,
+    pub out_htlc_id: Option<u64>,
+    pub status: Option<ForwardStatus>,
+    pub in_msat: Option<u64>,
+    pub out_msat: Option<u64>,
+    pub fee_msat: Option<u64>,
+    pub received_time: f64,
+    pub resolved_time: Option<f64>,
+    /// How long the HTLC was held, once resolved.
+    pub resolution_ms: Option<u64>,
+    /// The BOLT 4 failure code, for forwards that failed.
+    pub failcode: Option<u32>,
+    pub failreason: Option<String>,
+    pub created_index: Option<u64>,
+    pub updated_index: Option<u64>,
+}
+
+impl From<cln::ListforwardsForwards> for Forward {
+    fn from(forward: cln::ListforwardsForwards) -> Self {
+        Forward {
+            in_channel: forward.in_channel,
+            in_htlc_id: forward.in_htlc_id,
+            out_channel: forward.out_channel,
+            out_htlc_id: forward.out_htlc_id,
+            status: ForwardStatus::from_i32(forward.status),
+            in_msat: forward.in_msat.map(|a| a.msat),
+            out_msat: forward.out_msat.map(|a| a.msat),
+            fee_msat: forward.fee_msat.map(|a| a.msat),
+            received_time: forward.received_time,
+            resolved_time: forward.resolved_time,
+            resolution_ms: forward
+                .resolved_time
+                .map(|resolved| ((resolved - forward.received_time).max(0.0) * 1000.0) as u64),
+            failcode: forward.failcode,
+            failreason: forward.failreason,
+            created_index: forward.created_index,
+            updated_index: forward.updated_index,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListPeersRequest {
+    pub id: Option<String>,
+}
+
+impl TryFrom<ListPeersRequest> for cln::ListpeersRequest {
+    type Error = SdkError;
+
+    fn try_from(req: ListPeersRequest) -> Result<Self> {
+        Ok(cln::ListpeersRequest {
+            id: req
+                .id
+                .map(hex::decode)
+                .transpose()
+                .context("peer id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            level: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListPeersPeer {
+    pub id: String,
+    pub connected: bool,
+    pub netaddr: Vec<String>,
+    pub features: Option<String>,
+    pub feature_names: Vec<String>,
+}
+
+impl From<cln::ListpeersPeers> for ListPeersPeer {
+    fn from(peer: cln::ListpeersPeers) -> Self {
+        ListPeersPeer {
+            id: hex::encode(peer.id),
+            connected: peer.connected,
+            netaddr: peer.netaddr,
+            feature_names: peer
+                .features
+                .as_deref()
+                .map(features::feature_names)
+                .unwrap_or_default(),
+            features: peer.features.map(hex::encode),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListPeersResponse {
+    pub peers: Vec<ListPeersPeer>,
+}
+
+impl From<cln::ListpeersResponse> for ListPeersResponse {
+    fn from(response: cln::ListpeersResponse) -> Self {
+        ListPeersResponse {
+            peers: response
+                .peers
+                .into_iter()
+                .map(ListPeersPeer::from)
+                .collect(),
+        }
+    }
+}
+
+/// What opening a channel is expected to cost, before calling
+/// `fund_channel`.
+#[derive(Clone, Debug)]
+pub struct ChannelOpenEstimate {
+    pub amount_sat: u64,
+    pub feerate_perkw: u32,
+    pub funding_weight: u64,
+    pub funding_fee_sat: u64,
+    /// Held back by the peer and not spendable while the channel is open.
+    pub reserve_sat: u64,
+    /// Leaves the on-chain wallet: `amount_sat` plus `funding_fee_sat`.
+    pub total_cost_sat: u64,
+    /// What can be sent over the channel once it is open.
+    pub spendable_sat: u64,
+}
+
+impl ChannelOpenEstimate {
+    /// Weight CLN assumes for a funding transaction with one input and
+    /// change.
+    const FUNDING_WEIGHT: u64 = 702;
+    const DUST_LIMIT_SAT: u64 = 546;
+
+    fn new(amount_sat: u64, feerate_perkw: u32) -> Self {
+        let funding_fee_sat = feerate_perkw as u64 * Self::FUNDING_WEIGHT / 1000;
+        let reserve_sat = (amount_sat / 100).max(Self::DUST_LIMIT_SAT).min(amount_sat);
+
+        ChannelOpenEstimate {
+            amount_sat,
+            feerate_perkw,
+            funding_weight: Self::FUNDING_WEIGHT,
+            funding_fee_sat,
+            reserve_sat,
+            total_cost_sat: amount_sat + funding_fee_sat,
+            spendable_sat: amount_sat - reserve_sat,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WaitBlockHeightRequest {
+    pub block_height: u32,
+    pub timeout: Option<u32>,
+}
+
+impl From<WaitBlockHeightRequest> for cln::WaitblockheightRequest {
+    fn from(req: WaitBlockHeightRequest) -> Self {
+        cln::WaitblockheightRequest {
+            blockheight: req.block_height,
+            timeout: req.timeout,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WaitBlockHeightResponse {
+    pub block_height: u32,
+}
+
+impl From<cln::WaitblockheightResponse> for WaitBlockHeightResponse {
+    fn from(response: cln::WaitblockheightResponse) -> Self {
+        WaitBlockHeightResponse {
+            block_height: response.blockheight,
+        }
+    }
+}
+
+/// Whether the node's view of the chain is current. While `synced` is false
+/// `block_height` may lag behind the chain tip.
+#[derive(Clone, Debug)]
+pub struct ChainStatus {
+    pub block_height: u32,
+    pub synced: bool,
+    pub warning_bitcoind_sync: Option<String>,
+    pub warning_lightningd_sync: Option<String>,
+}
+
+impl From<cln::GetinfoResponse> for ChainStatus {
+    fn from(info: cln::GetinfoResponse) -> Self {
+        ChainStatus {
+            block_height: info.blockheight,
+            synced: info.warning_bitcoind_sync.is_none() && info.warning_lightningd_sync.is_none(),
+            warning_bitcoind_sync: info.warning_bitcoind_sync,
+            warning_lightningd_sync: info.warning_lightningd_sync,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListAddressesAddress {
+    pub address: String,
+    pub created_at: u64,
+    /// Whether the address ever received funds.
+    pub used: bool,
+    pub received_msat: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ListAddressesResponse {
+    pub addresses: Vec<ListAddressesAddress>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UnifiedReceiveRequest {
+    /// Leave unset to let the sender choose the amount.
+    pub amount_msat: Option<u64>,
+    pub description: String,
+}
+
+/// Every way to receive the same payment, for a single receive QR code.
+#[derive(Clone, Debug)]
+pub struct UnifiedReceiveResponse {
+    pub bolt11: String,
+    pub payment_hash: String,
+    pub offer_id: String,
+    pub bolt12: String,
+    pub address: String,
+    /// BIP 21 URI combining all of the above.
+    pub uri: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct SignInvoiceRequest {
+    pub invstring: String,
+}
+
+impl From<SignInvoiceRequest> for cln::SigninvoiceRequest {
+    fn from(req: SignInvoiceRequest) -> Self {
+        cln::SigninvoiceRequest {
+            invstring: req.invstring,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SignInvoiceResponse {
+    pub bolt11: String,
+}
+
+impl From<cln::SigninvoiceResponse> for SignInvoiceResponse {
+    fn from(response: cln::SigninvoiceResponse) -> Self {
+        SignInvoiceResponse {
+            bolt11: response.bolt11,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VoidInvoiceRequest {
+    pub label: String,
+}
+
+impl From<VoidInvoiceRequest> for cln::DelinvoiceRequest {
+    fn from(req: VoidInvoiceRequest) -> Self {
+        cln::DelinvoiceRequest {
+            label: req.label,
+            status: cln::delinvoice_request::DelinvoiceStatus::Unpaid as i32,
+            desconly: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VoidInvoiceResponse {
+    pub label: String,
+    pub payment_hash: String,
+}
+
+impl From<cln::DelinvoiceResponse> for VoidInvoiceResponse {
+    fn from(response: cln::DelinvoiceResponse) -> Self {
+        VoidInvoiceResponse {
+            label: response.label,
+            payment_hash: hex::encode(response.payment_hash),
+        }
+    }
+}
+
+/// Round trip times in milliseconds, nearest-rank percentiles.
+#[derive(Clone, Debug)]
+pub struct LatencyPercentiles {
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyPercentiles {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let percentile = |p: usize| {
+            let rank = (samples.len() * p).div_ceil(100).max(1);
+            samples[rank - 1].as_millis() as u64
+        };
+
+        LatencyPercentiles {
+            p50_ms: percentile(50),
+            p90_ms: percentile(90),
+            max_ms: percentile(100),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SchedulerLatency {
+    pub samples: u32,
+    pub scheduler: LatencyPercentiles,
+    pub node: LatencyPercentiles,
+    /// Where the scheduler placed the node, which tells which region the
+    /// node calls go to.
+    pub node_uri: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelEarningsRequest {
+    /// Only report this channel (short channel id), or all channels if unset.
+    pub short_channel_id: Option<String>,
+    /// Forwards resolved at or after this unix timestamp.
+    pub from: Option<u64>,
+    /// Forwards resolved before this unix timestamp.
+    pub to: Option<u64>,
+}
+
+/// Settled forwards through one channel. Routing fees are credited to the
+/// outgoing channel, since that is the channel whose fee policy was paid.
+#[derive(Clone, Debug, Default)]
+pub struct ChannelEarnings {
+    pub short_channel_id: String,
+    pub fees_msat: u64,
+    pub forwards_out: u32,
+    pub volume_out_msat: u64,
+    pub forwards_in: u32,
+    pub volume_in_msat: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelEarningsResponse {
+    pub channels: Vec<ChannelEarnings>,
+}
+
+impl ChannelEarningsResponse {
+    fn new(forwards: Vec<cln::ListforwardsForwards>, req: &ChannelEarningsRequest) -> Self {
+        fn entry<'a>(
+            channels: &'a mut BTreeMap<String, ChannelEarnings>,
+            short_channel_id: &str,
+        ) -> &'a mut ChannelEarnings {
+            channels
+                .entry(short_channel_id.to_string())
+                .or_insert_with(|| ChannelEarnings {
+                    short_channel_id: short_channel_id.to_string(),
+                    ..Default::default()
+                })
+        }
+
+        let mut channels = BTreeMap::new();
+
+        let in_range = |forward: &cln::ListforwardsForwards| {
+            let resolved_at = forward.resolved_time.unwrap_or(forward.received_time) as u64;
+            req.from.map_or(true, |from| resolved_at >= from)
+                && req.to.map_or(true, |to| resolved_at < to)
+        };
+
+        for forward in forwards.iter().filter(|f| in_range(f)) {
+            let wanted = |id: &str| req.short_channel_id.as_deref().map_or(true, |c| c == id);
+
+            if wanted(&forward.in_channel) {
+                let channel = entry(&mut channels, &forward.in_channel);
+                channel.forwards_in += 1;
+                channel.volume_in_msat += forward.in_msat.as_ref().map_or(0, |a| a.msat);
+            }
+
+            if let Some(out_channel) = forward.out_channel.as_deref().filter(|c| wanted(c)) {
+                let channel = entry(&mut channels, out_channel);
+                channel.forwards_out += 1;
+                channel.volume_out_msat += forward.out_msat.as_ref().map_or(0, |a| a.msat);
+                channel.fees_msat += forward.fee_msat.as_ref().map_or(0, |a| a.msat);
+            }
+        }
+
+        ChannelEarningsResponse {
+            channels: channels.into_values().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PayWithRetryRequest {
+    pub bolt11: String,
+    /// Defaults to 3.
+    pub max_attempts: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PaymentAttempt {
+    /// Channels excluded from routing for this attempt, as `scid/direction`.
+    pub excluded: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PayWithRetryResponse {
+    /// Set when one of the attempts succeeded.
+    pub payment: Option<PayResponse>,
+    pub attempts: Vec<PaymentAttempt>,
+}
+
+/// The `data` of a failed `pay`, naming the hop the last attempt failed at.
+#[derive(Deserialize)]
+struct PayErrorData {
+    erring_channel: String,
+    erring_direction: u32,
+}
+
+/// Finds the channel a failed payment attempt erred at, as `scid/direction`.
+fn erring_channel(e: &anyhow::Error) -> Option<String> {
+    let data: PayErrorData = serde_json::from_value(rpc_error(e)?.data?).ok()?;
+    Some(format!("{}/{}", data.erring_channel, data.erring_direction))
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum HtlcDirection {
+    In,
+    Out,
+}
+
+#[derive(Clone, Debug)]
+pub struct ListHtlcsRequest {
+    /// Only HTLCs of this channel (short channel id or channel id).
+    pub id: Option<String>,
+}
+
+impl From<ListHtlcsRequest> for cln::ListhtlcsRequest {
+    fn from(req: ListHtlcsRequest) -> Self {
+        cln::ListhtlcsRequest { id: req.id }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListHtlcsHtlc {
+    pub short_channel_id: String,
+    pub id: u64,
+    pub expiry: u32,
+    pub amount_msat: Option<u64>,
+    pub direction: HtlcDirection,
+    pub payment_hash: String,
+    /// The HTLC's state in the commitment dance as named by CLN, e.g.
+    /// `RCVD_REMOVE_ACK_REVOCATION` once an incoming HTLC is fully resolved.
+    pub state: String,
+}
+
+impl From<cln::ListhtlcsHtlcs> for ListHtlcsHtlc {
+    fn from(htlc: cln::ListhtlcsHtlcs) -> Self {
+        let direction =
+            match cln::listhtlcs_htlcs::ListhtlcsHtlcsDirection::from_i32(htlc.direction) {
+                Some(cln::listhtlcs_htlcs::ListhtlcsHtlcsDirection::In) => HtlcDirection::In,
+                _ => HtlcDirection::Out,
+            };
+
+        ListHtlcsHtlc {
+            short_channel_id: htlc.short_channel_id,
+            id: htlc.id,
+            expiry: htlc.expiry,
+            amount_msat: htlc.amount_msat.map(|a| a.msat),
+            direction,
+            payment_hash: hex::encode(htlc.payment_hash),
+            state: cln::HtlcState::from_i32(htlc.state)
+                .map(|s| s.as_str_name().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListHtlcsResponse {
+    pub htlcs: Vec<ListHtlcsHtlc>,
+}
+
+impl From<cln::ListhtlcsResponse> for ListHtlcsResponse {
+    fn from(response: cln::ListhtlcsResponse) -> Self {
+        ListHtlcsResponse {
+            htlcs: response.htlcs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// How long a CLTV recommendation is reused. Blocks come every ten minutes on
+/// average, and the delta is relative to the tip anyway.
+const CLTV_RECOMMENDATION_TTL: Duration = Duration::from_secs(60);
+
+/// How long a peer's alias from gossip is reused. Nodes rarely rename
+/// themselves.
+const PEER_ALIAS_TTL: Duration = Duration::from_secs(3600);
+
+/// Invoice counts by status as of the `created` and `updated` invoice
+/// indexes, for counting incrementally. Only unpaid invoices can still change
+/// status, so only theirs are remembered.
+#[derive(Clone, Debug, Default)]
+struct InvoiceCounts {
+    created: u64,
+    updated: u64,
+    deleted: u64,
+    by_status: HashMap<i32, u64>,
+    /// Created indexes of the invoices counted as unpaid.
+    unpaid: HashSet<u64>,
+}
+
+impl InvoiceCounts {
+    const UNPAID: i32 = cln::listinvoices_invoices::ListinvoicesInvoicesStatus::Unpaid as i32;
+
+    /// Counts an invoice listed by its created index.
+    fn add(&mut self, invoice: &cln::ListinvoicesInvoices) {
+        let created_index = invoice.created_index.unwrap_or_default();
+        self.created = self.created.max(created_index);
+        *self.by_status.entry(invoice.status).or_default() += 1;
+        if invoice.status == Self::UNPAID {
+            self.unpaid.insert(created_index);
+        }
+    }
+
+    /// Moves an invoice listed by its updated index out of unpaid, if it
+    /// was counted as unpaid.
+    fn update(&mut self, invoice: &cln::ListinvoicesInvoices) {
+        self.updated = self.updated.max(invoice.updated_index.unwrap_or_default());
+        let Some(created_index) = invoice.created_index else {
+            return;
+        };
+        if invoice.status != Self::UNPAID && self.unpaid.remove(&created_index) {
+            *self.by_status.entry(Self::UNPAID).or_default() -= 1;
+            *self.by_status.entry(invoice.status).or_default() += 1;
+        }
+    }
+
+    fn count(&self, status: Option<i32>) -> u64 {
+        match status {
+            Some(status) => self.by_status.get(&status).copied().unwrap_or_default(),
+            None => self.by_status.values().sum(),
+        }
+    }
+}
+
+/// A final CLTV delta for new invoices, relative to `block_height`.
+#[derive(Clone, Debug)]
+pub struct CltvRecommendation {
+    pub block_height: u32,
+    pub cltv: u32,
+}
+
+impl CltvRecommendation {
+    /// CLN's default `cltv-final`.
+    const MIN_FINAL_CLTV: u32 = 18;
+    /// Room for blocks found while the payment is in flight, and for payers
+    /// whose view of the chain differs from the node's.
+    const BLOCK_MARGIN: u32 = 6;
+    /// A day of blocks. Peer deltas above this are outliers, and following
+    /// them would eat into the route budget payers cap at 2016 blocks.
+    const MAX_PEER_DELTA: u32 = 144;
+
+    /// The delta is built up as
+    ///
+    /// `max(MIN_FINAL_CLTV, largest peer delta) + BLOCK_MARGIN`
+    /// `+ BLOCK_MARGIN` while the node is still syncing
+    /// `+ min(shortfall, MIN_FINAL_CLTV)` for pending HTLCs
+    ///
+    /// where a peer delta is the `cltv_expiry_delta` a peer announced for
+    /// its channel to us (`peer_deltas`), which is how long it expects to
+    /// need to resolve an HTLC on-chain; ours shouldn't be shorter. The
+    /// shortfall is how far the closest pending HTLC expiry is inside the
+    /// delta so far.
+    fn new(
+        info: &cln::GetinfoResponse,
+        channels: &[cln::ListpeerchannelsChannels],
+        peer_deltas: &[u32],
+    ) -> Self {
+        let block_height = info.blockheight;
+        let peer_delta = peer_deltas
+            .iter()
+            .map(|delta| (*delta).min(Self::MAX_PEER_DELTA))
+            .max()
+            .unwrap_or_default();
+        let mut cltv = Self::MIN_FINAL_CLTV.max(peer_delta) + Self::BLOCK_MARGIN;
+
+        // A node catching up with the chain sees HTLCs expire sooner than
+        // the payer expects.
+        if info.warning_bitcoind_sync.is_some() || info.warning_lightningd_sync.is_some() {
+            cltv += Self::BLOCK_MARGIN;
+        }
+
+        // HTLCs that are still pending close to their expiry show how long
+        // resolving them currently takes; leave at least that much room.
+        let tightest = channels
+            .iter()
+            .flat_map(|c| c.htlcs.iter())
+            .filter_map(|h| h.expiry)
+            .map(|expiry| expiry.saturating_sub(block_height))
+            .min();
+        if let Some(remaining) = tightest {
+            cltv += cltv.saturating_sub(remaining).min(Self::MIN_FINAL_CLTV);
+        }
+
+        CltvRecommendation { block_height, cltv }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Lsps0Request {
+    pub peer_id: String,
+    pub method: String,
+    /// JSON encoded params object.
+    pub params: String,
+    /// Defaults to 60 seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Lsps0Response {
+    /// JSON encoded result.
+    pub result: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiFundChannelDestination {
+    /// Node id, optionally followed by `@host:port`.
+    pub id: String,
+    pub amount_msat: Option<u64>,
+    pub announce: Option<bool>,
+}
+
+impl From<MultiFundChannelDestination> for cln::MultifundchannelDestinations {
+    fn from(destination: MultiFundChannelDestination) -> Self {
+        cln::MultifundchannelDestinations {
+            id: destination.id,
+            amount: destination.amount_msat.map(|a| cln::AmountOrAll {
+                value: Some(cln::amount_or_all::Value::Amount(cln::Amount { msat: a })),
+            }),
+            announce: destination.announce,
+            ..Default::default()
+        }
+    }
+}
+
+/// Opens channels to all `destinations` in a single funding transaction.
+/// Either every channel is opened or none is.
+#[derive(Clone, Debug)]
+pub struct MultiFundChannelRequest {
+    pub destinations: Vec<MultiFundChannelDestination>,
+}
+
+impl TryFrom<MultiFundChannelRequest> for cln::MultifundchannelRequest {
+    type Error = SdkError;
+
+    fn try_from(req: MultiFundChannelRequest) -> Result<Self> {
+        if req.destinations.is_empty() {
+            return Err(SdkError::InvalidArgument {
+                message: String::from("at least one destination is required"),
+                request_id: None,
+            });
+        }
+
+        Ok(cln::MultifundchannelRequest {
+            destinations: req.destinations.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiFundChannelChannel {
+    pub id: String,
+    pub channel_id: String,
+    pub outnum: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiFundChannelResponse {
+    pub txid: String,
+    pub channels: Vec<MultiFundChannelChannel>,
+    pub request_id: Option<String>,
+}
+
+impl From<cln::MultifundchannelResponse> for MultiFundChannelResponse {
+    fn from(response: cln::MultifundchannelResponse) -> Self {
+        MultiFundChannelResponse {
+            txid: hex::encode(response.txid),
+            channels: response
+                .channel_ids
+                .into_iter()
+                .map(|c| MultiFundChannelChannel {
+                    id: hex::encode(c.id),
+                    channel_id: hex::encode(c.channel_id),
+                    outnum: c.outnum,
+                })
+                .collect(),
+            request_id: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PollInvoiceUpdatesResponse {
+    /// Invoices paid after the requested pay index, oldest first.
+    pub invoices: Vec<ListInvoicesInvoice>,
+    /// Pass this to the next poll; unchanged if nothing was paid.
+    pub last_pay_index: u64,
+}
+
+/// Where an activity entry came from. When two sources report the same
+/// entry, the later variant wins, as it is the more specific one: a keysend
+/// is also an invoice on the node. Funds a channel close sweeps back to the
+/// wallet are never listed, as the payments they settle already are.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ActivitySource {
+    Onchain,
+    Invoice,
+    Keysend,
+    Payment,
+}
+
+#[derive(Clone, Debug)]
+pub struct ActivityEntry {
+    /// Stable across calls: `ln-in-<payment hash>`, `ln-out-<payment hash>`
+    /// or `chain-<txid>`.
+    pub id: String,
+    pub source: ActivitySource,
+    pub incoming: bool,
+    pub amount_msat: Option<u64>,
+    pub timestamp: Option<u64>,
+    pub payment_hash: Option<String>,
+    pub txid: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ActivityEntry {
+    fn from_invoice(invoice: ListInvoicesInvoice) -> Self {
+        let source = if invoice.bolt11.is_none() && invoice.bolt12.is_none() {
+            ActivitySource::Keysend
+        } else {
+            ActivitySource::Invoice
+        };
+
+        ActivityEntry {
+            id: format!("ln-in-{}", invoice.payment_hash),
+            source,
+            incoming: true,
+            amount_msat: invoice.amount_received_msat.or(invoice.amount_msat),
+            timestamp: invoice.paid_at,
+            payment_hash: Some(invoice.payment_hash),
+            txid: None,
+            description: invoice.description,
+        }
+    }
+
+    fn from_payment(payment: ListPaymentsPayment) -> Self {
+        ActivityEntry {
+            id: format!("ln-out-{}", payment.payment_hash),
+            source: ActivitySource::Payment,
+            incoming: false,
+            amount_msat: payment.amount_sent_msat.or(payment.amount_msat),
+            timestamp: payment.completed_at.or(Some(payment.created_at)),
+            payment_hash: Some(payment.payment_hash),
+            txid: None,
+            description: payment.description,
+        }
+    }
+
+    /// The wallet's side of a deposit or withdrawal; `None` for channel
+    /// opens and closes, which move funds between the wallet and its
+    /// channels, and for transactions that don't touch the wallet.
+    fn from_transaction(tx: Transaction, timestamp: u64) -> Option<Self> {
+        let incoming = match tx.kind {
+            TransactionKind::Deposit => true,
+            TransactionKind::Withdrawal => false,
+            _ => return None,
+        };
+        // A withdrawal's own outputs are change, not part of what was sent.
+        let amount_msat = tx
+            .outputs
+            .iter()
+            .filter(|o| o.ours == incoming)
+            .map(|o| o.amount_msat)
+            .sum::<Option<u64>>();
+
+        Some(ActivityEntry {
+            id: format!("chain-{}", tx.txid),
+            source: ActivitySource::Onchain,
+            incoming,
+            amount_msat,
+            timestamp: Some(timestamp),
+            payment_hash: None,
+            txid: Some(tx.txid),
+            description: None,
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// What a liquidity ad charges for leasing `lease_amount_msat` when the
+/// funding transaction pays `feerate_perkw`: the base fee, the basis points
+/// on the amount and the seller's `funding_weight` at that feerate.
+fn lease_fee_msat(
+    will_fund: &cln::ListnodesNodesOptionWillFund,
+    lease_amount_msat: u64,
+    feerate_perkw: u32,
+) -> u64 {
+    let base_msat = will_fund.lease_fee_base_msat.as_ref().map_or(0, |a| a.msat);
+    let basis_msat = lease_amount_msat * will_fund.lease_fee_basis as u64 / 10_000;
+    // weight * sat per kiloweight / 1000 is sat, which is the same number
+    // as weight * sat per kiloweight in msat.
+    let funding_msat = will_fund.funding_weight as u64 * feerate_perkw as u64;
+    base_msat + basis_msat + funding_msat
+}
+
+/// When the block at `height` was mined, estimated at ten minutes a block
+/// back from the chain tip at `now`. Unconfirmed transactions get `now`.
+fn estimated_block_time(height: Option<u32>, tip: u32, now: u64) -> u64 {
+    let depth = height.map_or(0, |h| tip.saturating_sub(h));
+    now.saturating_sub(depth as u64 * 600)
+}
+
+#[derive(Clone, Debug)]
+pub struct ListActivityResponse {
+    /// Newest first.
+    pub entries: Vec<ActivityEntry>,
+}
+
+impl ListActivityResponse {
+    fn new(entries: impl Iterator<Item = ActivityEntry>, limit: Option<u32>) -> Self {
+        let mut unique: BTreeMap<String, ActivityEntry> = BTreeMap::new();
+        for entry in entries {
+            match unique.get(&entry.id) {
+                Some(existing) if existing.source >= entry.source => {}
+                _ => {
+                    unique.insert(entry.id.clone(), entry);
+                }
+            }
+        }
+
+        let mut entries: Vec<_> = unique.into_values().collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        entries.truncate(limit.map_or(usize::MAX, |l| l as usize));
+        ListActivityResponse { entries }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChannelStateStats {
+    pub count: u32,
+    pub total_msat: u64,
+    pub to_us_msat: u64,
+}
+
+impl ChannelStateStats {
+    fn add(&mut self, total_msat: Option<u64>, to_us_msat: Option<u64>) {
+        self.count += 1;
+        self.total_msat += total_msat.unwrap_or_default();
+        self.to_us_msat += to_us_msat.unwrap_or_default();
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChannelStats {
+    pub active: ChannelStateStats,
+    pub pending_open: ChannelStateStats,
+    pub pending_close: ChannelStateStats,
+    pub closed: ChannelStateStats,
+    /// Capacity of the largest and smallest active channel.
+    pub largest_channel_msat: Option<u64>,
+    pub smallest_channel_msat: Option<u64>,
+    /// CLN keeps no history of peer connections, so peer uptime is given as
+    /// the share of channel peers connected right now.
+    pub peers: u32,
+    pub connected_peers: u32,
+}
+
+impl ChannelStats {
+    fn new(
+        channels: Vec<ListPeerChannelsChannel>,
+        closed: Vec<cln::ListclosedchannelsClosedchannels>,
+    ) -> Self {
+        use cln::ChannelState;
+
+        let mut stats = ChannelStats::default();
+        let mut closed_ids = HashSet::new();
+        let mut peers = HashMap::new();
+
+        for channel in channels {
+            if let Some(peer_id) = &channel.peer_id {
+                let connected = peers.entry(peer_id.clone()).or_insert(false);
+                *connected |= channel.peer_connected.unwrap_or_default();
+            }
+
+            let state = channel.state.and_then(ChannelState::from_i32);
+            let bucket = match state {
+                Some(ChannelState::ChanneldNormal) => {
+                    if let Some(total_msat) = channel.total_msat {
+                        stats.largest_channel_msat =
+                            stats.largest_channel_msat.max(Some(total_msat));
+                        stats.smallest_channel_msat = Some(
+                            stats
+                                .smallest_channel_msat
+                                .map_or(total_msat, |s| s.min(total_msat)),
+                        );
+                    }
+                    &mut stats.active
+                }
+                Some(
+                    ChannelState::Openingd
+                    | ChannelState::ChanneldAwaitingLockin
+                    | ChannelState::DualopendOpenInit
+                    | ChannelState::DualopendAwaitingLockin,
+                ) => &mut stats.pending_open,
+                Some(
+                    ChannelState::ChanneldShuttingDown
+                    | ChannelState::ClosingdSigexchange
+                    | ChannelState::AwaitingUnilateral
+                    | ChannelState::FundingSpendSeen,
+                ) => &mut stats.pending_close,
+                // The closing transaction is out; only the outputs are left
+                // to resolve.
+                Some(ChannelState::ClosingdComplete | ChannelState::Onchain) => {
+                    closed_ids.extend(channel.channel_id.clone());
+                    &mut stats.closed
+                }
+                // Don't guess at states newer than this client.
+                _ => continue,
+            };
+            bucket.add(channel.total_msat, channel.to_us_msat);
+        }
+
+        // Closed channels stay in listpeerchannels until they are
+        // forgotten, don't count them twice.
+        for channel in closed {
+            let channel_id = hex::encode(&channel.channel_id);
+            if !closed_ids.contains(&channel_id) {
+                stats.closed.add(
+                    channel.total_msat.map(|a| a.msat),
+                    channel.final_to_us_msat.map(|a| a.msat),
+                );
+            }
+        }
+
+        stats.peers = peers.len() as u32;
+        stats.connected_peers = peers.values().filter(|c| **c).count() as u32;
+        stats
+    }
+}
+
+/// What an on-chain transaction did for the wallet, worked out from which of
+/// its inputs and outputs belong to the wallet or to a channel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// Pays to the wallet without spending from it.
+    Deposit,
+    /// Spends from the wallet to somewhere else.
+    Withdrawal,
+    /// Funds a channel, open or since closed.
+    ChannelOpen,
+    /// Spends a channel's funding output, cooperatively or not, or sweeps
+    /// the outputs of such a spend.
+    ChannelClose,
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionInput {
+    pub txid: String,
+    pub index: u32,
+    pub sequence: u32,
+    /// Spends an output of the node's wallet.
+    pub ours: bool,
+}
+
+impl From<cln::ListtransactionsTransactionsInputs> for TransactionInput {
+    fn from(input: cln::ListtransactionsTransactionsInputs) -> Self {
+        TransactionInput {
+            txid: hex::encode(input.txid),
+            index: input.index,
+            sequence: input.sequence,
+            ours: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionOutput {
+    pub index: u32,
+    pub amount_msat: Option<u64>,
+    pub scriptpubkey: String,
+    /// Pays to the node's wallet.
+    pub ours: bool,
+}
+
+impl From<cln::ListtransactionsTransactionsOutputs> for TransactionOutput {
+    fn from(output: cln::ListtransactionsTransactionsOutputs) -> Self {
+        TransactionOutput {
+            index: output.index,
+            amount_msat: output.amount_msat.map(|a| a.msat),
+            scriptpubkey: hex::encode(output.script_pub_key),
+            ours: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub txid: String,
+    pub rawtx: String,
+    /// `None` while unconfirmed.
+    pub blockheight: Option<u32>,
+    pub txindex: u32,
+    pub locktime: u32,
+    pub version: u32,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+    pub kind: TransactionKind,
+}
+
+impl From<cln::ListtransactionsTransactions> for Transaction {
+    fn from(tx: cln::ListtransactionsTransactions) -> Self {
+        Transaction {
+            txid: hex::encode(tx.hash),
+            rawtx: hex::encode(tx.rawtx),
+            blockheight: Some(tx.blockheight).filter(|&h| h > 0),
+            txindex: tx.txindex,
+            locktime: tx.locktime,
+            version: tx.version,
+            inputs: tx.inputs.into_iter().map(TransactionInput::from).collect(),
+            outputs: tx
+                .outputs
+                .into_iter()
+                .map(TransactionOutput::from)
+                .collect(),
+            kind: TransactionKind::Unknown,
+        }
+    }
+}
+
+impl Transaction {
+    /// Marks the inputs and outputs in `wallet` as ours and sets `kind`.
+    /// Both sets hold `(txid, output index)` pairs.
+    fn annotate(&mut self, wallet: &HashSet<(String, u32)>, funding: &HashSet<(String, u32)>) {
+        for input in &mut self.inputs {
+            input.ours = wallet.contains(&(input.txid.clone(), input.index));
+        }
+        for output in &mut self.outputs {
+            output.ours = wallet.contains(&(self.txid.clone(), output.index));
+        }
+
+        self.kind = if self
+            .outputs
+            .iter()
+            .any(|o| funding.contains(&(self.txid.clone(), o.index)))
+        {
+            TransactionKind::ChannelOpen
+        } else if self
+            .inputs
+            .iter()
+            .any(|i| funding.contains(&(i.txid.clone(), i.index)))
+        {
+            TransactionKind::ChannelClose
+        } else if self.inputs.iter().any(|i| i.ours) {
+            TransactionKind::Withdrawal
+        } else if self.outputs.iter().any(|o| o.ours) {
+            TransactionKind::Deposit
+        } else {
+            TransactionKind::Unknown
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ListTransactionsResponse {
+    pub transactions: Vec<Transaction>,
+}
+
+/// Limits the node enforces, for constraining amount inputs up front. `None`
+/// means the node sets no limit or doesn't report it.
+#[derive(Clone, Debug)]
+pub struct NodeLimits {
+    /// The most any one peer may have in flight towards the node at once,
+    /// the largest across its channels. `None` without channels.
+    pub max_htlc_value_in_flight_msat: Option<u64>,
+    /// HTLCs a peer may have in flight towards the node per channel.
+    pub max_accepted_htlcs: Option<u32>,
+    /// The smallest HTLC the node forwards or accepts on new channels.
+    pub min_htlc_msat: Option<u64>,
+    /// The largest HTLC the node forwards or accepts on new channels.
+    pub max_htlc_msat: Option<u64>,
+    pub min_channel_size_sat: Option<u64>,
+    /// `None` when the node supports large channels.
+    pub max_channel_size_sat: Option<u64>,
+}
+
+/// Changes the routing policy of one channel, or of all channels with `id`
+/// left out. Fields left out keep their current value.
+#[derive(Clone, Debug)]
+pub struct SetChannelRequest {
+    /// A peer id, channel id or short channel id.
+    pub id: Option<String>,
+    pub fee_base_msat: Option<u64>,
+    pub fee_proportional_millionths: Option<u32>,
+    pub htlc_minimum_msat: Option<u64>,
+    pub htlc_maximum_msat: Option<u64>,
+    /// How long the previous fees are still accepted for, so payments routed
+    /// with them don't fail. CLN defaults to 600 seconds.
+    pub enforce_delay_secs: Option<u32>,
+}
+
+impl From<SetChannelRequest> for cln::SetchannelRequest {
+    fn from(req: SetChannelRequest) -> Self {
+        cln::SetchannelRequest {
+            id: req.id.unwrap_or_else(|| String::from("all")),
+            feebase: req.fee_base_msat.map(|a| cln::Amount { msat: a }),
+            feeppm: req.fee_proportional_millionths,
+            htlcmin: req.htlc_minimum_msat.map(|a| cln::Amount { msat: a }),
+            htlcmax: req.htlc_maximum_msat.map(|a| cln::Amount { msat: a }),
+            enforcedelay: req.enforce_delay_secs,
+            ..Default::default()
+        }
+    }
+}
+
+/// The policy a channel ended up with. The node clamps HTLC limits it can't
+/// honour and says so in the warnings.
+#[derive(Clone, Debug)]
+pub struct SetChannelChannel {
+    pub peer_id: String,
+    pub channel_id: String,
+    pub short_channel_id: Option<String>,
+    pub fee_base_msat: Option<u64>,
+    pub fee_proportional_millionths: u32,
+    pub minimum_htlc_out_msat: Option<u64>,
+    pub maximum_htlc_out_msat: Option<u64>,
+    pub warning_htlcmin_too_low: Option<String>,
+    pub warning_htlcmax_too_high: Option<String>,
+}
+
+impl From<cln::SetchannelChannels> for SetChannelChannel {
+    fn from(channel: cln::SetchannelChannels) -> Self {
+        SetChannelChannel {
+            peer_id: hex::encode(channel.peer_id),
+            channel_id: hex::encode(channel.channel_id),
+            short_channel_id: channel.short_channel_id,
+            fee_base_msat: channel.fee_base_msat.map(|a| a.msat),
+            fee_proportional_millionths: channel.fee_proportional_millionths,
+            minimum_htlc_out_msat: channel.minimum_htlc_out_msat.map(|a| a.msat),
+            maximum_htlc_out_msat: channel.maximum_htlc_out_msat.map(|a| a.msat),
+            warning_htlcmin_too_low: channel.warning_htlcmin_too_low,
+            warning_htlcmax_too_high: channel.warning_htlcmax_too_high,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SetChannelResponse {
+    pub channels: Vec<SetChannelChannel>,
+}
+
+impl From<cln::SetchannelResponse> for SetChannelResponse {
+    fn from(response: cln::SetchannelResponse) -> Self {
+        SetChannelResponse {
+            channels: response
+                .channels
+                .into_iter()
+                .map(SetChannelChannel::from)
+                .collect(),
+        }
+    }
+}
+
+/// The unit of the feerates `fee_rates` returns: sat per 1000 virtual bytes
+/// or sat per 1000 weight units.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FeeratesStyle {
+    Perkb,
+    Perkw,
+}
+
+impl From<FeeratesStyle> for cln::feerates_request::FeeratesStyle {
+    fn from(style: FeeratesStyle) -> Self {
+        match style {
+            FeeratesStyle::Perkb => cln::feerates_request::FeeratesStyle::Perkb,
+            FeeratesStyle::Perkw => cln::feerates_request::FeeratesStyle::Perkw,
+        }
+    }
+}
+
+/// The feerate to confirm within `blockcount` blocks.
+#[derive(Clone, Debug)]
+pub struct FeerateEstimate {
+    pub blockcount: u32,
+    pub feerate: u32,
+    pub smoothed_feerate: u32,
+}
+
+/// The node's feerates in one style. A feerate is `None` while the node has
+/// no estimate for it.
+#[derive(Clone, Debug)]
+pub struct Feerates {
+    pub min_acceptable: u32,
+    pub max_acceptable: u32,
+    /// The lowest feerate the node's bitcoin backend relays.
+    pub floor: Option<u32>,
+    pub opening: Option<u32>,
+    pub mutual_close: Option<u32>,
+    pub unilateral_close: Option<u32>,
+    pub unilateral_anchor_close: Option<u32>,
+    pub penalty: Option<u32>,
+    pub splice: Option<u32>,
+    pub estimates: Vec<FeerateEstimate>,
+}
+
+impl From<cln::FeeratesPerkb> for Feerates {
+    fn from(feerates: cln::FeeratesPerkb) -> Self {
+        Feerates {
+            min_acceptable: feerates.min_acceptable,
+            max_acceptable: feerates.max_acceptable,
+            floor: feerates.floor,
+            opening: feerates.opening,
+            mutual_close: feerates.mutual_close,
+            unilateral_close: feerates.unilateral_close,
+            unilateral_anchor_close: feerates.unilateral_anchor_close,
+            penalty: feerates.penalty,
+            splice: feerates.splice,
+            estimates: feerates
+                .estimates
+                .into_iter()
+                .map(|e| FeerateEstimate {
+                    blockcount: e.blockcount,
+                    feerate: e.feerate,
+                    smoothed_feerate: e.smoothed_feerate,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<cln::FeeratesPerkw> for Feerates {
+    fn from(feerates: cln::FeeratesPerkw) -> Self {
+        Feerates {
+            min_acceptable: feerates.min_acceptable,
+            max_acceptable: feerates.max_acceptable,
+            floor: feerates.floor,
+            opening: feerates.opening,
+            mutual_close: feerates.mutual_close,
+            unilateral_close: feerates.unilateral_close,
+            unilateral_anchor_close: feerates.unilateral_anchor_close,
+            penalty: feerates.penalty,
+            splice: feerates.splice,
+            estimates: feerates
+                .estimates
+                .into_iter()
+                .map(|e| FeerateEstimate {
+                    blockcount: e.blockcount,
+                    feerate: e.feerate,
+                    smoothed_feerate: e.smoothed_feerate,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What typical on-chain operations cost at the current feerates, in sat.
+#[derive(Clone, Debug)]
+pub struct OnchainFeeEstimates {
+    pub opening_channel_satoshis: u64,
+    pub mutual_close_satoshis: u64,
+    pub unilateral_close_satoshis: u64,
+    pub htlc_timeout_satoshis: u64,
+    pub htlc_success_satoshis: u64,
+}
+
+impl From<cln::FeeratesOnchainFeeEstimates> for OnchainFeeEstimates {
+    fn from(estimates: cln::FeeratesOnchainFeeEstimates) -> Self {
+        OnchainFeeEstimates {
+            opening_channel_satoshis: estimates.opening_channel_satoshis,
+            mutual_close_satoshis: estimates.mutual_close_satoshis,
+            unilateral_close_satoshis: estimates.unilateral_close_satoshis,
+            htlc_timeout_satoshis: estimates.htlc_timeout_satoshis,
+            htlc_success_satoshis: estimates.htlc_success_satoshis,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FeeratesResponse {
+    /// In the requested style.
+    pub feerates: Option<Feerates>,
+    pub onchain_fee_estimates: Option<OnchainFeeEstimates>,
+    /// Set while the node is still gathering fee estimates after startup.
+    pub warning_missing_feerates: Option<String>,
+}
+
+impl From<cln::FeeratesResponse> for FeeratesResponse {
+    fn from(response: cln::FeeratesResponse) -> Self {
+        FeeratesResponse {
+            feerates: response
+                .perkb
+                .map(Feerates::from)
+                .or(response.perkw.map(Feerates::from)),
+            onchain_fee_estimates: response
+                .onchain_fee_estimates
+                .map(OnchainFeeEstimates::from),
+            warning_missing_feerates: response.warning_missing_feerates,
+        }
+    }
+}
+
+/// One hop of a route found by `get_route`, with the amount and delay that
+/// reach it.
+#[derive(Clone, Debug)]
+pub struct RouteHop {
+    pub id: String,
+    pub channel: String,
+    pub direction: u32,
+    pub amount_msat: Option<u64>,
+    pub delay: u32,
+}
+
+impl From<cln::GetrouteRoute> for RouteHop {
+    fn from(hop: cln::GetrouteRoute) -> Self {
+        RouteHop {
+            id: hex::encode(hop.id),
+            channel: hop.channel,
+            direction: hop.direction,
+            amount_msat: hop.amount_msat.map(|a| a.msat),
+            delay: hop.delay,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GetRouteResponse {
+    pub route: Vec<RouteHop>,
+    /// What the route charges on top of the amount, in total.
+    pub fee_msat: u64,
+}
+
+impl GetRouteResponse {
+    fn new(response: cln::GetrouteResponse, amount_msat: u64) -> Self {
+        let route: Vec<RouteHop> = response.route.into_iter().map(RouteHop::from).collect();
+        let fee_msat = route
+            .first()
+            .and_then(|hop| hop.amount_msat)
+            .map_or(0, |sent| sent.saturating_sub(amount_msat));
+        GetRouteResponse { route, fee_msat }
+    }
+}
+
+impl TryFrom<RouteHop> for cln::SendpayRoute {
+    type Error = SdkError;
+
+    fn try_from(hop: RouteHop) -> Result<Self> {
+        Ok(cln::SendpayRoute {
+            id: hex::decode(&hop.id)
+                .context("route hop id contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            channel: hop.channel,
+            amount_msat: Some(cln::Amount {
+                msat: hop
+                    .amount_msat
+                    .context("route hop is missing its amount")
+                    .map_err(SdkError::invalid_arg)?,
+            }),
+            delay: hop.delay,
+        })
+    }
+}
+
+/// Sends one payment attempt along `route`, e.g. one found by `get_route`.
+/// The call returns once the attempt is on its way; `wait_send_pay` waits
+/// for its outcome.
+#[derive(Clone, Debug)]
+pub struct SendPayRequest {
+    pub route: Vec<RouteHop>,
+    pub payment_hash: String,
+    pub label: Option<String>,
+    /// The invoice amount, required when sending one part of a multi-part
+    /// payment.
+    pub amount_msat: Option<u64>,
+    pub bolt11: Option<String>,
+    pub payment_secret: Option<String>,
+    pub partid: Option<u64>,
+    pub groupid: Option<u64>,
+}
+
+impl TryFrom<SendPayRequest> for cln::SendpayRequest {
+    type Error = SdkError;
+
+    fn try_from(req: SendPayRequest) -> Result<Self> {
+        Ok(cln::SendpayRequest {
+            route: req
+                .route
+                .into_iter()
+                .map(cln::SendpayRoute::try_from)
+                .collect::<Result<_>>()?,
+            payment_hash: hex::decode(&req.payment_hash)
+                .context("payment hash contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            label: req.label,
+            amount_msat: req.amount_msat.map(|a| cln::Amount { msat: a }),
+            bolt11: req.bolt11,
+            payment_secret: req
+                .payment_secret
+                .map(hex::decode)
+                .transpose()
+                .context("payment secret contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?,
+            partid: req.partid,
+            groupid: req.groupid,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SendPayResponse {
+    pub id: u64,
+    pub groupid: Option<u64>,
+    pub partid: Option<u64>,
+    pub payment_hash: String,
+    pub status: Option<PayStatus>,
+    pub destination: Option<String>,
+    pub amount_msat: Option<u64>,
+    pub amount_sent_msat: Option<u64>,
+    pub created_at: u64,
+    /// Only set if the attempt completed right away.
+    pub payment_preimage: Option<String>,
+    pub message: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl From<cln::SendpayResponse> for SendPayResponse {
+    fn from(response: cln::SendpayResponse) -> Self {
+        use cln::sendpay_response::SendpayStatus;
+
+        SendPayResponse {
+            id: response.id,
+            groupid: response.groupid,
+            partid: response.partid,
+            payment_hash: hex::encode(response.payment_hash),
+            status: SendpayStatus::from_i32(response.status).map(|status| match status {
+                SendpayStatus::Pending => PayStatus::Pending,
+                SendpayStatus::Complete => PayStatus::Complete,
+            }),
+            destination: response.destination.map(hex::encode),
+            amount_msat: response.amount_msat.map(|a| a.msat),
+            amount_sent_msat: response.amount_sent_msat.map(|a| a.msat),
+            created_at: response.created_at,
+            payment_preimage: response.payment_preimage.map(hex::encode),
+            message: response.message,
+            request_id: None,
+        }
+    }
+}
+
+/// A `send_pay` attempt that completed. Failed attempts are returned as
+/// errors.
+#[derive(Clone, Debug)]
+pub struct WaitSendPayResponse {
+    pub id: u64,
+    pub groupid: Option<u64>,
+    pub partid: Option<u64>,
+    pub payment_hash: String,
+    pub destination: Option<String>,
+    pub amount_msat: Option<u64>,
+    pub amount_sent_msat: Option<u64>,
+    pub created_at: u64,
+    pub completed_at: Option<u64>,
+    pub payment_preimage: Option<String>,
+}
+
+impl From<cln::WaitsendpayResponse> for WaitSendPayResponse {
+    fn from(response: cln::WaitsendpayResponse) -> Self {
+        WaitSendPayResponse {
+            id: response.id,
+            groupid: response.groupid,
+            partid: response.partid,
+            payment_hash: hex::encode(response.payment_hash),
+            destination: response.destination.map(hex::encode),
+            amount_msat: response.amount_msat.map(|a| a.msat),
+            amount_sent_msat: response.amount_sent_msat.map(|a| a.msat),
+            created_at: response.created_at,
+            completed_at: response.completed_at.map(|t| t as u64),
+            payment_preimage: response.payment_preimage.map(hex::encode),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DashboardSnapshot {
+    pub info: GetInfoResponse,
+    pub funds: ListFundsResponse,
+    pub channels: Vec<ListPeerChannelsChannel>,
+    pub pending_channels: Vec<PendingChannel>,
+    pub recent_invoices: Vec<ListInvoicesInvoice>,
+    pub recent_payments: Vec<ListPaymentsPayment>,
+}
+
+/// A connected node and its signer. Background tasks started by the client
+/// run on the tokio runtime it was created on.
+pub struct GreenlightAlbyClient {
+    scheduler: Scheduler<Device>,
+    node: gl_client::node::ClnClient,
+    gl_node: gl_client::node::Client,
+    network: Network,
+    node_id: Vec<u8>,
+    shutdown: Mutex<Sender<()>>,
+    signer_handle: Mutex<Option<JoinHandle<()>>>,
+    /// Set while the wallet is locked: the signer is stopped and its seed
+    /// dropped, so calls that need signatures are refused.
+    locked: Arc<AtomicBool>,
+    journal: Mutex<Option<Arc<Journal>>>,
+    events: Arc<EventDispatcher>,
+    channel_policy: Mutex<Option<JoinHandle<()>>>,
+    keysend_messages: Mutex<Option<JoinHandle<()>>>,
+    privacy_mode: Mutex<bool>,
+    byte_fields: Mutex<bool>,
+    invoice_limits: Mutex<Option<InvoiceLimits>>,
+    balance_alerts: Mutex<Option<(Arc<BalanceAlerts>, JoinHandle<()>)>>,
+    invoice_events: Mutex<Option<JoinHandle<()>>>,
+    invoice_subscriptions: Mutex<Vec<JoinHandle<()>>>,
+    payment_queue: Mutex<Option<(Arc<PaymentQueue>, JoinHandle<()>)>>,
+    lsps0: Arc<Lsps0Transport>,
+    cltv_recommendation: Mutex<Option<(Instant, CltvRecommendation)>>,
+    alias_cache: Mutex<HashMap<String, (Instant, Option<String>)>>,
+    invoice_counts: Mutex<InvoiceCounts>,
+    /// Decoded BOLT12 invoices of payments, by invoice.
+    bolt12_cache: Mutex<HashMap<String, Bolt12Metadata>>,
+    lsps0_listener: Mutex<Option<JoinHandle<()>>>,
+    snapshot_store: Mutex<Option<Arc<dyn SnapshotStore>>>,
+    storage: Mutex<Arc<dyn Storage>>,
+    backup_monitor: Arc<BackupMonitor>,
+    backup_watch: Mutex<Option<JoinHandle<()>>>,
+    refund_watch: Mutex<Option<JoinHandle<()>>>,
+    deposit_watch: Mutex<Option<JoinHandle<()>>>,
+    close_watch: Mutex<Option<JoinHandle<()>>>,
+    /// A `NodeUpgraded` event noticed while connecting, held until the first
+    /// listener subscribes.
+    node_upgrade: Mutex<Option<ClientEvent>>,
+    credentials: GreenlightCredentials,
+    export_key: [u8; 32],
+}
+
+fn derive_secret(mnemonic: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let mnemonic = Mnemonic::from_str(mnemonic)
+        .context("failed to parse mnemonic")
+        .map_err(SdkError::invalid_arg)?;
+
+    let seed = Zeroizing::new(mnemonic.to_seed(""));
+    Ok(Zeroizing::new(seed[0..32].to_vec())) // Only need the first 32 bytes
+}
+
+/// The node secret, signer and scheduler derived from a mnemonic. Onboarding
+/// recovers or registers and then creates a client; doing both through one
+/// session derives the seed and connects to the scheduler only once.
+pub struct Session {
+    secret: Zeroizing<Vec<u8>>,
+    network: Network,
+    signer: Signer,
+    scheduler: Scheduler<Nobody>,
+}
+
+impl Session {
+    pub async fn new(mnemonic: String, network: Network) -> Result<Self> {
+        Self::start(mnemonic, network, |_| {}).await
+    }
+
+    async fn start(
+        mnemonic: String,
+        network: Network,
+        on_stage: impl Fn(RecoverStage),
+    ) -> Result<Self> {
+        on_stage(RecoverStage::DerivingKeys);
+        let secret = derive_secret(&mnemonic)?;
+        let creds = tls::nobody();
+
+        let signer = Signer::new(secret.to_vec(), network.into(), creds.clone())
+            .context("failed to create signer")
+            .map_err(SdkError::greenlight_api)?;
+
+        on_stage(RecoverStage::ConnectingScheduler {
+            node_id: hex::encode(signer.node_id()),
+        });
+        let scheduler = Scheduler::new(signer.node_id(), network.into(), creds)
+            .await
+            .context("failed to create scheduler")
+            .map_err(SdkError::scheduler)?;
+
+        Ok(Session {
+            secret,
+            network,
+            signer,
+            scheduler,
+        })
+    }
+
+    pub async fn recover(&self) -> Result<GreenlightCredentials> {
+        Ok(self
+            .scheduler
+            .recover(&self.signer)
+            .await
+            .context("failed to recover credentials")
+            .map_err(SdkError::scheduler)?
+            .into())
+    }
+
+    pub async fn register(&self, invite_code: String) -> Result<GreenlightCredentials> {
+        Ok(self
+            .scheduler
+            .register(&self.signer, Some(invite_code))
+            .await
+            .context("failed to register node")
+            .map_err(SdkError::registration)?
+            .into())
+    }
+
+    pub async fn new_client(
+        &self,
+        credentials: GreenlightCredentials,
+    ) -> Result<Arc<GreenlightAlbyClient>> {
+        self.connect(credentials, None).await
+    }
+
+    pub async fn new_client_with_store(
+        &self,
+        store: &dyn CredentialStore,
+    ) -> Result<Arc<GreenlightAlbyClient>> {
+        let credentials = load_credentials(store)?;
+        self.connect(credentials, Some(store)).await
+    }
+
+    /// Connects with the session's scheduler connection, authenticated as the
+    /// node. The session's signer only holds the anonymous credentials used
+    /// to register, so the client's signer is made from the same secret with
+    /// the node's.
+    async fn connect(
+        &self,
+        credentials: GreenlightCredentials,
+        store: Option<&dyn CredentialStore>,
+    ) -> Result<Arc<GreenlightAlbyClient>> {
+        let creds = device_creds(&credentials)?;
+        let signer = Signer::new(self.secret.to_vec(), self.network.into(), creds.clone())
+            .context("failed to create signer")
+            .map_err(SdkError::greenlight_api)?;
+        let scheduler = self
+            .scheduler
+            .authenticate(creds)
+            .await
+            .context("failed to authenticate with the scheduler")
+            .map_err(SdkError::scheduler)?;
+
+        start_client(
+            &self.secret,
+            self.network,
+            credentials,
+            store,
+            signer,
+            scheduler,
+        )
+        .await
+    }
+}
+
+pub async fn recover(mnemonic: String, network: Network) -> Result<GreenlightCredentials> {
+    Session::new(mnemonic, network).await?.recover().await
+}
+
+/// Like `recover`, reporting each stage to `progress` and giving up with
+/// `SdkError::Cancelled` as soon as `cancel` is cancelled.
+pub async fn recover_with_progress(
+    mnemonic: String,
+    network: Network,
+    progress: &dyn RecoverProgress,
+    cancel: &CancellationToken,
+) -> Result<GreenlightCredentials> {
+    let recovery = async {
+        let session = Session::start(mnemonic, network, |stage| progress.on_stage(stage)).await?;
+        progress.on_stage(RecoverStage::Recovering);
+        session.recover().await
+    };
+
+    let credentials = tokio::select! {
+        credentials = recovery => credentials?,
+        _ = cancel.cancelled() => return Err(SdkError::Cancelled { request_id: None }),
+    };
+    progress.on_stage(RecoverStage::Completed);
+    Ok(credentials)
+}
+
+pub async fn register(
+    mnemonic: String,
+    network: Network,
+    invite_code: String,
+) -> Result<GreenlightCredentials> {
+    Session::new(mnemonic, network)
+        .await?
+        .register(invite_code)
+        .await
+}
+
+pub async fn recover_with_store(
+    mnemonic: String,
+    network: Network,
+    store: &dyn CredentialStore,
+) -> Result<GreenlightCredentials> {
+    let credentials = recover(mnemonic, network).await?;
+    store.save(credentials.clone());
+    Ok(credentials)
+}
+
+pub async fn register_with_store(
+    mnemonic: String,
+    network: Network,
+    invite_code: String,
+    store: &dyn CredentialStore,
+) -> Result<GreenlightCredentials> {
+    let credentials = register(mnemonic, network, invite_code).await?;
+    store.save(credentials.clone());
+    Ok(credentials)
+}
+
+pub async fn new_greenlight_alby_client(
+    mnemonic: String,
+    network: Network,
+    credentials: GreenlightCredentials,
+) -> Result<Arc<GreenlightAlbyClient>> {
+    connect(&derive_secret(&mnemonic)?, network, credentials, None).await
+}
+
+pub async fn new_greenlight_alby_client_with_store(
+    mnemonic: String,
+    network: Network,
+    store: &dyn CredentialStore,
+) -> Result<Arc<GreenlightAlbyClient>> {
+    let credentials = load_credentials(store)?;
+    connect(
+        &derive_secret(&mnemonic)?,
+        network,
+        credentials,
+        Some(store),
+    )
+    .await
+}
+
+fn load_credentials(store: &dyn CredentialStore) -> Result<GreenlightCredentials> {
+    store.load().ok_or_else(|| SdkError::InvalidArgument {
+        message: String::from("no credentials in store"),
+        request_id: None,
+    })
+}
+
+fn device_creds(credentials: &GreenlightCredentials) -> Result<Device> {
+    let cred_bytes = hex::decode(&credentials.gl_creds)
+        .context("failed to decode credentials")
+        .map_err(SdkError::invalid_arg)?;
+    Ok(tls::device(Device::from_bytes(&cred_bytes)))
+}
+
+async fn connect(
+    secret: &[u8],
+    network: Network,
+    credentials: GreenlightCredentials,
+    store: Option<&dyn CredentialStore>,
+) -> Result<Arc<GreenlightAlbyClient>> {
+    let creds = device_creds(&credentials)?;
+
+    let signer = Signer::new(secret.to_vec(), network.into(), creds.clone())
+        .context("failed to create signer")
+        .map_err(SdkError::greenlight_api)?;
+
+    let scheduler = Scheduler::new(signer.node_id(), network.into(), creds)
+        .await
+        .context("failed to create scheduler")
+        .map_err(SdkError::scheduler)?;
+
+    start_client(secret, network, credentials, store, signer, scheduler).await
+}
+
+/// Brings up a client on an existing signer and scheduler connection, both
+/// using `credentials`.
+async fn start_client(
+    secret: &[u8],
+    network: Network,
+    mut credentials: GreenlightCredentials,
+    store: Option<&dyn CredentialStore>,
+    signer: Signer,
+    mut scheduler: Scheduler<Device>,
+) -> Result<Arc<GreenlightAlbyClient>> {
+    let cred_bytes = hex::decode(&credentials.gl_creds)
+        .context("failed to decode credentials")
+        .map_err(SdkError::invalid_arg)?;
+    let mut creds = tls::device(Device::from_bytes(&cred_bytes));
+    let export_key = state_export::derive_key(secret);
+
+    // A warm restart goes straight to the node the last session reached and
+    // only asks the scheduler when that node no longer answers. Upgrading
+    // the credentials is a scheduler call too, so it waits for a cold start.
+    let resumed = match session_cache::load(&signer.node_id()) {
+        Some(grpc_uri) => {
+            let node_handle = Node::new(signer.node_id(), creds.clone())
+                .context("failed to create node")
+                .map_err(SdkError::scheduler)?;
+            match time::timeout(
+                SESSION_RESUME_TIMEOUT,
+                resume_session(&node_handle, grpc_uri),
+            )
+            .await
+            .context("timed out resuming session")
+            .and_then(|resumed| resumed)
+            {
+                Ok(resumed) => Some(resumed),
+                Err(e) => {
+                    eprintln!("Failed to resume session: {:?}", e);
+                    session_cache::invalidate(Some(&signer.node_id()));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let (node, gl_node, node_version) = match resumed {
+        Some(resumed) => resumed,
+        None => {
+            // Older credentials get upgraded (e.g. a rune is added) by the
+            // scheduler; hand the rotated blob back to the host so it is
+            // never lost.
+            if let Some(store) = store {
+                creds = creds
+                    .upgrade(&scheduler, &signer)
+                    .await
+                    .context("failed to upgrade credentials")
+                    .map_err(SdkError::scheduler)?;
+
+                let upgraded_bytes = creds.to_bytes();
+                if upgraded_bytes != cred_bytes {
+                    credentials = GreenlightCredentials {
+                        gl_creds: hex::encode(upgraded_bytes),
+                    };
+                    store.save(credentials.clone());
+
+                    // Only reconnect when the identity actually changed.
+                    scheduler = Scheduler::new(signer.node_id(), network.into(), creds.clone())
+                        .await
+                        .context("failed to create scheduler")
+                        .map_err(SdkError::scheduler)?;
+                }
+            }
+
+            let node_handle = Node::new(signer.node_id(), creds)
+                .context("failed to create node")
+                .map_err(SdkError::scheduler)?;
+
+            let grpc_uri = scheduler
+                .schedule()
+                .await
+                .context("failed to schedule node")
+                .map_err(SdkError::scheduler)?
+                .grpc_uri;
+
+            let node: gl_client::node::ClnClient = node_handle
+                .connect(grpc_uri.clone())
+                .await
+                .context("failed to create node")
+                .map_err(SdkError::scheduler)?;
+
+            let gl_node: gl_client::node::Client = node_handle
+                .connect(grpc_uri.clone())
+                .await
+                .context("failed to create node")
+                .map_err(SdkError::scheduler)?;
+
+            let node_version = get_node_version(&node)
+                .await
+                .map_err(SdkError::greenlight_api)?;
+            session_cache::save(&signer.node_id(), grpc_uri);
+            (node, gl_node, node_version)
+        }
+    };
+    check_signer_compatibility(signer.version(), &node_version)?;
+
+    let node_upgrade = match node_version::record(node.clone(), &node_version).await {
+        Ok(previous) => previous.map(|previous_version| ClientEvent::NodeUpgraded {
+            previous_version,
+            version: node_version.clone(),
+        }),
+        Err(e) => {
+            eprintln!("Failed to record node version: {:?}", e);
+            None
+        }
+    };
+
+    let events = Arc::new(EventDispatcher::default());
+
+    let node_id = signer.node_id();
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let signer_handle = tokio::spawn(signer_supervisor::supervise(signer, rx, events.clone()));
+
+    // Listen for LSPS0 messages from the start, so neither responses nor
+    // notifications from an LSP arrive before anyone is subscribed.
+    let lsps0 = Arc::new(Lsps0Transport::new(events.clone()));
+    let lsps0_listener = tokio::spawn(lsps0::listen(lsps0.clone(), gl_node.clone()));
+
+    Ok(Arc::new(GreenlightAlbyClient {
+        scheduler,
+        node,
+        gl_node,
+        network,
+        node_id,
+        signer_handle: Mutex::new(Some(signer_handle)),
+        shutdown: Mutex::new(tx),
+        locked: Arc::new(AtomicBool::new(false)),
+        journal: Mutex::new(None),
+        events,
+        channel_policy: Mutex::new(None),
+        keysend_messages: Mutex::new(None),
+        privacy_mode: Mutex::new(false),
+        byte_fields: Mutex::new(false),
+        invoice_limits: Mutex::new(None),
+        balance_alerts: Mutex::new(None),
+        invoice_events: Mutex::new(None),
+        invoice_subscriptions: Mutex::new(Vec::new()),
+        payment_queue: Mutex::new(None),
+        lsps0,
+        lsps0_listener: Mutex::new(Some(lsps0_listener)),
+        cltv_recommendation: Mutex::new(None),
+        alias_cache: Mutex::new(HashMap::new()),
+        invoice_counts: Mutex::new(InvoiceCounts::default()),
+        bolt12_cache: Mutex::new(HashMap::new()),
+        snapshot_store: Mutex::new(None),
+        storage: Mutex::new(Arc::new(MemoryStorage::default())),
+        backup_monitor: Arc::new(BackupMonitor::default()),
+        backup_watch: Mutex::new(None),
+        refund_watch: Mutex::new(None),
+        deposit_watch: Mutex::new(None),
+        close_watch: Mutex::new(None),
+        node_upgrade: Mutex::new(node_upgrade),
+        credentials,
+        export_key,
+    }))
+}
+
+/// How long a warm restart waits for the cached node before asking the
+/// scheduler; a node that has moved may not refuse the connection quickly.
+const SESSION_RESUME_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn resume_session(
+    node_handle: &Node,
+    grpc_uri: String,
+) -> anyhow::Result<(gl_client::node::ClnClient, gl_client::node::Client, String)> {
+    let node: gl_client::node::ClnClient = node_handle.connect(grpc_uri.clone()).await?;
+    let gl_node: gl_client::node::Client = node_handle.connect(grpc_uri).await?;
+    let node_version = get_node_version(&node).await?;
+    Ok((node, gl_node, node_version))
+}
+
+async fn get_node_version(node: &gl_client::node::ClnClient) -> anyhow::Result<String> {
+    Ok(node
+        .clone()
+        .getinfo(traced_request(cln::GetinfoRequest::default()))
+        .await
+        .context("failed to get node version")?
+        .into_inner()
+        .version)
+}
+
+/// Lets clients created from now on connect straight to the node address a
+/// previous session reached, kept in `storage`, instead of waiting for the
+/// scheduler first. Addresses older than `max_age_secs` (default an hour) are
+/// not tried. Passing `None` turns the cache off.
+pub fn set_session_cache(
+    storage: Option<Arc<dyn Storage>>,
+    max_age_secs: Option<u64>,
+) -> Result<()> {
+    let max_age_secs = max_age_secs.unwrap_or(session_cache::DEFAULT_MAX_AGE_SECS);
+    if max_age_secs == 0 {
+        return Err(SdkError::InvalidArgument {
+            message: String::from("max age must be at least 1 second"),
+            request_id: None,
+        });
+    }
+    session_cache::configure(storage, max_age_secs);
+    Ok(())
+}
+
+/// Forgets every cached node address, so the next client goes through the
+/// scheduler, e.g. after the node was moved or its credentials were replaced.
+pub fn invalidate_session_cache() {
+    session_cache::invalidate(None)
+}
+
+/// Trusts only `ca_certificate` (PEM) for connections to Greenlight made
+/// from now on, or the default root again when `None`.
+pub fn set_tls_ca_certificate(ca_certificate: Option<String>) -> Result<()> {
+    tls::set_ca_certificate(ca_certificate).map_err(SdkError::invalid_arg)
+}
+
+/// Whether `preimage` hashes to `payment_hash`, i.e. proves that the payment
+/// settled. Both are hex encoded 32 byte values; the comparison runs in
+/// constant time.
+pub fn verify_preimage(payment_hash: String, preimage: String) -> Result<bool> {
+    let decode = |name: &str, value: &str| {
+        hex::decode(value)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| SdkError::InvalidArgument {
+                message: format!("{} must be 32 hex encoded bytes", name),
+                request_id: None,
+            })
+    };
+    let payment_hash = decode("payment hash", &payment_hash)?;
+    let preimage = decode("preimage", &preimage)?;
+
+    let digest = Sha256::digest(&preimage);
+    let difference = digest
+        .iter()
+        .zip(&payment_hash)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    Ok(difference == 0)
+}
+
+/// Decodes `bolt11` locally, without a scheduled node.
+pub fn decode_bolt11(bolt11: String) -> Result<DecodedInvoice> {
+    bolt11::decode(&bolt11)
+        .context("invalid bolt11 invoice")
+        .map_err(SdkError::invalid_arg)
+}
+
+/// The last snapshot saved to `store`, without contacting Greenlight.
+pub fn get_cached_snapshot(store: &dyn SnapshotStore) -> Option<CachedSnapshot> {
+    snapshot_cache::load(store)
+}
+
+/// The last snapshot saved to `storage`, without contacting Greenlight.
+pub fn get_cached_snapshot_from_storage(storage: Arc<dyn Storage>) -> Option<CachedSnapshot> {
+    snapshot_cache::load(&StorageSnapshotStore(storage))
+}
+
+/// Checks `phrase` and reports what is wrong with it, if anything.
+pub fn validate_mnemonic(phrase: String) -> MnemonicValidation {
+    mnemonic::validate(&phrase)
+}
+
+/// Generates a new English BIP39 phrase of `word_count` words.
+pub fn generate_mnemonic(word_count: u32) -> Result<String> {
+    mnemonic::generate(word_count).map_err(SdkError::invalid_arg)
+}
+
+/// Decrypts a state blob created by `export_state` on another device and
+/// returns the credentials it contains.
+pub fn import_state(mnemonic: String, state: String) -> Result<GreenlightCredentials> {
+    let secret = derive_secret(&mnemonic)?;
+
+    let state = state_export::open(&state_export::derive_key(&secret), &state)
+        .map_err(SdkError::invalid_arg)?;
+
+    Ok(GreenlightCredentials {
+        gl_creds: state.credentials,
+    })
+}
+
+/// Reads the creation timestamp of a bolt11 invoice, which is encoded in the
+/// first 35 bits of its bech32 data part.
+fn bolt11_timestamp(bolt11: &str) -> Option<u64> {
+    const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    let bolt11 = bolt11.to_lowercase();
+    let data = &bolt11[bolt11.rfind('1')? + 1..];
+    if data.len() < 7 {
+        return None;
+    }
+
+    data.chars().take(7).try_fold(0u64, |timestamp, c| {
+        Some(timestamp << 5 | CHARSET.find(c)? as u64)
+    })
+}
+
+/// Picks the payments to `hashes` out of a full `listpays`, in the order of
+/// `hashes`. A hash given twice yields its payments twice, as the per-hash
+/// lookups do.
+fn pays_in_hash_order(pays: Vec<cln::ListpaysPays>, hashes: &[Vec<u8>]) -> Vec<cln::ListpaysPays> {
+    let mut by_hash: HashMap<Vec<u8>, Vec<cln::ListpaysPays>> = HashMap::new();
+    for pay in pays {
+        by_hash
+            .entry(pay.payment_hash.clone())
+            .or_default()
+            .push(pay);
+    }
+
+    hashes
+        .iter()
+        .filter_map(|hash| by_hash.get(hash))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn invoice_created_at(invoice: &ListInvoicesInvoice) -> Option<u64> {
+    invoice.bolt11.as_deref().and_then(bolt11_timestamp)
+}
+
+fn is_bolt12_invoice(invstring: &str) -> bool {
+    invstring.to_lowercase().starts_with("lni1")
+}
+
+/// Fails if the embedded signer speaks a different protocol version than the
+/// node, which otherwise only shows up later as obscure signing errors.
+fn check_signer_compatibility(signer_version: &str, node_version: &str) -> Result<()> {
+    if release_of(signer_version) != release_of(node_version) {
+        return Err(SdkError::IncompatibleVersion {
+            signer_version: signer_version.to_string(),
+            node_version: node_version.to_string(),
+            request_id: None,
+        });
+    }
+    Ok(())
+}
+
+/// Strips the `@host:port` a peer address may carry.
+fn node_id_of(address: &str) -> String {
+    address.split('@').next().unwrap_or_default().to_string()
+}
+
+/// Extracts the CLN release (e.g. `23.08`) from version strings such as
+/// `v23.08` or `v23.08gl1`.
+fn release_of(version: &str) -> String {
+    version
+        .trim_start_matches('v')
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl GreenlightAlbyClient {
+    pub async fn shutdown(&self) -> Result<ShutdownResponse> {
+        if let Some(task) = self.channel_policy.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.keysend_messages.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some((_, task)) = self.balance_alerts.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.invoice_events.lock().unwrap().take() {
+            task.abort();
+        }
+        for task in self.invoice_subscriptions.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        if let Some((_, task)) = self.payment_queue.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.backup_watch.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.refund_watch.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.deposit_watch.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.close_watch.lock().unwrap().take() {
+            task.abort();
+        }
+        if let Some(task) = self.lsps0_listener.lock().unwrap().take() {
+            task.abort();
+        }
+
+        let started = Instant::now();
+        let outcome = self
+            .stop_signer()
+            .await
+            .unwrap_or(ShutdownOutcome::CleanShutdown);
+
+        Ok(ShutdownResponse {
+            outcome,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Stops the signer, returning `None` if it wasn't running.
+    async fn stop_signer(&self) -> Option<ShutdownOutcome> {
+        const SIGNER_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+        let mut signer = self.signer_handle.lock().unwrap().take()?;
+
+        // The signer may have exited on its own, closing the channel.
+        let _ = self.shutdown.lock().unwrap().try_send(());
+
+        match time::timeout(SIGNER_STOP_TIMEOUT, &mut signer).await {
+            Ok(_) => Some(ShutdownOutcome::CleanShutdown),
+            Err(_) => {
+                eprintln!("Signer did not stop in time, aborting it");
+                signer.abort();
+                Some(ShutdownOutcome::Aborted)
+            }
+        }
+    }
+
+    /// Stops the signer and drops its seed. Read-only calls keep working;
+    /// calls that need a signature fail with `WalletLocked` until `unlock`.
+    pub async fn lock(&self) {
+        self.locked.store(true, Ordering::SeqCst);
+        self.stop_signer().await;
+    }
+
+    /// Restarts the signer from `mnemonic`, which must belong to this node.
+    pub async fn unlock(&self, mnemonic: String) -> Result<()> {
+        let secret = derive_secret(&mnemonic)?;
+        let creds = device_creds(&self.credentials)?;
+
+        let signer = Signer::new(secret.to_vec(), self.network.into(), creds)
+            .context("failed to create signer")
+            .map_err(SdkError::greenlight_api)?;
+        if signer.node_id() != self.node_id {
+            return Err(SdkError::InvalidArgument {
+                message: String::from("mnemonic belongs to a different node"),
+                request_id: None,
+            });
+        }
+
+        let mut signer_handle = self.signer_handle.lock().unwrap();
+        if signer_handle.is_none() {
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            *signer_handle = Some(tokio::spawn(signer_supervisor::supervise(
+                signer,
+                rx,
+                self.events.clone(),
+            )));
+            *self.shutdown.lock().unwrap() = tx;
+        }
+        self.locked.store(false, Ordering::SeqCst);
+        if let Ok(queue) = self.payment_queue() {
+            queue.retry_now();
+        }
+        Ok(())
+    }
+
+    fn require_unlocked(&self) -> Result<()> {
+        if self.locked.load(Ordering::SeqCst) {
+            return Err(SdkError::WalletLocked { request_id: None });
+        }
+        Ok(())
+    }
+
+    pub async fn get_info(&self) -> Result<GetInfoResponse> {
+        self.node
+            .clone()
+            .getinfo(traced_request(cln::GetinfoRequest::default()))
+            .await
+            .context("failed to get node info")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Without an explicit `cltv` the invoice uses `recommend_invoice_cltv`,
+    /// falling back to the node's default if that can't be determined.
+    pub async fn make_invoice(&self, mut req: MakeInvoiceRequest) -> Result<MakeInvoiceResponse> {
+        self.require_unlocked()?;
+
+        self.apply_invoice_limits(
+            Some(req.amount_msat),
+            &mut req.expiry,
+            Some(DEFAULT_INVOICE_EXPIRY_SECS),
+        )?;
+
+        if req.cltv.is_none() {
+            match self.recommend_invoice_cltv().await {
+                Ok(recommendation) => req.cltv = Some(recommendation.cltv),
+                Err(e) => eprintln!("Failed to recommend invoice cltv: {}", e),
+            }
+        }
+
+        let truncated = req.fit_description();
+        let mut response: MakeInvoiceResponse = self
+            .node
+            .clone()
+            .invoice(traced_request(cln::InvoiceRequest::try_from(req)?))
+            .await
+            .context("failed to make invoice")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .into();
+
+        if let Some(message) = truncated {
+            response
+                .warnings
+                .push(InvoiceWarning::Truncated { message });
+        }
+        Ok(response)
+    }
+
+    pub async fn pay(&self, req: PayRequest) -> Result<PayResponse> {
+        self.require_unlocked()?;
+
+        let journal_id = self.journal_start(JournalOperation::Pay {
+            bolt11: req.bolt11.clone(),
+        })?;
+
+        let invstring = req.bolt11.clone();
+        let bolt12 = is_bolt12_invoice(&req.bolt11).then(|| req.bolt11.clone());
+        let amount_msat = req.amount_msat.or_else(|| {
+            bolt11::decode(&req.bolt11)
+                .ok()
+                .and_then(|invoice| invoice.amount_msat)
+        });
+
+        let result = match self
+            .node
+            .clone()
+            .pay(traced_request(cln::PayRequest::from(req)))
+            .await
+            .context("failed to pay invoice")
+            .map_err(|e| SdkError::funds(e, amount_msat))
+        {
+            Ok(r) => Ok(PayResponse::from(r.into_inner())),
+            Err(e) => self.settle_pay_error(&invstring, e).await,
+        };
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+
+        let mut response = result?;
+        if let Some(bolt12) = bolt12 {
+            let metadata = self.bolt12_metadata(bolt12).await;
+            response.invreq_payer_note = metadata.invreq_payer_note;
+            response.offer_description = metadata.offer_description;
+            response.offer_issuer = metadata.offer_issuer;
+        }
+        Ok(response)
+    }
+
+    /// Waits for the outcome of a payment `pay` reported as `PaymentPending`.
+    /// Fails with `PaymentPending` again if it is still unresolved after a
+    /// minute, so the host can decide whether to keep waiting.
+    pub async fn resume_payment(&self, payment_hash: String) -> Result<PayResponse> {
+        let hash = hex::decode(&payment_hash)
+            .context("payment hash contains invalid hex value")
+            .map_err(SdkError::invalid_arg)?;
+
+        let deadline = Instant::now() + RESUME_TIMEOUT;
+        loop {
+            match self.payment_outcome(&hash).await? {
+                PaymentOutcome::Complete(response) => return Ok(response),
+                PaymentOutcome::Failed => {
+                    return Err(SdkError::GreenlightApi {
+                        message: format!("payment {} failed", payment_hash),
+                        request_id: None,
+                    })
+                }
+                PaymentOutcome::Unknown => {
+                    return Err(SdkError::InvalidArgument {
+                        message: format!("no payment with hash {}", payment_hash),
+                        request_id: None,
+                    })
+                }
+                PaymentOutcome::Pending if Instant::now() >= deadline => {
+                    return Err(SdkError::PaymentPending {
+                        payment_hash,
+                        request_id: None,
+                    })
+                }
+                PaymentOutcome::Pending => time::sleep(RESUME_POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    /// A failed pay call doesn't mean the payment failed: the call may have
+    /// timed out with HTLCs still in flight, or even after the payment
+    /// completed. The node's record of the payment has the final word.
+    async fn settle_pay_error(&self, invstring: &str, error: SdkError) -> Result<PayResponse> {
+        let Some(payment_hash) = self.payment_hash_of(invstring).await else {
+            return Err(error);
+        };
+
+        match self.payment_outcome(&payment_hash).await {
+            Ok(PaymentOutcome::Complete(response)) => Ok(response),
+            Ok(PaymentOutcome::Pending) => Err(SdkError::PaymentPending {
+                payment_hash: hex::encode(payment_hash),
+                request_id: None,
+            }),
+            _ => Err(error),
+        }
+    }
+
+    async fn payment_hash_of(&self, invstring: &str) -> Option<Vec<u8>> {
+        if !is_bolt12_invoice(invstring) {
+            let invoice = bolt11::decode(invstring).ok()?;
+            return hex::decode(invoice.payment_hash).ok();
+        }
+
+        self.node
+            .clone()
+            .decode(traced_request(cln::DecodeRequest {
+                string: invstring.to_string(),
+            }))
+            .await
+            .ok()?
+            .into_inner()
+            .invoice_payment_hash
+    }
+
+    async fn payment_outcome(&self, payment_hash: &[u8]) -> Result<PaymentOutcome> {
+        use cln::listpays_pays::ListpaysPaysStatus;
+
+        let pays = self
+            .node
+            .clone()
+            .list_pays(traced_request(cln::ListpaysRequest {
+                payment_hash: Some(payment_hash.to_vec()),
+                ..Default::default()
+            }))
+            .await
+            .context("failed to list payments")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .pays;
+
+        if let Some(pay) = pays
+            .iter()
+            .find(|p| p.status == ListpaysPaysStatus::Complete as i32)
+        {
+            return Ok(PaymentOutcome::Complete(pay.clone().into()));
+        }
+
+        Ok(if pays.is_empty() {
+            PaymentOutcome::Unknown
+        } else if pays
+            .iter()
+            .any(|p| p.status == ListpaysPaysStatus::Pending as i32)
+        {
+            PaymentOutcome::Pending
+        } else {
+            PaymentOutcome::Failed
+        })
+    }
+
+    pub async fn key_send(&self, req: KeySendRequest) -> Result<KeySendResponse> {
+        self.require_unlocked()?;
+
+        let operation = JournalOperation::KeySend {
+            destination: req.destination.clone(),
+            amount_msat: req.amount_msat,
+        };
+        let request = cln::KeysendRequest::try_from(req)?;
+        let amount_msat = request.amount_msat.as_ref().map(|a| a.msat);
+
+        let journal_id = self.journal_start(operation)?;
+
+        let mut result = self
+            .node
+            .clone()
+            .key_send(traced_request(request))
+            .await
+            .context("failed to send keysend")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into());
+
+        // keysend can't be split across channels; when it fails, tell the
+        // caller if that is because no single channel can carry the amount.
+        if let (Err(_), Some(amount_msat)) = (&result, amount_msat) {
+            if let Ok(max_sendable_msat) = self.max_single_channel_sendable().await {
+                if amount_msat > max_sendable_msat {
+                    result = Err(SdkError::ExceedsMaxSendable {
+                        amount_msat,
+                        max_sendable_msat,
+                        request_id: None,
+                    });
+                }
+            }
+        }
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+        result
+    }
+
+    /// Route hints for the node's usable private channels, for invoices
+    /// created outside the node (e.g. by an LNURL server). Each hint is a
+    /// single hop from the peer, using the alias the peer knows the channel
+    /// by and the fees the peer charges towards us. Channels whose peer
+    /// hasn't sent a channel update yet are left out.
+    pub async fn get_route_hints(&self) -> Result<Vec<RouteHint>> {
+        let (channels, updates) = tokio::try_join!(
+            async {
+                self.node
+                    .clone()
+                    .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::default()))
+                    .await
+                    .context("failed to list peer channels")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().channels)
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_channels(traced_request(cln::ListchannelsRequest {
+                        destination: Some(self.node_id.clone()),
+                        ..Default::default()
+                    }))
+                    .await
+                    .context("failed to list channels")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().channels)
+            },
+        )?;
+
+        let hints = channels
+            .into_iter()
+            .filter(|c| c.private == Some(true) && c.peer_connected == Some(true))
+            .filter(|c| c.state == Some(cln::ChannelState::ChanneldNormal as i32))
+            .filter_map(|channel| {
+                let peer_id = channel.peer_id?;
+                let short_channel_id = channel.short_channel_id?;
+                let update = updates
+                    .iter()
+                    .find(|u| u.short_channel_id == short_channel_id && u.source == peer_id)?;
+                let alias = channel.alias.and_then(|alias| alias.remote);
+
+                Some(RouteHint {
+                    hops: vec![RouteHintHop {
+                        id: hex::encode(peer_id),
+                        short_channel_id: alias.unwrap_or(short_channel_id),
+                        fee_base_msat: update.base_fee_millisatoshi as u64,
+                        fee_proportional_millionths: update.fee_per_millionth,
+                        cltv_expiry_delta: update.delay,
+                    }],
+                })
+            })
+            .collect();
+        Ok(hints)
+    }
+
+    async fn max_single_channel_sendable(&self) -> Result<u64> {
+        Ok(self
+            .all_peer_channels()
+            .await?
+            .iter()
+            .filter(|c| c.state == Some(cln::ChannelState::ChanneldNormal as i32))
+            .filter_map(|c| c.spendable_msat)
+            .max()
+            .unwrap_or(0))
+    }
+
+    pub async fn list_funds(&self, req: ListFundsRequest) -> Result<ListFundsResponse> {
+        let enrich = req.enrich;
+        let mut response: ListFundsResponse = self
+            .node
+            .clone()
+            .list_funds(traced_request(cln::ListfundsRequest::from(req)))
+            .await
+            .context("failed to list funds")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .into();
+
+        if enrich {
+            let aliases = self
+                .peer_aliases(response.channels.iter().map(|c| c.peer_id.clone()))
+                .await;
+            for channel in response.channels.iter_mut() {
+                channel.peer_alias = aliases.get(&channel.peer_id).cloned();
+            }
+        }
+
+        let has_spent = response
+            .outputs
+            .iter()
+            .any(|o| o.status == Some(ListFundsOutputStatus::Spent));
+        if has_spent {
+            let spenders = self.spending_transactions().await?;
+            for output in response.outputs.iter_mut() {
+                output.spent_by = spenders.get(&(output.txid.clone(), output.output)).cloned();
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Maps every outpoint spent by a wallet transaction to the id of that
+    /// transaction.
+    async fn spending_transactions(&self) -> Result<HashMap<(String, u32), String>> {
+        let transactions = self
+            .node
+            .clone()
+            .list_transactions(traced_request(cln::ListtransactionsRequest::default()))
+            .await
+            .context("failed to list transactions")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .transactions;
+
+        Ok(transactions
+            .into_iter()
+            .flat_map(|tx| {
+                let spender = hex::encode(&tx.hash);
+                tx.inputs
+                    .into_iter()
+                    .map(move |input| ((hex::encode(input.txid), input.index), spender.clone()))
+            })
+            .collect())
+    }
+
+    /// Looks up the aliases of `peer_ids` from gossip. Answers are kept for
+    /// `PEER_ALIAS_TTL`, so only peers not looked up recently cost a
+    /// `listnodes` call, run concurrently. Peers without a known alias are
+    /// missing from the result.
+    async fn peer_aliases(
+        &self,
+        peer_ids: impl Iterator<Item = String>,
+    ) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        let mut missing = HashSet::new();
+        {
+            let cache = self.alias_cache.lock().unwrap();
+            for peer_id in peer_ids {
+                match cache.get(&peer_id) {
+                    Some((at, alias)) if at.elapsed() < PEER_ALIAS_TTL => {
+                        if let Some(alias) = alias {
+                            aliases.insert(peer_id, alias.clone());
+                        }
+                    }
+                    _ => {
+                        missing.insert(peer_id);
+                    }
+                }
+            }
+        }
+
+        let mut lookups = JoinSet::new();
+        for peer_id in missing {
+            let mut node = self.node.clone();
+            lookups.spawn(async move {
+                let id = hex::decode(&peer_id).ok()?;
+                let alias = node
+                    .list_nodes(traced_request(cln::ListnodesRequest { id: Some(id) }))
+                    .await
+                    .ok()?
+                    .into_inner()
+                    .nodes
+                    .into_iter()
+                    .next()
+                    .and_then(|node| node.alias);
+                Some((peer_id, alias))
+            });
+        }
+
+        // Failed lookups aren't cached, peers without an alias are.
+        while let Some(result) = lookups.join_next().await {
+            if let Ok(Some((peer_id, alias))) = result {
+                self.alias_cache
+                    .lock()
+                    .unwrap()
+                    .insert(peer_id.clone(), (Instant::now(), alias.clone()));
+                if let Some(alias) = alias {
+                    aliases.insert(peer_id, alias);
+                }
+            }
+        }
+        aliases
+    }
+
+    pub async fn connect_peer(&self, req: ConnectPeerRequest) -> Result<ConnectPeerResponse> {
+        // The handshake is done with the node key, which the signer holds.
+        self.require_unlocked()?;
+
+        self.node
+            .clone()
+            .connect_peer(traced_request(cln::ConnectRequest::from(req)))
+            .await
+            .context("failed to connect peer")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn fund_channel(&self, req: FundChannelRequest) -> Result<FundChannelResponse> {
+        self.require_unlocked()?;
+
+        let amount_msat = req.amount_msat;
+        let operation = JournalOperation::FundChannel {
+            id: req.id.clone(),
+            amount_msat,
+        };
+        let request = cln::FundchannelRequest::try_from(req)?;
+        let journal_id = self.journal_start(operation)?;
+
+        let result = self
+            .node
+            .clone()
+            .fund_channel(traced_request(request))
+            .await
+            .context("failed to fund channel")
+            .map_err(|e| SdkError::funds(e, amount_msat))
+            .map(|r| r.into_inner().into());
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+        result
+    }
+
+    pub async fn multi_fund_channel(
+        &self,
+        req: MultiFundChannelRequest,
+    ) -> Result<MultiFundChannelResponse> {
+        self.require_unlocked()?;
+
+        let operation = JournalOperation::MultiFundChannel {
+            ids: req.destinations.iter().map(|d| node_id_of(&d.id)).collect(),
+        };
+        let request = cln::MultifundchannelRequest::try_from(req)?;
+        let journal_id = self.journal_start(operation)?;
+
+        let result = self
+            .node
+            .clone()
+            .multi_fund_channel(traced_request(request))
+            .await
+            .context("failed to fund channels")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into());
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+        result
+    }
+
+    pub async fn new_address(&self, req: NewAddressRequest) -> Result<NewAddressResponse> {
+        self.require_unlocked()?;
+
+        let response: NewAddressResponse = self
+            .node
+            .clone()
+            .new_addr(traced_request(cln::NewaddrRequest::from(req)))
+            .await
+            .context("failed to request new address")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .into();
+
+        // The address is already reserved by the node, losing it from the
+        // history must not fail the call.
+        for address in [&response.p2tr, &response.bech32].into_iter().flatten() {
+            if let Err(e) = address_history::record(self.node.clone(), address).await {
+                eprintln!("Failed to record address: {:?}", e);
+            }
+        }
+
+        Ok(response)
+    }
+
+    pub async fn list_invoices(&self, req: ListInvoicesRequest) -> Result<ListInvoicesResponse> {
+        let redact = self.redacts(req.include_sensitive);
+        let mut response = if req.created_after.is_some() || req.paid_after.is_some() {
+            self.list_invoices_since(req).await?
+        } else {
+            self.list_invoices_page(req).await?
+        };
+
+        if self.keysend_messages.lock().unwrap().is_some() {
+            let messages = keysend_messages::load(self.node.clone())
+                .await
+                .map_err(SdkError::greenlight_api)?;
+            for invoice in response.invoices.iter_mut() {
+                if let Some(message) = messages.get(&invoice.label) {
+                    invoice.description = Some(message.clone());
+                }
+            }
+        }
+
+        if redact {
+            response
+                .invoices
+                .iter_mut()
+                .for_each(ListInvoicesInvoice::redact);
+        }
+
+        Ok(response)
+    }
+
+    async fn list_invoices_page(&self, req: ListInvoicesRequest) -> Result<ListInvoicesResponse> {
+        let byte_fields = self.byte_fields();
+        self.node
+            .clone()
+            .list_invoices(traced_request(cln::ListinvoicesRequest::try_from(req)?))
+            .await
+            .context("failed to list invoices")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| ListInvoicesResponse::new(r.into_inner(), byte_fields))
+    }
+
+    /// Filters invoices on their creation and payment times. With a `start`
+    /// the caller's index is paged forwards from there; otherwise pages go
+    /// backwards from the newest invoice (by creation, or by update when
+    /// filtering on payment time) until invoices get older than the requested
+    /// timestamps, so only the recent history is transferred. Either way
+    /// `limit` applies to the matches, which are returned oldest first.
+    async fn list_invoices_since(&self, req: ListInvoicesRequest) -> Result<ListInvoicesResponse> {
+        const PAGE_SIZE: u64 = 100;
+
+        let index = req.index.unwrap_or(if req.paid_after.is_some() {
+            ListInvoicesIndex::Updated
+        } else {
+            ListInvoicesIndex::Created
+        });
+        let limit = req.limit.map_or(usize::MAX, |limit| limit as usize);
+        let threshold = req.paid_after.or(req.created_after).unwrap_or(0);
+        let matches = |invoice: &ListInvoicesInvoice| {
+            let created_matches = match (req.created_after, invoice_created_at(invoice)) {
+                (Some(after), Some(created_at)) => created_at >= after,
+                _ => true,
+            };
+            let paid_matches = match req.paid_after {
+                Some(after) => invoice.paid_at.is_some_and(|t| t >= after),
+                None => true,
+            };
+            created_matches && paid_matches
+        };
+
+        let mut invoices = Vec::new();
+        if let Some(mut start) = req.start {
+            while invoices.len() < limit {
+                let page = self
+                    .list_invoices_page(ListInvoicesRequest {
+                        index: Some(index),
+                        start: Some(start),
+                        limit: Some(PAGE_SIZE as u32),
+                        ..req.clone()
+                    })
+                    .await?
+                    .invoices;
+
+                let count = page.len();
+                for invoice in page {
+                    let position = match index {
+                        ListInvoicesIndex::Created => invoice.created_index,
+                        ListInvoicesIndex::Updated => invoice.updated_index,
+                    };
+                    start = start.max(position.unwrap_or(start) + 1);
+                    if invoices.len() < limit && matches(&invoice) {
+                        invoices.push(invoice);
+                    }
+                }
+                if count < PAGE_SIZE as usize {
+                    break;
+                }
+            }
+            return Ok(ListInvoicesResponse { invoices });
+        }
+
+        let mut end = self.current_invoice_index(index).await?;
+        'pages: while end > 0 {
+            let start = end.saturating_sub(PAGE_SIZE - 1).max(1);
+            let page = self
+                .list_invoices_page(ListInvoicesRequest {
+                    index: Some(index),
+                    start: Some(start),
+                    limit: Some((end - start + 1) as u32),
+                    ..req.clone()
+                })
+                .await?;
+
+            let mut reached_older = false;
+            for invoice in page.invoices.into_iter().rev() {
+                let order_time = match index {
+                    ListInvoicesIndex::Created => invoice_created_at(&invoice),
+                    ListInvoicesIndex::Updated => invoice.paid_at,
+                };
+                if order_time.is_some_and(|t| t < threshold) {
+                    reached_older = true;
+                    continue;
+                }
+                if matches(&invoice) {
+                    invoices.push(invoice);
+                    // Going backwards, so these are the newest matches.
+                    if invoices.len() == limit {
+                        break 'pages;
+                    }
+                }
+            }
+
+            if reached_older {
+                break;
+            }
+            end = start - 1;
+        }
+
+        invoices.reverse();
+        Ok(ListInvoicesResponse { invoices })
+    }
+
+    async fn current_invoice_index(&self, index: ListInvoicesIndex) -> Result<u64> {
+        // `wait` returns immediately when the index is already past
+        // `nextvalue`, which makes it a cheap way to read the current value.
+        let response = self
+            .node
+            .clone()
+            .wait(traced_request(cln::WaitRequest {
+                subsystem: cln::wait_request::WaitSubsystem::Invoices as i32,
+                indexname: match index {
+                    ListInvoicesIndex::Created => cln::wait_request::WaitIndexname::Created,
+                    ListInvoicesIndex::Updated => cln::wait_request::WaitIndexname::Updated,
+                } as i32,
+                nextvalue: 0,
+            }))
+            .await
+            .context("failed to get current invoice index")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+
+        Ok(match index {
+            ListInvoicesIndex::Created => response.created,
+            ListInvoicesIndex::Updated => response.updated,
+        }
+        .unwrap_or(0))
+    }
+
+    pub async fn list_payments(&self, req: ListPaymentsRequest) -> Result<ListPaymentsResponse> {
+        let redact = self.redacts(req.include_sensitive);
+        let response = self
+            .node
+            .clone()
+            .list_pays(traced_request(cln::ListpaysRequest::try_from(req)?))
+            .await
+            .context("failed to list payments")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+        let mut response = ListPaymentsResponse::new(response, self.byte_fields());
+
+        self.add_bolt12_metadata(&mut response.payments).await;
+
+        if redact {
+            response
+                .payments
+                .iter_mut()
+                .for_each(ListPaymentsPayment::redact);
+        }
+
+        Ok(response)
+    }
+
+    /// Looks up the payments to many hashes, running the `listpays` queries
+    /// in concurrent batches. Past `BULK_THRESHOLD` hashes a single unfiltered
+    /// `listpays` is cheaper, so the payments are fetched once and picked out
+    /// here. Payments come back in the order of `payment_hashes`; hashes the
+    /// node never paid are skipped. Privacy mode applies as for
+    /// `list_payments`.
+    pub async fn get_payments_by_hashes(
+        &self,
+        payment_hashes: Vec<String>,
+    ) -> Result<Vec<ListPaymentsPayment>> {
+        const BATCH_SIZE: usize = 16;
+        const BULK_THRESHOLD: usize = 64;
+
+        let hashes = payment_hashes
+            .iter()
+            .map(|hash| {
+                hex::decode(hash)
+                    .context("payment hash contains invalid hex value")
+                    .map_err(SdkError::invalid_arg)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let payments = if hashes.len() > BULK_THRESHOLD {
+            let pays = self
+                .node
+                .clone()
+                .list_pays(traced_request(cln::ListpaysRequest::default()))
+                .await
+                .context("failed to list payments")
+                .map_err(SdkError::greenlight_api)?
+                .into_inner()
+                .pays;
+            pays_in_hash_order(pays, &hashes)
+        } else {
+            let mut payments = Vec::new();
+            for batch in hashes.chunks(BATCH_SIZE) {
+                let mut lookups = JoinSet::new();
+                for (i, hash) in batch.iter().enumerate() {
+                    let mut node = self.node.clone();
+                    let request = cln::ListpaysRequest {
+                        payment_hash: Some(hash.clone()),
+                        ..Default::default()
+                    };
+                    lookups
+                        .spawn(async move { (i, node.list_pays(traced_request(request)).await) });
+                }
+
+                let mut found = vec![Vec::new(); batch.len()];
+                while let Some(result) = lookups.join_next().await {
+                    let (i, response) = result
+                        .context("payment lookup panicked")
+                        .map_err(SdkError::greenlight_api)?;
+                    found[i] = response
+                        .context("failed to list payments")
+                        .map_err(SdkError::greenlight_api)?
+                        .into_inner()
+                        .pays;
+                }
+                payments.extend(found.into_iter().flatten());
+            }
+            payments
+        };
+
+        let byte_fields = self.byte_fields();
+        let mut payments: Vec<_> = payments
+            .into_iter()
+            .map(|p| ListPaymentsPayment::new(p, byte_fields))
+            .collect();
+        self.add_bolt12_metadata(&mut payments).await;
+        if self.redacts(false) {
+            payments.iter_mut().for_each(ListPaymentsPayment::redact);
+        }
+        Ok(payments)
+    }
+
+    /// Fills in the offer fields of BOLT12 payments. Each invoice is decoded
+    /// once per client: the ones not seen before are decoded concurrently and
+    /// kept, so listing the same payments again costs no RPCs.
+    async fn add_bolt12_metadata(&self, payments: &mut [ListPaymentsPayment]) {
+        let missing: HashSet<String> = {
+            let cache = self.bolt12_cache.lock().unwrap();
+            payments
+                .iter()
+                .filter_map(|p| p.bolt12.clone())
+                .filter(|bolt12| !cache.contains_key(bolt12))
+                .collect()
+        };
+
+        let mut decodes = JoinSet::new();
+        for bolt12 in missing {
+            let mut node = self.node.clone();
+            decodes.spawn(async move {
+                let decoded = node
+                    .decode(traced_request(cln::DecodeRequest {
+                        string: bolt12.clone(),
+                    }))
+                    .await;
+                (bolt12, decoded)
+            });
+        }
+        while let Some(result) = decodes.join_next().await {
+            let Ok((bolt12, decoded)) = result else {
+                continue;
+            };
+            // Failures must not fail the surrounding call, and aren't kept
+            // so the next listing tries again.
+            match decoded {
+                Ok(r) => {
+                    self.bolt12_cache
+                        .lock()
+                        .unwrap()
+                        .insert(bolt12, r.into_inner().into());
+                }
+                Err(e) => eprintln!("Failed to decode bolt12 invoice: {:?}", e),
+            }
+        }
+
+        let cache = self.bolt12_cache.lock().unwrap();
+        for payment in payments.iter_mut() {
+            let Some(metadata) = payment.bolt12.as_ref().and_then(|b| cache.get(b)) else {
+                continue;
+            };
+            payment.invreq_payer_note = metadata.invreq_payer_note.clone();
+            payment.offer_description = metadata.offer_description.clone();
+            payment.offer_issuer = metadata.offer_issuer.clone();
+        }
+    }
+
+    /// Decodes a BOLT12 invoice for display purposes, unless it was decoded
+    /// before. Failures are logged and yield empty metadata, since they must
+    /// not fail the surrounding call.
+    async fn bolt12_metadata(&self, bolt12: String) -> Bolt12Metadata {
+        if let Some(metadata) = self.bolt12_cache.lock().unwrap().get(&bolt12) {
+            return metadata.clone();
+        }
+
+        match self
+            .node
+            .clone()
+            .decode(traced_request(cln::DecodeRequest {
+                string: bolt12.clone(),
+            }))
+            .await
+        {
+            Ok(r) => {
+                let metadata = Bolt12Metadata::from(r.into_inner());
+                self.bolt12_cache
+                    .lock()
+                    .unwrap()
+                    .insert(bolt12, metadata.clone());
+                metadata
+            }
+            Err(e) => {
+                eprintln!("Failed to decode bolt12 invoice: {:?}", e);
+                Bolt12Metadata::default()
+            }
+        }
+    }
+
+    pub async fn decode(&self, invstring: String) -> Result<DecodeResponse> {
+        self.node
+            .clone()
+            .decode(traced_request(cln::DecodeRequest { string: invstring }))
+            .await
+            .context("failed to decode")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn sign_message(&self, req: SignMessageRequest) -> Result<SignMessageResponse> {
+        self.require_unlocked()?;
+
+        self.node
+            .clone()
+            .sign_message(traced_request(cln::SignmessageRequest::from(req)))
+            .await
+            .context("failed to sign message")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Converts a PSBT, such as the one returned by `withdraw`, to the given
+    /// version for wallets and coordinators that only accept one of them.
+    pub async fn set_psbt_version(
+        &self,
+        req: SetPsbtVersionRequest,
+    ) -> Result<SetPsbtVersionResponse> {
+        self.node
+            .clone()
+            .set_psbt_version(traced_request(cln::SetpsbtversionRequest::try_from(req)?))
+            .await
+            .context("failed to set psbt version")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn withdraw(&self, req: WithdrawRequest) -> Result<WithdrawResponse> {
+        self.require_unlocked()?;
+
+        let amount_msat = match req.amount {
+            Some(AmountOrAll::Amount { msat }) => Some(msat),
+            Some(AmountOrAll::All) | None => None,
+        };
+        let journal_id = self.journal_start(JournalOperation::Withdraw {
+            destination: req.destination.clone(),
+            amount_msat,
+        })?;
+
+        let result = self
+            .node
+            .clone()
+            .withdraw(traced_request(cln::WithdrawRequest::from(req)))
+            .await
+            .context("failed to withdraw")
+            .map_err(|e| SdkError::funds(e, amount_msat))
+            .map(|r| r.into_inner().into());
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+        result
+    }
+
+    pub async fn create_offer(&self, mut req: CreateOfferRequest) -> Result<CreateOfferResponse> {
+        self.require_unlocked()?;
+
+        let now = unix_now();
+        let mut expiry_secs = req.absolute_expiry.map(|at| at.saturating_sub(now));
+        self.apply_invoice_limits(req.amount_msat, &mut expiry_secs, None)?;
+        req.absolute_expiry = expiry_secs.map(|secs| now + secs);
+
+        self.node
+            .clone()
+            .offer(traced_request(cln::OfferRequest::from(req)))
+            .await
+            .context("failed to create offer")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn list_offers(&self, req: ListOffersRequest) -> Result<ListOffersResponse> {
+        self.node
+            .clone()
+            .list_offers(traced_request(cln::ListoffersRequest::try_from(req)?))
+            .await
+            .context("failed to list offers")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn disable_offer(&self, req: DisableOfferRequest) -> Result<DisableOfferResponse> {
+        self.node
+            .clone()
+            .disable_offer(traced_request(cln::DisableofferRequest::try_from(req)?))
+            .await
+            .context("failed to disable offer")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn create_invoice_request(
+        &self,
+        req: CreateInvoiceRequestRequest,
+    ) -> Result<CreateInvoiceRequestResponse> {
+        self.require_unlocked()?;
+
+        self.node
+            .clone()
+            .invoice_request(traced_request(cln::InvoicerequestRequest::from(req)))
+            .await
+            .context("failed to create invoice request")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn list_invoice_requests(
+        &self,
+        req: ListInvoiceRequestsRequest,
+    ) -> Result<ListInvoiceRequestsResponse> {
+        self.node
+            .clone()
+            .list_invoice_requests(traced_request(cln::ListinvoicerequestsRequest::try_from(
+                req,
+            )?))
+            .await
+            .context("failed to list invoice requests")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn disable_invoice_request(
+        &self,
+        req: DisableInvoiceRequestRequest,
+    ) -> Result<DisableInvoiceRequestResponse> {
+        self.node
+            .clone()
+            .disable_invoice_request(traced_request(cln::DisableinvoicerequestRequest::from(req)))
+            .await
+            .context("failed to disable invoice request")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn funder_update(&self, req: FunderUpdateRequest) -> Result<FunderUpdateResponse> {
+        self.node
+            .clone()
+            .funder_update(traced_request(cln::FunderupdateRequest::try_from(req)?))
+            .await
+            .context("failed to update funder policy")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Opens a dual-funded channel to `node_id`, buying inbound liquidity
+    /// from its liquidity ad. Fails without opening anything if the
+    /// advertised lease fee exceeds `max_lease_fee_msat`.
+    pub async fn lease_channel_from(
+        &self,
+        req: LeaseChannelRequest,
+    ) -> Result<LeaseChannelResponse> {
+        self.require_unlocked()?;
+
+        let node_id = hex::decode(&req.node_id)
+            .context("node id contains invalid hex value")
+            .map_err(SdkError::invalid_arg)?;
+
+        let will_fund = self
+            .node
+            .clone()
+            .list_nodes(traced_request(cln::ListnodesRequest {
+                id: Some(node_id.clone()),
+            }))
+            .await
+            .context("failed to look up liquidity ad")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .nodes
+            .into_iter()
+            .find_map(|n| n.option_will_fund)
+            .ok_or_else(|| SdkError::InvalidArgument {
+                message: format!("node {} does not advertise liquidity", req.node_id),
+                request_id: None,
+            })?;
+
+        // The seller's share of the funding transaction is part of the fee
+        // and depends on the feerate, so pin the one the fee was checked at.
+        let feerate_perkw = self.opening_feerate().await?;
+        let lease_fee_msat = lease_fee_msat(&will_fund, req.lease_amount_msat, feerate_perkw);
+        if lease_fee_msat > req.max_lease_fee_msat {
+            return Err(SdkError::InvalidArgument {
+                message: format!(
+                    "lease fee of {} msat exceeds maximum of {} msat",
+                    lease_fee_msat, req.max_lease_fee_msat
+                ),
+                request_id: None,
+            });
+        }
+
+        let response = self
+            .node
+            .clone()
+            .fund_channel(traced_request(cln::FundchannelRequest {
+                id: node_id,
+                amount: Some(cln::AmountOrAll {
+                    value: Some(cln::amount_or_all::Value::Amount(cln::Amount {
+                        msat: req.our_amount_msat,
+                    })),
+                }),
+                announce: req.announce,
+                minconf: req.minconf,
+                request_amt: Some(cln::Amount {
+                    msat: req.lease_amount_msat,
+                }),
+                compact_lease: Some(hex::encode(will_fund.compact_lease)),
+                feerate: Some(cln::Feerate {
+                    style: Some(cln::feerate::Style::Perkw(feerate_perkw)),
+                }),
+                ..Default::default()
+            }))
+            .await
+            .context("failed to lease channel")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+
+        Ok(LeaseChannelResponse {
+            txid: hex::encode(response.txid),
+            lease_fee_msat,
+        })
+    }
+
+    /// Changes the node alias and/or color (as `#rrggbb`) and returns the
+    /// refreshed node info. Greenlight only applies options it treats as
+    /// dynamic, in which case the node error is returned unchanged.
+    pub async fn set_appearance(&self, req: SetAppearanceRequest) -> Result<GetInfoResponse> {
+        if let Some(alias) = req.alias {
+            // BOLT7 node aliases are 32 bytes.
+            if alias.len() > 32 {
+                return Err(SdkError::InvalidArgument {
+                    message: String::from("alias must not be longer than 32 bytes"),
+                    request_id: None,
+                });
+            }
+            self.set_config("alias", alias).await?;
+        }
+
+        if let Some(color) = req.color {
+            let rgb = color.trim_start_matches('#');
+            if rgb.len() != 6 || hex::decode(rgb).is_err() {
+                return Err(SdkError::InvalidArgument {
+                    message: String::from("color must be formatted as #rrggbb"),
+                    request_id: None,
+                });
+            }
+            self.set_config("rgb", rgb.to_string()).await?;
+        }
+
+        self.get_info().await
+    }
+
+    async fn set_config(&self, config: &str, val: String) -> Result<()> {
+        self.node
+            .clone()
+            .set_config(traced_request(cln::SetconfigRequest {
+                config: config.to_string(),
+                val: Some(val),
+            }))
+            .await
+            .with_context(|| format!("failed to set {}", config))
+            .map_err(SdkError::greenlight_api)
+            .map(|_| ())
+    }
+
+    /// Requests an invoice for a BOLT12 offer without paying it, so the host
+    /// can confirm the details before passing the invoice to `pay`.
+    pub async fn fetch_invoice(&self, req: FetchInvoiceRequest) -> Result<FetchInvoiceResponse> {
+        self.require_unlocked()?;
+
+        let fetched = self
+            .node
+            .clone()
+            .fetch_invoice(traced_request(cln::FetchinvoiceRequest::from(req)))
+            .await
+            .context("failed to fetch invoice")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+
+        let decoded = self
+            .node
+            .clone()
+            .decode(traced_request(cln::DecodeRequest {
+                string: fetched.invoice.clone(),
+            }))
+            .await
+            .context("failed to decode fetched invoice")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+
+        Ok(FetchInvoiceResponse::new(fetched, decoded))
+    }
+
+    /// Fetches an invoice for a BOLT12 offer and pays it straight away, for
+    /// when there is nothing to confirm with the user in between.
+    pub async fn pay_offer(&self, req: FetchInvoiceRequest) -> Result<PayResponse> {
+        self.require_unlocked()?;
+
+        let fetched = self.fetch_invoice(req).await?;
+        self.pay(PayRequest {
+            bolt11: fetched.invoice,
+            description: None,
+            amount_msat: None,
+            maxfee_msat: None,
+            maxfeepercent: None,
+            exemptfee_msat: None,
+            retry_for: None,
+            maxdelay: None,
+            label: None,
+        })
+        .await
+    }
+
+    /// Number of invoices, optionally only those with `status`. Counts are
+    /// kept between calls, so only invoices created or updated since the
+    /// last count are listed. Deleting invoices starts the count over.
+    pub async fn count_invoices(&self, status: Option<ListInvoicesStatus>) -> Result<u64> {
+        use cln::listinvoices_request::ListinvoicesIndex;
+
+        let list_since = |index: ListinvoicesIndex, start: u64| {
+            let mut node = self.node.clone();
+            async move {
+                node.list_invoices(traced_request(cln::ListinvoicesRequest {
+                    index: Some(index as i32),
+                    start: Some(start),
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to list invoices")
+                .map_err(SdkError::greenlight_api)
+                .map(|r| r.into_inner().invoices)
+            }
+        };
+
+        let mut counts = self.invoice_counts.lock().unwrap().clone();
+        let deleted = self.deleted_invoice_index().await?;
+        if deleted != counts.deleted {
+            counts = InvoiceCounts {
+                deleted,
+                ..Default::default()
+            };
+        }
+
+        // Read before listing new invoices: they are listed with their
+        // current status, so only later updates matter.
+        let updated = self
+            .current_invoice_index(ListInvoicesIndex::Updated)
+            .await?;
+        let fresh = counts.created == 0;
+        for invoice in list_since(ListinvoicesIndex::Created, counts.created + 1).await? {
+            counts.add(&invoice);
+        }
+        if !fresh && counts.updated < updated {
+            for invoice in list_since(ListinvoicesIndex::Updated, counts.updated + 1).await? {
+                counts.update(&invoice);
+            }
+        }
+        counts.updated = counts.updated.max(updated);
+
+        let count = counts.count(
+            status
+                .map(cln::listinvoices_invoices::ListinvoicesInvoicesStatus::from)
+                .map(|s| s as i32),
+        );
+        *self.invoice_counts.lock().unwrap() = counts;
+        Ok(count)
+    }
+
+    async fn deleted_invoice_index(&self) -> Result<u64> {
+        Ok(self
+            .node
+            .clone()
+            .wait(traced_request(cln::WaitRequest {
+                subsystem: cln::wait_request::WaitSubsystem::Invoices as i32,
+                indexname: cln::wait_request::WaitIndexname::Deleted as i32,
+                nextvalue: 0,
+            }))
+            .await
+            .context("failed to get current invoice index")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .deleted
+            .unwrap_or(0))
+    }
+
+    /// Number of payments, optionally only those with `status`.
+    pub async fn count_payments(&self, status: Option<ListPaymentsStatus>) -> Result<u64> {
+        let pays = self
+            .node
+            .clone()
+            .list_pays(traced_request(cln::ListpaysRequest {
+                status: status
+                    .map(cln::listpays_request::ListpaysStatus::from)
+                    .map(|s| s as i32),
+                ..Default::default()
+            }))
+            .await
+            .context("failed to list payments")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .pays;
+
+        Ok(pays.len() as u64)
+    }
+
+    pub async fn list_invoices_paged(&self, page: Page) -> Result<PagedInvoices> {
+        let index = page.index.unwrap_or(PageIndex::Created);
+        let invoices = self
+            .list_invoices(ListInvoicesRequest {
+                label: None,
+                invstring: None,
+                payment_hash: None,
+                offer_id: None,
+                index: Some(index.into()),
+                start: page.start,
+                limit: page.limit,
+                created_after: None,
+                paid_after: None,
+                include_sensitive: false,
+            })
+            .await?
+            .invoices;
+
+        Ok(Paged::new(invoices, page.limit, |invoice| match index {
+            PageIndex::Created => invoice.created_index,
+            PageIndex::Updated => invoice.updated_index,
+        }))
+    }
+
+    /// listpays has no index of its own, so payments are numbered by their
+    /// position in creation order and sliced here.
+    pub async fn list_payments_paged(&self, page: Page) -> Result<PagedPayments> {
+        if let Some(PageIndex::Updated) = page.index {
+            return Err(SdkError::InvalidArgument {
+                message: String::from("payments can only be paged by creation"),
+                request_id: None,
+            });
+        }
+
+        let mut payments: Vec<ListPaymentsPayment> = self
+            .node
+            .clone()
+            .list_pays(traced_request(cln::ListpaysRequest::default()))
+            .await
+            .context("failed to list payments")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .pays
+            .into_iter()
+            .map(|payment| ListPaymentsPayment::new(payment, self.byte_fields()))
+            .collect();
+        payments.sort_by_key(|p| p.created_at);
+
+        let start = page.start.unwrap_or(1).max(1);
+        let mut items: Vec<_> = payments
+            .into_iter()
+            .skip((start - 1) as usize)
+            .take(page.limit.map_or(usize::MAX, |l| l as usize))
+            .collect();
+        self.add_bolt12_metadata(&mut items).await;
+        if self.redacts(false) {
+            items.iter_mut().for_each(ListPaymentsPayment::redact);
+        }
+
+        let last_index = start + items.len() as u64 - 1;
+        Ok(Paged::new(items, page.limit, |_| Some(last_index)))
+    }
+
+    pub async fn list_forwards(
+        &self,
+        req: ListForwardsRequest,
+        page: Page,
+    ) -> Result<PagedForwards> {
+        use cln::listforwards_request::{ListforwardsIndex, ListforwardsStatus};
+
+        let index = page.index.unwrap_or(PageIndex::Created);
+        let forwards = self
+            .node
+            .clone()
+            .list_forwards(traced_request(cln::ListforwardsRequest {
+                status: req.status.map(ListforwardsStatus::from).map(|s| s as i32),
+                in_channel: req.in_channel,
+                out_channel: req.out_channel,
+                index: Some(match index {
+                    PageIndex::Created => ListforwardsIndex::Created,
+                    PageIndex::Updated => ListforwardsIndex::Updated,
+                } as i32),
+                start: page.start,
+                limit: page.limit,
+            }))
+            .await
+            .context("failed to list forwards")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .forwards
+            .into_iter()
+            .map(Forward::from)
+            .collect();
+
+        Ok(Paged::new(forwards, page.limit, |forward| match index {
+            PageIndex::Created => forward.created_index,
+            PageIndex::Updated => forward.updated_index,
+        }))
+    }
+
+    pub async fn list_peer_channels(
+        &self,
+        req: ListPeerChannelsRequest,
+    ) -> Result<ListPeerChannelsResponse> {
+        let enrich = req.enrich;
+        let mut response: ListPeerChannelsResponse = self
+            .node
+            .clone()
+            .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::try_from(req)?))
+            .await
+            .context("failed to list peer channels")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .into();
+
+        if enrich {
+            let aliases = self
+                .peer_aliases(response.channels.iter().filter_map(|c| c.peer_id.clone()))
+                .await;
+            for channel in response.channels.iter_mut() {
+                channel.peer_alias = channel
+                    .peer_id
+                    .as_ref()
+                    .and_then(|peer_id| aliases.get(peer_id).cloned());
+            }
+        }
+        Ok(response)
+    }
+
+    pub async fn list_peers(&self, req: ListPeersRequest) -> Result<ListPeersResponse> {
+        self.node
+            .clone()
+            .list_peers(traced_request(cln::ListpeersRequest::try_from(req)?))
+            .await
+            .context("failed to list peers")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// The node's current feerates in `style`, e.g. for picking the feerate of
+    /// a `withdraw`.
+    pub async fn fee_rates(&self, style: FeeratesStyle) -> Result<FeeratesResponse> {
+        self.node
+            .clone()
+            .feerates(traced_request(cln::FeeratesRequest {
+                style: cln::feerates_request::FeeratesStyle::from(style) as i32,
+            }))
+            .await
+            .context("failed to get feerates")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Finds a route for paying `amount_msat` to `destination` without paying,
+    /// to check reachability and estimate fees. Higher `riskfactor`s prefer
+    /// routes with shorter delays. `exclude` lists channels (as
+    /// `<short_channel_id>/<direction>`) or node ids to route around.
+    pub async fn get_route(
+        &self,
+        destination: String,
+        amount_msat: u64,
+        riskfactor: u64,
+        exclude: Vec<String>,
+    ) -> Result<GetRouteResponse> {
+        let id = hex::decode(&destination)
+            .context("destination contains invalid hex value")
+            .map_err(SdkError::invalid_arg)?;
+
+        self.node
+            .clone()
+            .get_route(traced_request(cln::GetrouteRequest {
+                id,
+                amount_msat: Some(cln::Amount { msat: amount_msat }),
+                riskfactor,
+                exclude,
+                ..Default::default()
+            }))
+            .await
+            .context("failed to get route")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| GetRouteResponse::new(r.into_inner(), amount_msat))
+    }
+
+    pub async fn send_pay(&self, req: SendPayRequest) -> Result<SendPayResponse> {
+        self.require_unlocked()?;
+
+        self.node
+            .clone()
+            .send_pay(traced_request(cln::SendpayRequest::try_from(req)?))
+            .await
+            .context("failed to send payment")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Waits for the outcome of a `send_pay` attempt, for at most `timeout`
+    /// seconds if given. `partid` picks one part of a multi-part payment.
+    pub async fn wait_send_pay(
+        &self,
+        payment_hash: String,
+        timeout: Option<u32>,
+        partid: Option<u64>,
+    ) -> Result<WaitSendPayResponse> {
+        let payment_hash = hex::decode(&payment_hash)
+            .context("payment hash contains invalid hex value")
+            .map_err(SdkError::invalid_arg)?;
+
+        let result = self
+            .node
+            .clone()
+            .wait_send_pay(traced_request(cln::WaitsendpayRequest {
+                payment_hash,
+                timeout,
+                partid,
+                ..Default::default()
+            }))
+            .await
+            .context("payment attempt failed")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into());
+
+        self.check_balance_alerts();
+        result
+    }
+
+    /// Estimates the cost of opening a channel of `amount_sat`. Without a
+    /// `feerate_perkw` the node's current opening feerate is used.
+    pub async fn estimate_channel_open(
+        &self,
+        amount_sat: u64,
+        feerate_perkw: Option<u32>,
+    ) -> Result<ChannelOpenEstimate> {
+        let feerate_perkw = match feerate_perkw {
+            Some(feerate) => feerate,
+            None => self.opening_feerate().await?,
+        };
+
+        Ok(ChannelOpenEstimate::new(amount_sat, feerate_perkw))
+    }
+
+    /// The feerate the node currently opens channels at, per kiloweight.
+    async fn opening_feerate(&self) -> Result<u32> {
+        self.node
+            .clone()
+            .feerates(traced_request(cln::FeeratesRequest {
+                style: cln::feerates_request::FeeratesStyle::Perkw as i32,
+            }))
+            .await
+            .context("failed to get feerates")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .perkw
+            .and_then(|perkw| perkw.opening)
+            .context("node has no opening feerate estimate")
+            .map_err(SdkError::greenlight_api)
+    }
+
+    /// Waits until the node has seen `block_height`, failing once `timeout`
+    /// seconds have passed.
+    pub async fn wait_block_height(
+        &self,
+        req: WaitBlockHeightRequest,
+    ) -> Result<WaitBlockHeightResponse> {
+        self.node
+            .clone()
+            .wait_block_height(traced_request(cln::WaitblockheightRequest::from(req)))
+            .await
+            .context("failed to wait for block height")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn chain_status(&self) -> Result<ChainStatus> {
+        self.node
+            .clone()
+            .getinfo(traced_request(cln::GetinfoRequest::default()))
+            .await
+            .context("failed to get chain status")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Addresses generated through `new_address`, oldest first. Addresses
+    /// created by other tools for the same node are not included.
+    pub async fn list_addresses(&self) -> Result<ListAddressesResponse> {
+        let (addresses, outputs) = tokio::try_join!(
+            async {
+                address_history::load(self.node.clone())
+                    .await
+                    .map_err(SdkError::greenlight_api)
+            },
+            self.list_funds(ListFundsRequest {
+                spent: Some(true),
+                enrich: false,
+            }),
+        )?;
+
+        Ok(ListAddressesResponse {
+            addresses: addresses
+                .into_iter()
+                .map(|(address, created_at)| {
+                    let received: Vec<_> = outputs
+                        .outputs
+                        .iter()
+                        .filter(|o| o.address.as_deref() == Some(address.as_str()))
+                        .collect();
+                    ListAddressesAddress {
+                        used: !received.is_empty(),
+                        received_msat: received.iter().filter_map(|o| o.amount_msat).sum(),
+                        address,
+                        created_at,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// Creates a bolt11 invoice, a bolt12 offer and an on-chain address for
+    /// the same payment at once.
+    pub async fn create_unified_receive(
+        &self,
+        req: UnifiedReceiveRequest,
+    ) -> Result<UnifiedReceiveResponse> {
+        self.require_unlocked()?;
+
+        // The offer would never expire by default, cap both by the same limit.
+        let mut expiry_secs = None;
+        self.apply_invoice_limits(req.amount_msat, &mut expiry_secs, None)?;
+        let absolute_expiry = expiry_secs.map(|secs| unix_now() + secs);
+
+        let label = format!("unified-{}", hex::encode(rand::random::<[u8; 8]>()));
+
+        let invoice = async {
+            self.node
+                .clone()
+                .invoice(traced_request(cln::InvoiceRequest {
+                    label: label.clone(),
+                    amount_msat: Some(cln::AmountOrAny {
+                        value: Some(match req.amount_msat {
+                            Some(msat) => cln::amount_or_any::Value::Amount(cln::Amount { msat }),
+                            None => cln::amount_or_any::Value::Any(true),
+                        }),
+                    }),
+                    description: req.description.clone(),
+                    expiry: expiry_secs,
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to create invoice")
+                .map_err(SdkError::greenlight_api)
+                .map(|r| r.into_inner())
+        };
+        let offer = async {
+            self.node
+                .clone()
+                .offer(traced_request(cln::OfferRequest {
+                    amount: req
+                        .amount_msat
+                        .map_or(String::from("any"), |msat| format!("{}msat", msat)),
+                    description: req.description.clone(),
+                    label: Some(label.clone()),
+                    absolute_expiry,
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to create offer")
+                .map_err(SdkError::greenlight_api)
+                .map(|r| r.into_inner())
+        };
+        let address = self.new_address(NewAddressRequest {
+            address_type: Some(NewAddressType::Bech32),
+        });
+
+        let (invoice, offer, address) = tokio::try_join!(invoice, offer, address)?;
+        let address = address
+            .bech32
+            .context("node did not return a bech32 address")
+            .map_err(SdkError::greenlight_api)?;
+
+        let mut uri = format!("bitcoin:{}?", address);
+        if let Some(msat) = req.amount_msat {
+            let sat = msat / 1000;
+            let btc = format!("{}.{:08}", sat / 100_000_000, sat % 100_000_000);
+            uri.push_str(&format!(
+                "amount={}&",
+                btc.trim_end_matches('0').trim_end_matches('.')
+            ));
+        }
+        uri.push_str(&format!(
+            "lightning={}&lno={}",
+            invoice.bolt11, offer.bolt12
+        ));
+
+        Ok(UnifiedReceiveResponse {
+            bolt11: invoice.bolt11,
+            payment_hash: hex::encode(invoice.payment_hash),
+            offer_id: hex::encode(offer.offer_id),
+            bolt12: offer.bolt12,
+            address,
+            uri,
+        })
+    }
+
+    /// Signs a (possibly modified) bolt11 invoice with the node key.
+    pub async fn sign_invoice(&self, req: SignInvoiceRequest) -> Result<SignInvoiceResponse> {
+        self.require_unlocked()?;
+
+        self.node
+            .clone()
+            .sign_invoice(traced_request(cln::SigninvoiceRequest::from(req)))
+            .await
+            .context("failed to sign invoice")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    pub async fn backup_status(&self) -> Result<BackupStatus> {
+        self.backup_monitor
+            .status(self.node.clone())
+            .await
+            .map_err(SdkError::greenlight_api)
+    }
+
+    /// Sums up channel and on-chain balances, holding back the on-chain
+    /// reserve CLN keeps for anchor fee-bumping while channels are open.
+    pub async fn get_balances(&self) -> Result<BalanceSummary> {
+        balance_alerts::summary(self.node.clone())
+            .await
+            .map_err(SdkError::greenlight_api)
+    }
+
+    /// Deletes an unpaid invoice so HTLCs arriving for its payment hash are
+    /// failed back instead of settled. Greenlight nodes don't let clients
+    /// install an `htlc_accepted` hook, so inbound HTLCs can't be held or
+    /// inspected before settlement; voiding the invoice up front is the way
+    /// to refuse a payment.
+    pub async fn void_invoice(&self, req: VoidInvoiceRequest) -> Result<VoidInvoiceResponse> {
+        self.node
+            .clone()
+            .del_invoice(traced_request(cln::DelinvoiceRequest::from(req)))
+            .await
+            .context("failed to void invoice")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Times `samples` scheduler and node round trips one after another.
+    /// Defaults to 5 samples.
+    pub async fn measure_scheduler_latency(
+        &self,
+        samples: Option<u32>,
+    ) -> Result<SchedulerLatency> {
+        let samples = samples.unwrap_or(5);
+        if samples == 0 {
+            return Err(SdkError::InvalidArgument {
+                message: String::from("samples must be at least 1"),
+                request_id: None,
+            });
+        }
+
+        let mut scheduler_times = Vec::new();
+        let mut node_times = Vec::new();
+        let mut node_uri = String::new();
+        for _ in 0..samples {
+            let started = Instant::now();
+            node_uri = self
+                .scheduler
+                .schedule()
+                .await
+                .context("failed to reach scheduler")
+                .map_err(SdkError::greenlight_api)?
+                .grpc_uri;
+            scheduler_times.push(started.elapsed());
+
+            let started = Instant::now();
+            self.node
+                .clone()
+                .getinfo(traced_request(cln::GetinfoRequest::default()))
+                .await
+                .context("failed to reach node")
+                .map_err(SdkError::greenlight_api)?;
+            node_times.push(started.elapsed());
+        }
+
+        Ok(SchedulerLatency {
+            samples,
+            scheduler: LatencyPercentiles::new(scheduler_times),
+            node: LatencyPercentiles::new(node_times),
+            node_uri,
+        })
+    }
+
+    /// Sums up routing fees and volume per channel from settled forwards.
+    pub async fn channel_earnings(
+        &self,
+        req: ChannelEarningsRequest,
+    ) -> Result<ChannelEarningsResponse> {
+        let list_forwards = |in_channel: Option<String>, out_channel: Option<String>| {
+            let mut node = self.node.clone();
+            async move {
+                node.list_forwards(traced_request(cln::ListforwardsRequest {
+                    status: Some(cln::listforwards_request::ListforwardsStatus::Settled as i32),
+                    in_channel,
+                    out_channel,
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to list forwards")
+                .map_err(SdkError::greenlight_api)
+                .map(|r| r.into_inner().forwards)
+            }
+        };
+
+        let forwards = match &req.short_channel_id {
+            Some(channel) => {
+                let (incoming, outgoing) = tokio::try_join!(
+                    list_forwards(Some(channel.clone()), None),
+                    list_forwards(None, Some(channel.clone())),
+                )?;
+                // A forward that loops through the same channel shows up in both.
+                incoming
+                    .into_iter()
+                    .chain(outgoing.into_iter().filter(|f| &f.in_channel != channel))
+                    .collect()
+            }
+            None => list_forwards(None, None).await?,
+        };
+
+        Ok(ChannelEarningsResponse::new(forwards, &req))
+    }
+
+    /// Pays `bolt11`, and when an attempt fails at a specific channel, tries
+    /// again with that channel excluded. Stops at the first success, after
+    /// `max_attempts`, or when a failure doesn't name a channel to avoid.
+    pub async fn pay_with_retry(&self, req: PayWithRetryRequest) -> Result<PayWithRetryResponse> {
+        self.require_unlocked()?;
+
+        let max_attempts = req.max_attempts.unwrap_or(3);
+        if max_attempts == 0 {
+            return Err(SdkError::InvalidArgument {
+                message: String::from("max_attempts must be at least 1"),
+                request_id: None,
+            });
+        }
+
+        let journal_id = self.journal_start(JournalOperation::Pay {
+            bolt11: req.bolt11.clone(),
+        })?;
+
+        let mut excluded: Vec<String> = Vec::new();
+        let mut attempts = Vec::new();
+        let mut result = Err(SdkError::InvalidArgument {
+            message: String::from("no attempt made"),
+            request_id: None,
+        });
+        for _ in 0..max_attempts {
+            let attempt = self
+                .node
+                .clone()
+                .pay(traced_request(cln::PayRequest {
+                    bolt11: req.bolt11.clone(),
+                    exclude: excluded.clone(),
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to pay invoice");
+            let channel = attempt.as_ref().err().and_then(erring_channel);
+            // Retrying while HTLCs of the failed call are still in flight
+            // could pay twice, so failures are settled like `pay` does.
+            result = match attempt {
+                Ok(r) => Ok(PayResponse::from(r.into_inner())),
+                Err(e) => {
+                    self.settle_pay_error(&req.bolt11, SdkError::greenlight_api(e))
+                        .await
+                }
+            };
+
+            attempts.push(PaymentAttempt {
+                excluded: excluded.clone(),
+                error: result.as_ref().err().map(SdkError::to_string),
+            });
+
+            match (&result, channel) {
+                (Err(SdkError::GreenlightApi { .. }), Some(channel))
+                    if !excluded.contains(&channel) =>
+                {
+                    excluded.push(channel)
+                }
+                _ => break,
+            }
+        }
+
+        self.journal_finish(journal_id, &result);
+        self.check_balance_alerts();
+
+        Ok(PayWithRetryResponse {
+            payment: result.ok(),
+            attempts,
+        })
+    }
+
+    /// Every HTLC CLN still remembers, for auditing what crossed the node.
+    pub async fn list_htlcs(&self, req: ListHtlcsRequest) -> Result<ListHtlcsResponse> {
+        self.node
+            .clone()
+            .list_htlcs(traced_request(cln::ListhtlcsRequest::from(req)))
+            .await
+            .context("failed to list htlcs")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// Recommends the final CLTV delta for new invoices from the current
+    /// block height, the node's sync state, the deltas peers announced for
+    /// their channels to us and pending HTLC expirations. Reused for
+    /// `CLTV_RECOMMENDATION_TTL`, since `make_invoice` asks every time.
+    pub async fn recommend_invoice_cltv(&self) -> Result<CltvRecommendation> {
+        if let Some((at, recommendation)) = self.cltv_recommendation.lock().unwrap().as_ref() {
+            if at.elapsed() < CLTV_RECOMMENDATION_TTL {
+                return Ok(recommendation.clone());
+            }
+        }
+
+        let (info, channels, updates) = tokio::try_join!(
+            async {
+                self.node
+                    .clone()
+                    .getinfo(traced_request(cln::GetinfoRequest::default()))
+                    .await
+                    .context("failed to get info")
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::default()))
+                    .await
+                    .context("failed to list peer channels")
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_channels(traced_request(cln::ListchannelsRequest {
+                        destination: Some(self.node_id.clone()),
+                        ..Default::default()
+                    }))
+                    .await
+                    .context("failed to list channels")
+            },
+        )
+        .map_err(SdkError::greenlight_api)?;
+
+        let peer_deltas: Vec<u32> = updates
+            .into_inner()
+            .channels
+            .iter()
+            .filter(|u| u.active)
+            .map(|u| u.delay)
+            .collect();
+        let recommendation = CltvRecommendation::new(
+            &info.into_inner(),
+            &channels.into_inner().channels,
+            &peer_deltas,
+        );
+        *self.cltv_recommendation.lock().unwrap() = Some((Instant::now(), recommendation.clone()));
+        Ok(recommendation)
+    }
+
+    /// Sends a JSON-RPC request to an LSP over LSPS0 and waits for its
+    /// result. This is the transport LSPS1, LSPS2 and other protocols run on.
+    pub async fn lsps0_request(&self, req: Lsps0Request) -> Result<Lsps0Response> {
+        let peer_id = hex::decode(&req.peer_id)
+            .context("peer id contains invalid hex value")
+            .map_err(SdkError::invalid_arg)?;
+        let params = serde_json::from_str(&req.params)
+            .context("params are not valid JSON")
+            .map_err(SdkError::invalid_arg)?;
+        let timeout = Duration::from_secs(req.timeout_secs.unwrap_or(60));
+
+        let result = self
+            .lsps0
+            .request(self.node.clone(), peer_id, &req.method, params, timeout)
+            .await
+            .map_err(SdkError::greenlight_api)?;
+
+        Ok(Lsps0Response {
+            result: result.to_string(),
+        })
+    }
+
+    /// The LSP protocols a peer supports, from `lsps0.list_protocols`.
+    pub async fn lsps0_list_protocols(&self, peer_id: String) -> Result<Vec<u32>> {
+        let response = self
+            .lsps0_request(Lsps0Request {
+                peer_id,
+                method: String::from("lsps0.list_protocols"),
+                params: String::from("{}"),
+                timeout_secs: None,
+            })
+            .await?;
+
+        let result: serde_json::Value = serde_json::from_str(&response.result)
+            .context("failed to decode protocols")
+            .map_err(SdkError::greenlight_api)?;
+        Ok(result["protocols"]
+            .as_array()
+            .map(|protocols| {
+                protocols
+                    .iter()
+                    .filter_map(|p| p.as_u64())
+                    .map(|p| p as u32)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Waits up to `timeout_secs` for invoices paid after `last_pay_index`
+    /// and returns all that were paid by then. An empty result means the
+    /// timeout passed without a payment. Start with a `last_pay_index` of 0.
+    pub async fn poll_invoice_updates(
+        &self,
+        last_pay_index: u64,
+        timeout_secs: u64,
+    ) -> Result<PollInvoiceUpdatesResponse> {
+        let first_paid =
+            invoice_events::first_paid_after(self.node.clone(), last_pay_index, timeout_secs)
+                .await
+                .map_err(SdkError::greenlight_api)?;
+        let Some(start) = first_paid else {
+            return Ok(PollInvoiceUpdatesResponse {
+                invoices: Vec::new(),
+                last_pay_index,
+            });
+        };
+
+        // Payments move invoices up the updated index in the order they
+        // are paid, so the rest were updated after the first.
+        let mut invoices: Vec<ListInvoicesInvoice> = self
+            .list_invoices(ListInvoicesRequest {
+                label: None,
+                invstring: None,
+                payment_hash: None,
+                offer_id: None,
+                index: Some(ListInvoicesIndex::Updated),
+                start: Some(start),
+                limit: None,
+                created_after: None,
+                paid_after: None,
+                include_sensitive: false,
+            })
+            .await?
+            .invoices
+            .into_iter()
+            .filter(|i| i.pay_index.is_some_and(|index| index > last_pay_index))
+            .collect();
+        invoices.sort_by_key(|i| i.pay_index);
+
+        Ok(PollInvoiceUpdatesResponse {
+            last_pay_index: invoices
+                .last()
+                .and_then(|i| i.pay_index)
+                .unwrap_or(last_pay_index),
+            invoices,
+        })
+    }
+
+    /// Settled incoming and outgoing payments and on-chain deposits and
+    /// withdrawals in one list, with every entry appearing once. See
+    /// `ActivitySource` for which source wins when several report the same
+    /// entry. On-chain entries are dated from their block's depth, as the
+    /// node doesn't keep block times.
+    pub async fn list_activity(&self, limit: Option<u32>) -> Result<ListActivityResponse> {
+        let (invoices, payments, transactions, tip) = tokio::try_join!(
+            self.list_invoices(ListInvoicesRequest {
+                label: None,
+                invstring: None,
+                payment_hash: None,
+                offer_id: None,
+                index: None,
+                start: None,
+                limit: None,
+                created_after: None,
+                paid_after: None,
+                include_sensitive: false,
+            }),
+            self.list_payments(ListPaymentsRequest {
+                bolt11: None,
+                payment_hash: None,
+                status: Some(ListPaymentsStatus::Complete),
+                include_sensitive: false,
+            }),
+            self.list_transactions(),
+            async {
+                self.node
+                    .clone()
+                    .getinfo(traced_request(cln::GetinfoRequest::default()))
+                    .await
+                    .context("failed to get node info")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().blockheight)
+            },
+        )?;
+
+        let now = unix_now();
+        let entries = invoices
+            .invoices
+            .into_iter()
+            .filter(|i| i.paid_at.is_some())
+            .map(ActivityEntry::from_invoice)
+            .chain(
+                payments
+                    .payments
+                    .into_iter()
+                    .map(ActivityEntry::from_payment),
+            )
+            .chain(transactions.transactions.into_iter().filter_map(|tx| {
+                let timestamp = estimated_block_time(tx.blockheight, tip, now);
+                ActivityEntry::from_transaction(tx, timestamp)
+            }));
+
+        Ok(ListActivityResponse::new(entries, limit))
+    }
+
+    /// Lists the node's invoice requests as refunds, each with the payment
+    /// that settled it.
+    pub async fn list_refunds(&self) -> Result<Vec<Refund>> {
+        refunds::list(self.node.clone())
+            .await
+            .map_err(SdkError::greenlight_api)
+    }
+
+    /// Starts or stops emitting `RefundPaid` events.
+    pub async fn set_refund_events(&self, enabled: bool) {
+        let task =
+            enabled.then(|| tokio::spawn(refunds::watch(self.node.clone(), self.events.clone())));
+        if let Some(previous) = std::mem::replace(&mut *self.refund_watch.lock().unwrap(), task) {
+            previous.abort();
+        }
+    }
+
+    /// The CLN version the node is running, e.g. `v24.02gl1`.
+    pub async fn node_version(&self) -> Result<String> {
+        self.node
+            .clone()
+            .getinfo(traced_request(cln::GetinfoRequest::default()))
+            .await
+            .context("failed to get node info")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().version)
+    }
+
+    pub async fn supports(&self, feature: Feature) -> Result<bool> {
+        let info = self
+            .node
+            .clone()
+            .getinfo(traced_request(cln::GetinfoRequest::default()))
+            .await
+            .context("failed to get node info")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner();
+
+        let node_features = info.our_features.map(|f| f.node).unwrap_or_default();
+        Ok(features::supports(
+            feature,
+            &release_of(&info.version),
+            &node_features,
+        ))
+    }
+
+    /// Summarizes the node's configured limits and the ones in effect on its
+    /// channels.
+    pub async fn get_node_limits(&self) -> Result<NodeLimits> {
+        // Channels are capped at 2^24 - 1 sat unless both sides support
+        // large channels.
+        const MAX_STANDARD_CHANNEL_SAT: u64 = 16_777_215;
+
+        let (configs, info, channels) = tokio::try_join!(
+            async {
+                self.node
+                    .clone()
+                    .list_configs(traced_request(cln::ListconfigsRequest::default()))
+                    .await
+                    .context("failed to list configs")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().configs.unwrap_or_default())
+            },
+            async {
+                self.node
+                    .clone()
+                    .getinfo(traced_request(cln::GetinfoRequest::default()))
+                    .await
+                    .context("failed to get node info")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner())
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::default()))
+                    .await
+                    .context("failed to list peer channels")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().channels)
+            },
+        )?;
+
+        let node_features = info.our_features.map(|f| f.node).unwrap_or_default();
+        let large_channels = features::feature_names(&node_features)
+            .iter()
+            .any(|name| name == "option_support_large_channel");
+
+        Ok(NodeLimits {
+            max_htlc_value_in_flight_msat: channels
+                .iter()
+                .filter_map(|c| c.max_total_htlc_in_msat.as_ref().map(|a| a.msat))
+                .max(),
+            max_accepted_htlcs: configs.max_concurrent_htlcs.map(|c| c.value_int),
+            min_htlc_msat: configs
+                .htlc_minimum_msat
+                .and_then(|c| c.value_msat)
+                .map(|a| a.msat),
+            max_htlc_msat: configs
+                .htlc_maximum_msat
+                .and_then(|c| c.value_msat)
+                .map(|a| a.msat),
+            min_channel_size_sat: configs.min_capacity_sat.map(|c| c.value_int),
+            max_channel_size_sat: (!large_channels).then_some(MAX_STANDARD_CHANNEL_SAT),
+        })
+    }
+
+    pub async fn list_closed_channels(
+        &self,
+        req: ListClosedChannelsRequest,
+    ) -> Result<ListClosedChannelsResponse> {
+        self.node
+            .clone()
+            .list_closed_channels(traced_request(cln::ListclosedchannelsRequest::try_from(
+                req,
+            )?))
+            .await
+            .context("failed to list closed channels")
+            .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into())
+    }
+
+    /// The wallet's on-chain transactions, each marked with what it did for
+    /// the wallet. Channels the node has forgotten entirely can't be told
+    /// apart from other spends and show up as `Unknown` or `Withdrawal`.
+    pub async fn list_transactions(&self) -> Result<ListTransactionsResponse> {
+        let (transactions, funds, channels, closed) = tokio::try_join!(
+            async {
+                self.node
+                    .clone()
+                    .list_transactions(traced_request(cln::ListtransactionsRequest::default()))
+                    .await
+                    .context("failed to list transactions")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().transactions)
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_funds(traced_request(cln::ListfundsRequest { spent: Some(true) }))
+                    .await
+                    .context("failed to list funds")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().outputs)
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::default()))
+                    .await
+                    .context("failed to list peer channels")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().channels)
+            },
+            async {
+                self.node
+                    .clone()
+                    .list_closed_channels(traced_request(cln::ListclosedchannelsRequest::default()))
+                    .await
+                    .context("failed to list closed channels")
+                    .map_err(SdkError::greenlight_api)
+                    .map(|r| r.into_inner().closedchannels)
+            },
+        )?;
+
+        let wallet: HashSet<(String, u32)> = funds
+            .into_iter()
+            .map(|o| (hex::encode(o.txid), o.output))
+            .collect();
+        let funding: HashSet<(String, u32)> = channels
+            .into_iter()
+            .filter_map(|c| Some((hex::encode(c.funding_txid?), c.funding_outnum?)))
+            .chain(
+                closed
+                    .into_iter()
+                    .map(|c| (hex::encode(c.funding_txid), c.funding_outnum)),
+            )
+            .collect();
+
+        let mut transactions: Vec<Transaction> = transactions
+            .into_iter()
+            .map(|tx| {
+                let mut tx = Transaction::from(tx);
+                tx.annotate(&wallet, &funding);
+                tx
+            })
+            .collect();
+
+        // Outputs of a force close reach the wallet through a second
+        // transaction sweeping them; that belongs to the close, not a deposit.
+        let closes: HashSet<String> = transactions
+            .iter()
+            .filter(|tx| tx.kind == TransactionKind::ChannelClose)
+            .map(|tx| tx.txid.clone())
+            .collect();
+        for tx in &mut transactions {
+            if tx.kind != TransactionKind::ChannelOpen
+                && tx.inputs.iter().any(|i| closes.contains(&i.txid))
+            {
+                tx.kind = TransactionKind::ChannelClose;
+            }
+        }
+        Ok(ListTransactionsResponse { transactions })
+    }
+
+    /// Channel counts and amounts by state, the numbers at the top of a
+    /// channel dashboard.
+    pub async fn channel_stats(&self) -> Result<ChannelStats> {
+        let (channels, closed) = tokio::try_join!(self.all_peer_channels(), async {
+            self.node
+                .clone()
+                .list_closed_channels(traced_request(cln::ListclosedchannelsRequest::default()))
+                .await
+                .context("failed to list closed channels")
+                .map_err(SdkError::greenlight_api)
+                .map(|r| r.into_inner().closedchannels)
+        })?;
+
+        Ok(ChannelStats::new(channels, closed))
+    }
+
+    /// Fetches everything a wallet home screen needs with the RPCs running
+    /// concurrently. Recent invoices and payments are returned newest first.
+    pub async fn get_dashboard_snapshot(&self, recent_limit: u32) -> Result<DashboardSnapshot> {
+        let (info, funds, channels, recent_invoices, recent_payments) = tokio::try_join!(
+            self.get_info(),
+            self.list_funds(ListFundsRequest {
+                spent: None,
+                enrich: false,
+            }),
+            self.all_peer_channels(),
+            self.recent_invoices(recent_limit),
+            self.recent_payments(recent_limit),
+        )?;
+
+        let pending_channels = channels
+            .iter()
+            .filter_map(|c| PendingChannel::new(c, info.block_height))
+            .collect();
+
+        let store = self.snapshot_store.lock().unwrap().clone();
+        if let Some(store) = store {
+            snapshot_cache::save(store.as_ref(), &info, &channels);
+        }
+
+        Ok(DashboardSnapshot {
+            info,
+            funds,
+            channels,
+            pending_channels,
+            recent_invoices,
+            recent_payments,
+        })
+    }
+
+    /// The `limit` most recently created invoices, newest first, paged
+    /// backwards by creation index so the older history stays on the node.
+    async fn recent_invoices(&self, limit: u32) -> Result<Vec<ListInvoicesInvoice>> {
+        let mut invoices = Vec::new();
+        let mut end = self
+            .current_invoice_index(ListInvoicesIndex::Created)
+            .await?;
+        // Deleted invoices leave gaps in the index, so a page may come back
+        // short.
+        while end > 0 && invoices.len() < limit as usize {
+            let wanted = (limit as usize - invoices.len()) as u64;
+            let start = end.saturating_sub(wanted - 1).max(1);
+            let page = self
+                .list_invoices(ListInvoicesRequest {
+                    label: None,
+                    invstring: None,
+                    payment_hash: None,
+                    offer_id: None,
+                    index: Some(ListInvoicesIndex::Created),
+                    start: Some(start),
+                    limit: Some((end - start + 1) as u32),
+                    created_after: None,
+                    paid_after: None,
+                    include_sensitive: false,
+                })
+                .await?;
+            invoices.extend(page.invoices.into_iter().rev());
+            end = start - 1;
+        }
+        invoices.truncate(limit as usize);
+        Ok(invoices)
+    }
+
+    /// The `limit` most recently started payments, newest first. Their
+    /// hashes are found by paging the node's payment parts backwards by
+    /// creation index, and only those payments are then looked up.
+    async fn recent_payments(&self, limit: u32) -> Result<Vec<ListPaymentsPayment>> {
+        use cln::listsendpays_request::ListsendpaysIndex;
+
+        let mut hashes: Vec<String> = Vec::new();
+        let mut end = self
+            .node
+            .clone()
+            .wait(traced_request(cln::WaitRequest {
+                subsystem: cln::wait_request::WaitSubsystem::Sendpays as i32,
+                indexname: cln::wait_request::WaitIndexname::Created as i32,
+                nextvalue: 0,
+            }))
+            .await
+            .context("failed to get current payment index")
+            .map_err(SdkError::greenlight_api)?
+            .into_inner()
+            .created
+            .unwrap_or(0);
+
+        // A payment split into several parts shows up once per part.
+        while end > 0 && hashes.len() < limit as usize {
+            let start = end.saturating_sub(limit as u64 - 1).max(1);
+            let parts = self
+                .node
+                .clone()
+                .list_send_pays(traced_request(cln::ListsendpaysRequest {
+                    index: Some(ListsendpaysIndex::Created as i32),
+                    start: Some(start),
+                    limit: Some((end - start + 1) as u32),
+                    ..Default::default()
+                }))
+                .await
+                .context("failed to list payment parts")
+                .map_err(SdkError::greenlight_api)?
+                .into_inner()
+                .payments;
+            for part in parts.into_iter().rev() {
+                let hash = hex::encode(part.payment_hash);
+                if !hashes.contains(&hash) {
+                    hashes.push(hash);
+                }
+            }
+            end = start - 1;
+        }
+        hashes.truncate(limit as usize);
+
+        let mut payments = self.get_payments_by_hashes(hashes).await?;
+        payments.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+        Ok(payments)
+    }
+
+    async fn all_peer_channels(&self) -> Result<Vec<ListPeerChannelsChannel>> {
         self.node
             .clone()
-            .withdraw(cln::WithdrawRequest::from(req))
+            .list_peer_channels(traced_request(cln::ListpeerchannelsRequest::default()))
             .await
-            .context("failed to withdraw")
+            .context("failed to list peer channels")
             .map_err(SdkError::greenlight_api)
-            .map(|r| r.into_inner().into())
+            .map(|r| {
+                r.into_inner()
+                    .channels
+                    .into_iter()
+                    .map(ListPeerChannelsChannel::from)
+                    .collect()
+            })
     }
 
     pub async fn close(&self, req: CloseRequest) -> Result<CloseResponse> {
-        self.node
+        self.require_unlocked()?;
+
+        let result = self
+            .node
             .clone()
-            .close(cln::CloseRequest::from(req))
+            .close(traced_request(cln::CloseRequest::try_from(req)?))
             .await
             .context("failed to close channel")
             .map_err(SdkError::greenlight_api)
+            .map(|r| r.into_inner().into());
+
+        self.check_balance_alerts();
+        result
+    }
+
+    pub async fn set_channel(&self, req: SetChannelRequest) -> Result<SetChannelResponse> {
+        self.node
+            .clone()
+            .set_channel(traced_request(cln::SetchannelRequest::from(req)))
+            .await
+            .context("failed to set channel")
+            .map_err(SdkError::greenlight_api)
             .map(|r| r.into_inner().into())
     }
+
+    /// Runs a call under a fresh request id. The id is sent to the node with
+    /// every RPC the call makes, attached to errors, log lines and responses
+    /// and reported with timing in a `RequestCompleted` event, so a failure
+    /// seen by a user can be matched with Greenlight's logs.
+    pub(crate) async fn traced<T>(
+        &self,
+        method: &'static str,
+        f: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.trace(method, f).await.1
+    }
+
+    /// Like `traced`, and also sets the request id on the response.
+    pub(crate) async fn traced_response<T: TracedResponse>(
+        &self,
+        method: &'static str,
+        f: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let (request_id, result) = self.trace(method, f).await;
+        result.map(|mut response| {
+            *response.request_id() = Some(request_id);
+            response
+        })
+    }
+
+    async fn trace<T>(
+        &self,
+        method: &'static str,
+        f: impl Future<Output = Result<T>>,
+    ) -> (String, Result<T>) {
+        let request_id = hex::encode(rand::random::<[u8; 8]>());
+        let started = Instant::now();
+
+        let result = REQUEST_ID
+            .scope(request_id.clone(), f)
+            .await
+            .map_err(|e| e.with_request_id(&request_id));
+        if let Err(e) = &result {
+            eprintln!("[{}] {} failed: {}", request_id, method, e);
+        }
+
+        self.events.emit(ClientEvent::RequestCompleted {
+            request_id: request_id.clone(),
+            method: method.to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+
+        (request_id, result)
+    }
+
+    pub fn subscribe_events(&self, listener: Box<dyn EventListener>) {
+        self.events.subscribe(listener);
+        if let Some(event) = self.node_upgrade.lock().unwrap().take() {
+            self.events.emit(event);
+        }
+    }
+
+    /// Replaces the policy applied to channels opened by peers. Passing
+    /// `None` stops enforcement.
+    pub async fn set_channel_open_policy(&self, policy: Option<ChannelOpenPolicy>) {
+        let task = policy.map(|policy| {
+            tokio::spawn(channel_policy::enforce(
+                self.node.clone(),
+                policy,
+                self.events.clone(),
+            ))
+        });
+
+        if let Some(previous) = std::mem::replace(&mut *self.channel_policy.lock().unwrap(), task) {
+            previous.abort();
+        }
+    }
+
+    /// Replaces the thresholds for `LowBalance` events. Balances are checked
+    /// after every payment, withdrawal, channel open and close, and
+    /// periodically in between. Passing `None` turns the alerts off.
+    pub async fn set_balance_alerts(&self, thresholds: Option<BalanceAlertThresholds>) {
+        let alerts = thresholds.map(|thresholds| {
+            let alerts = Arc::new(BalanceAlerts::new(thresholds));
+            let task = tokio::spawn(balance_alerts::watch(
+                alerts.clone(),
+                self.node.clone(),
+                self.events.clone(),
+            ));
+            (alerts, task)
+        });
+
+        if let Some((_, previous)) =
+            std::mem::replace(&mut *self.balance_alerts.lock().unwrap(), alerts)
+        {
+            previous.abort();
+        }
+    }
+
+    fn check_balance_alerts(&self) {
+        let Some(alerts) = self
+            .balance_alerts
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(alerts, _)| alerts.clone())
+        else {
+            return;
+        };
+        let node = self.node.clone();
+        let events = self.events.clone();
+        tokio::spawn(async move { alerts.check(node, &events).await });
+    }
+
+    /// Calls `listener` for every invoice paid after `last_pay_index` (0 or
+    /// `None` for all of them) until the client shuts down. Invoices paid so
+    /// far are caught up on first, then `InvoicePaid` events are followed,
+    /// which are started if they aren't already.
+    pub async fn subscribe_invoices(
+        &self,
+        listener: Box<dyn InvoiceListener>,
+        last_pay_index: Option<u64>,
+    ) -> Result<()> {
+        if self.invoice_events.lock().unwrap().is_none() {
+            self.subscribe_invoice_events(None).await?;
+        }
+
+        let subscription =
+            invoice_events::Subscription::new(listener, last_pay_index.unwrap_or_default());
+        self.events.subscribe(Box::new(subscription.clone()));
+        let task = tokio::spawn(invoice_events::catch_up(self.node.clone(), subscription));
+        self.invoice_subscriptions.lock().unwrap().push(task);
+        Ok(())
+    }
+
+    /// Starts emitting `InvoicePaid` events. With a `start_index`, every
+    /// invoice settled since that `updated` index is replayed first; without
+    /// one only invoices settled from now on are reported. Replaces any
+    /// previous subscription.
+    pub async fn subscribe_invoice_events(&self, start_index: Option<u64>) -> Result<()> {
+        let start = match start_index {
+            Some(start) => start,
+            None => {
+                self.current_invoice_index(ListInvoicesIndex::Updated)
+                    .await?
+                    + 1
+            }
+        };
+
+        let task = tokio::spawn(invoice_events::follow(
+            self.node.clone(),
+            start,
+            self.events.clone(),
+        ));
+
+        if let Some(previous) = self.invoice_events.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Emits a `BackupStale` event when none of the channel peers has been
+    /// able to store the node's state for `max_age_secs`. Passing `None`
+    /// stops monitoring.
+    pub async fn set_backup_monitor(&self, max_age_secs: Option<u64>) {
+        let task = max_age_secs.map(|max_age| {
+            tokio::spawn(backup_monitor::watch(
+                self.backup_monitor.clone(),
+                self.node.clone(),
+                Duration::from_secs(max_age),
+                self.events.clone(),
+            ))
+        });
+
+        if let Some(previous) = std::mem::replace(&mut *self.backup_watch.lock().unwrap(), task) {
+            previous.abort();
+        }
+    }
+
+    /// Starts emitting `AddressDeposit` events for deposits until they have
+    /// `target_confirmations`, or stops with `None`.
+    pub async fn set_deposit_events(&self, target_confirmations: Option<u32>) {
+        let task = target_confirmations.map(|target| {
+            tokio::spawn(deposits::watch(
+                self.node.clone(),
+                target,
+                self.events.clone(),
+            ))
+        });
+
+        if let Some(previous) = std::mem::replace(&mut *self.deposit_watch.lock().unwrap(), task) {
+            previous.abort();
+        }
+    }
+
+    /// Starts or stops emitting `ChannelClosed` events.
+    pub async fn set_channel_close_events(&self, enabled: bool) {
+        let task = enabled.then(|| {
+            tokio::spawn(channel_closes::watch(
+                self.node.clone(),
+                self.events.clone(),
+            ))
+        });
+
+        if let Some(previous) = std::mem::replace(&mut *self.close_watch.lock().unwrap(), task) {
+            previous.abort();
+        }
+    }
+
+    /// In privacy mode invoice and payment lists leave out labels,
+    /// descriptions and payer notes, so apps can log responses without
+    /// leaking what was paid for. Requests can still ask for them with
+    /// `include_sensitive`.
+    pub fn set_privacy_mode(&self, enabled: bool) {
+        *self.privacy_mode.lock().unwrap() = enabled;
+    }
+
+    fn redacts(&self, include_sensitive: bool) -> bool {
+        *self.privacy_mode.lock().unwrap() && !include_sensitive
+    }
+
+    /// Applies the limits set with `set_invoice_limits`, see
+    /// `InvoiceLimits::apply`.
+    fn apply_invoice_limits(
+        &self,
+        amount_msat: Option<u64>,
+        expiry_secs: &mut Option<u64>,
+        default_expiry_secs: Option<u64>,
+    ) -> Result<()> {
+        match self.invoice_limits.lock().unwrap().as_ref() {
+            Some(limits) => limits.apply(amount_msat, expiry_secs, default_expiry_secs),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the limits new invoices and offers are held to. Passing
+    /// `None` removes them.
+    pub fn set_invoice_limits(&self, limits: Option<InvoiceLimits>) {
+        *self.invoice_limits.lock().unwrap() = limits;
+    }
+
+    /// Returns preimages and error onions in list responses as bytes
+    /// instead of hex strings, in the `*_bytes` field next to each.
+    pub fn set_byte_fields(&self, enabled: bool) {
+        *self.byte_fields.lock().unwrap() = enabled;
+    }
+
+    fn byte_fields(&self) -> bool {
+        *self.byte_fields.lock().unwrap()
+    }
+
+    /// Keeps the node info and channels from every dashboard snapshot in
+    /// `store`, so `get_cached_snapshot` has something to show on the next
+    /// start before the node is reachable.
+    pub fn set_snapshot_store(&self, store: Box<dyn SnapshotStore>) {
+        *self.snapshot_store.lock().unwrap() = Some(Arc::from(store));
+    }
+
+    /// Persists the optional storage features in `storage` instead of
+    /// memory. The snapshot cache moves over immediately; the journal and
+    /// payment queue use it once enabled with `enable_journal_in_storage`
+    /// and `enable_payment_queue_in_storage`.
+    pub fn set_storage(&self, storage: Box<dyn Storage>) {
+        let storage: Arc<dyn Storage> = Arc::from(storage);
+        *self.snapshot_store.lock().unwrap() =
+            Some(Arc::new(StorageSnapshotStore(storage.clone())));
+        *self.storage.lock().unwrap() = storage;
+    }
+
+    fn storage(&self) -> Arc<dyn Storage> {
+        self.storage.lock().unwrap().clone()
+    }
+
+    /// Opts in to decoding the message TLV of incoming keysends. Messages
+    /// are stored on the node, used as the description of the keysend's
+    /// invoice in `list_invoices`, and emitted as `KeysendReceived` events.
+    pub async fn set_keysend_descriptions(&self, enabled: bool) {
+        let task = enabled.then(|| {
+            tokio::spawn(keysend_messages::record(
+                self.gl_node.clone(),
+                self.node.clone(),
+                self.events.clone(),
+            ))
+        });
+
+        if let Some(previous) = std::mem::replace(&mut *self.keysend_messages.lock().unwrap(), task)
+        {
+            previous.abort();
+        }
+    }
+
+    /// Packages the credentials and everything this crate stored on the node
+    /// into a blob encrypted with a key derived from the mnemonic.
+    pub async fn export_state(&self) -> Result<String> {
+        let state = WalletState {
+            credentials: self.credentials.gl_creds.clone(),
+            datastore: self.crate_datastore().await?,
+        };
+        state_export::seal(&self.export_key, &state).map_err(SdkError::storage)
+    }
+
+    /// Writes datastore entries from an exported state that are missing on
+    /// this node. Existing entries are left untouched.
+    pub async fn restore_state(&self, state: String) -> Result<()> {
+        let state = state_export::open(&self.export_key, &state).map_err(SdkError::invalid_arg)?;
+
+        for entry in state.datastore {
+            let hex = entry
+                .hex
+                .map(hex::decode)
+                .transpose()
+                .context("datastore entry contains invalid hex value")
+                .map_err(SdkError::invalid_arg)?;
+
+            if let Err(e) = self
+                .node
+                .clone()
+                .datastore(traced_request(cln::DatastoreRequest {
+                    key: entry.key,
+                    string: entry.string,
+                    hex,
+                    mode: Some(cln::datastore_request::DatastoreMode::MustCreate as i32),
+                    ..Default::default()
+                }))
+                .await
+            {
+                eprintln!("Skipping datastore entry: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn crate_datastore(&self) -> Result<Vec<DatastoreEntry>> {
+        let mut entries = Vec::new();
+        let mut prefixes = vec![vec![String::from("glalby")]];
+
+        // listdatastore only returns direct children, walk the tree.
+        while let Some(prefix) = prefixes.pop() {
+            let datastore = self
+                .node
+                .clone()
+                .list_datastore(traced_request(cln::ListdatastoreRequest {
+                    key: prefix.clone(),
+                }))
+                .await
+                .context("failed to list datastore")
+                .map_err(SdkError::greenlight_api)?
+                .into_inner()
+                .datastore;
+
+            for entry in datastore {
+                if entry.string.is_none() && entry.hex.is_none() {
+                    if entry.key.len() > prefix.len() {
+                        prefixes.push(entry.key);
+                    }
+                } else {
+                    entries.push(DatastoreEntry {
+                        key: entry.key,
+                        string: entry.string,
+                        hex: entry.hex.map(hex::encode),
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Starts recording mutating operations to an append-only journal at
+    /// `path`, so their outcome can be reconciled after a crash.
+    pub async fn enable_journal(&self, path: String) -> Result<()> {
+        let journal = Journal::open(PathBuf::from(path)).map_err(SdkError::storage)?;
+        *self.journal.lock().unwrap() = Some(Arc::new(journal));
+        Ok(())
+    }
+
+    /// Like `enable_journal`, but keeps the journal in the storage set with
+    /// `set_storage`.
+    pub async fn enable_journal_in_storage(&self) {
+        let journal = Journal::in_storage(self.storage());
+        *self.journal.lock().unwrap() = Some(Arc::new(journal));
+    }
+
+    /// Opens the payment outbox at `path` and starts sending what is queued
+    /// in it whenever the node is reachable. Outcomes are reported as
+    /// `QueuedPaymentSucceeded` and `QueuedPaymentFailed` events.
+    pub async fn enable_payment_queue(&self, path: String) -> Result<()> {
+        let queue = PaymentQueue::open(PathBuf::from(path)).map_err(SdkError::storage)?;
+        self.start_payment_queue(queue);
+        Ok(())
+    }
+
+    /// Like `enable_payment_queue`, but keeps the outbox in the storage set
+    /// with `set_storage`.
+    pub async fn enable_payment_queue_in_storage(&self) -> Result<()> {
+        let queue = PaymentQueue::in_storage(self.storage()).map_err(SdkError::storage)?;
+        self.start_payment_queue(queue);
+        Ok(())
+    }
+
+    fn start_payment_queue(&self, queue: PaymentQueue) {
+        let queue = Arc::new(queue);
+        let task = tokio::spawn(payment_queue::work(
+            queue.clone(),
+            self.node.clone(),
+            self.locked.clone(),
+            self.events.clone(),
+        ));
+
+        if let Some((_, previous)) = self.payment_queue.lock().unwrap().replace((queue, task)) {
+            previous.abort();
+        }
+    }
+
+    /// Queues a payment to be sent as soon as the node is reachable and
+    /// returns its id in the outbox. Only `bolt11` and `description` are
+    /// kept in the outbox; queued payments use the node's defaults for fees
+    /// and timeouts.
+    pub async fn enqueue_payment(&self, req: PayRequest) -> Result<u64> {
+        self.payment_queue()?
+            .enqueue(req.bolt11, req.description)
+            .map_err(SdkError::storage)
+    }
+
+    pub async fn list_queued_payments(&self) -> Result<Vec<QueuedPayment>> {
+        Ok(self.payment_queue()?.payments())
+    }
+
+    fn payment_queue(&self) -> Result<Arc<PaymentQueue>> {
+        self.payment_queue
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(queue, _)| queue.clone())
+            .ok_or_else(|| SdkError::InvalidArgument {
+                message: String::from("payment queue is not enabled"),
+                request_id: None,
+            })
+    }
+
+    /// Reconciles journal entries that never recorded an outcome against the
+    /// node state. Everything but in-flight operations is marked as finished.
+    pub async fn recover_pending_operations(&self) -> Result<Vec<PendingOperation>> {
+        let journal = self.journal().ok_or_else(|| SdkError::InvalidArgument {
+            message: String::from("journal is not enabled"),
+            request_id: None,
+        })?;
+
+        let mut pending = journal.unfinished().map_err(SdkError::storage)?;
+        for operation in pending.iter_mut() {
+            operation.resolution = self.reconcile(operation).await?;
+            if operation.resolution != OperationResolution::InFlight {
+                journal
+                    .finish(
+                        operation.id,
+                        operation.resolution,
+                        Some(String::from("recovered")),
+                    )
+                    .map_err(SdkError::storage)?;
+            }
+        }
+
+        Ok(pending)
+    }
+
+    async fn reconcile(&self, operation: &PendingOperation) -> Result<OperationResolution> {
+        match &operation.operation {
+            JournalOperation::Pay { bolt11 } => {
+                let payments = self
+                    .list_payments(ListPaymentsRequest {
+                        bolt11: Some(bolt11.clone()),
+                        payment_hash: None,
+                        status: None,
+                        include_sensitive: false,
+                    })
+                    .await?
+                    .payments;
+                Ok(resolution_of(&payments))
+            }
+            JournalOperation::KeySend { destination, .. } => {
+                let payments: Vec<_> = self
+                    .list_payments(ListPaymentsRequest {
+                        bolt11: None,
+                        payment_hash: None,
+                        status: None,
+                        include_sensitive: false,
+                    })
+                    .await?
+                    .payments
+                    .into_iter()
+                    .filter(|p| {
+                        p.bolt11.is_none()
+                            && p.destination.as_ref() == Some(destination)
+                            && p.created_at >= operation.started_at
+                    })
+                    .collect();
+                Ok(resolution_of(&payments))
+            }
+            JournalOperation::FundChannel { id, .. } => {
+                let channels = self.all_peer_channels().await?;
+                if channels.iter().any(|c| c.peer_id.as_ref() == Some(id)) {
+                    // An older channel with the same peer is indistinguishable
+                    // from one opened by the interrupted call.
+                    Ok(OperationResolution::Unknown)
+                } else {
+                    Ok(OperationResolution::Failed)
+                }
+            }
+            JournalOperation::MultiFundChannel { ids } => {
+                let channels = self.all_peer_channels().await?;
+                if channels
+                    .iter()
+                    .any(|c| c.peer_id.as_ref().is_some_and(|peer| ids.contains(peer)))
+                {
+                    Ok(OperationResolution::Unknown)
+                } else {
+                    Ok(OperationResolution::Failed)
+                }
+            }
+            JournalOperation::Withdraw { .. } => Ok(OperationResolution::Unknown),
+        }
+    }
+
+    fn journal(&self) -> Option<Arc<Journal>> {
+        self.journal.lock().unwrap().clone()
+    }
+
+    fn journal_start(&self, operation: JournalOperation) -> Result<Option<u64>> {
+        self.journal()
+            .map(|journal| journal.start(operation))
+            .transpose()
+            .map_err(SdkError::storage)
+    }
+
+    fn journal_finish<T>(&self, id: Option<u64>, result: &Result<T>) {
+        let (Some(journal), Some(id)) = (self.journal(), id) else {
+            return;
+        };
+
+        let (resolution, detail) = match result {
+            // Left unfinished, for recover_pending_operations to resolve.
+            Err(SdkError::PaymentPending { .. }) => return,
+            Ok(_) => (OperationResolution::Succeeded, None),
+            Err(e) => (OperationResolution::Failed, Some(e.to_string())),
+        };
+        if let Err(e) = journal.finish(id, resolution, detail) {
+            eprintln!("Failed to write journal record: {:?}", e);
+        }
+    }
+}
+
+enum PaymentOutcome {
+    Complete(PayResponse),
+    Pending,
+    Failed,
+    /// The node has no record of the payment.
+    Unknown,
+}
+
+fn resolution_of(payments: &[ListPaymentsPayment]) -> OperationResolution {
+    use cln::listpays_pays::ListpaysPaysStatus;
+
+    if payments
+        .iter()
+        .any(|p| p.status == ListpaysPaysStatus::Complete as i32)
+    {
+        OperationResolution::Succeeded
+    } else if payments
+        .iter()
+        .any(|p| p.status == ListpaysPaysStatus::Pending as i32)
+    {
+        OperationResolution::InFlight
+    } else {
+        OperationResolution::Failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: tonic::Code, message: &str) -> anyhow::Error {
+        anyhow::Error::new(tonic::Status::new(code, message)).context("failed to schedule node")
+    }
+
+    #[test]
+    fn scheduler_rate_limit_reads_retry_after_metadata() {
+        let mut status = tonic::Status::new(tonic::Code::ResourceExhausted, "slow down");
+        status
+            .metadata_mut()
+            .insert("retry-after", "30".parse().unwrap());
+        let e = anyhow::Error::new(status).context("failed to schedule node");
+
+        match SdkError::scheduler(e) {
+            SdkError::RateLimited {
+                retry_after_secs, ..
+            } => assert_eq!(retry_after_secs, Some(30)),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn scheduler_rate_limit_without_metadata_has_no_delay() {
+        match SdkError::scheduler(status(tonic::Code::ResourceExhausted, "retry-after: 30")) {
+            SdkError::RateLimited {
+                retry_after_secs, ..
+            } => assert_eq!(retry_after_secs, None),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn scheduler_maps_status_codes() {
+        assert!(matches!(
+            SdkError::scheduler(status(tonic::Code::NotFound, "no such node")),
+            SdkError::NodeNotFound { .. }
+        ));
+        assert!(matches!(
+            SdkError::scheduler(status(tonic::Code::Unauthenticated, "bad certificate")),
+            SdkError::CertificateExpired { .. }
+        ));
+        assert!(matches!(
+            SdkError::scheduler(status(tonic::Code::Unavailable, "down for maintenance")),
+            SdkError::Maintenance { .. }
+        ));
+        assert!(matches!(
+            SdkError::scheduler(status(
+                tonic::Code::Internal,
+                "maintenance invite not found"
+            )),
+            SdkError::GreenlightApi { .. }
+        ));
+    }
+
+    #[test]
+    fn scheduler_without_status_is_greenlight_api() {
+        assert!(matches!(
+            SdkError::scheduler(anyhow::anyhow!("Certificate expired, invite needed")),
+            SdkError::GreenlightApi { .. }
+        ));
+    }
+
+    #[test]
+    fn registration_rejection_is_invalid_invite() {
+        assert!(matches!(
+            SdkError::registration(status(tonic::Code::InvalidArgument, "bad code")),
+            SdkError::InviteInvalid { .. }
+        ));
+        assert!(matches!(
+            SdkError::registration(status(tonic::Code::PermissionDenied, "used code")),
+            SdkError::InviteInvalid { .. }
+        ));
+        assert!(matches!(
+            SdkError::registration(status(tonic::Code::ResourceExhausted, "slow down")),
+            SdkError::RateLimited { .. }
+        ));
+    }
+
+    fn rpc_error(message: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Error calling method Withdraw: RpcError {{ code: Some(301), message: \"{}\", data: None }}",
+            message
+        )
+        .context("failed to withdraw")
+    }
+
+    #[test]
+    fn funds_reads_shortfall_from_utxo_message() {
+        let e = rpc_error("Could not afford 100000sat using all 1 available UTXOs: 99830sat short");
+        match SdkError::funds(e, None) {
+            SdkError::InsufficientFunds {
+                amount_msat,
+                available_msat,
+                ..
+            } => {
+                assert_eq!(amount_msat, Some(100_000_000));
+                assert_eq!(available_msat, Some(170_000));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn funds_reads_total_from_feerate_message() {
+        let e = rpc_error(
+            "Could not afford 100000sat using UTXOs totalling 170sat with weight 272 at feerate 253",
+        );
+        match SdkError::funds(e, Some(1)) {
+            SdkError::InsufficientFunds {
+                amount_msat,
+                available_msat,
+                ..
+            } => {
+                assert_eq!(amount_msat, Some(100_000_000));
+                assert_eq!(available_msat, Some(170_000));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn funds_reads_fee_budget() {
+        let e = rpc_error("Fee exceeds our fee budget: 1200msat > 1000msat, discarding route");
+        match SdkError::funds(e, Some(50_000)) {
+            SdkError::FeeBudgetExceeded {
+                fee_msat,
+                max_fee_msat,
+                ..
+            } => {
+                assert_eq!(fee_msat, Some(1200));
+                assert_eq!(max_fee_msat, Some(1000));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn funds_reads_htlc_minimum() {
+        let e = rpc_error("HTLC too small (1000msat minimum)");
+        match SdkError::funds(e, Some(500)) {
+            SdkError::AmountBelowMinimumHtlc {
+                amount_msat,
+                min_htlc_msat,
+                ..
+            } => {
+                assert_eq!(amount_msat, Some(500));
+                assert_eq!(min_htlc_msat, Some(1000));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn funds_reads_channel_maximum() {
+        let e = rpc_error("Amount exceeded 16777215sat");
+        match SdkError::funds(e, Some(20_000_000_000)) {
+            SdkError::ExceedsChannelCapacity {
+                amount_msat,
+                max_msat,
+                ..
+            } => {
+                assert_eq!(amount_msat, Some(20_000_000_000));
+                assert_eq!(max_msat, Some(16_777_215_000));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let e = rpc_error("Capacity exceeded - HTLC fee: 1000sat");
+        assert!(matches!(
+            SdkError::funds(e, Some(1)),
+            SdkError::ExceedsChannelCapacity { max_msat: None, .. }
+        ));
+    }
+
+    #[test]
+    fn funds_leaves_other_failures_alone() {
+        let e = rpc_error("Ran out of routes to try after 3 attempts: see `paystatus`");
+        assert!(matches!(
+            SdkError::funds(e, Some(1)),
+            SdkError::GreenlightApi { .. }
+        ));
+    }
+
+    #[test]
+    fn request_id_is_set_without_touching_the_message() {
+        let e = rpc_error("Could not afford 100000sat using all 1 available UTXOs: 99830sat short");
+        match SdkError::funds(e, None).with_request_id("abc") {
+            SdkError::InsufficientFunds {
+                message,
+                request_id,
+                ..
+            } => {
+                assert!(!message.contains("abc"));
+                assert_eq!(request_id.as_deref(), Some("abc"));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn request_id_is_set_on_variants_without_a_message() {
+        match (SdkError::WalletLocked { request_id: None }).with_request_id("abc") {
+            SdkError::WalletLocked { request_id } => assert_eq!(request_id.as_deref(), Some("abc")),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn traced_request_carries_the_request_id() {
+        let request = REQUEST_ID
+            .scope("abc".to_string(), async { traced_request(()) })
+            .await;
+        assert_eq!(
+            request
+                .metadata()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok()),
+            Some("abc")
+        );
+        assert!(traced_request(())
+            .metadata()
+            .get(REQUEST_ID_HEADER)
+            .is_none());
+    }
+
+    fn activity(id: &str, source: ActivitySource, timestamp: Option<u64>) -> ActivityEntry {
+        ActivityEntry {
+            id: id.to_string(),
+            source,
+            incoming: true,
+            amount_msat: Some(1000),
+            timestamp,
+            payment_hash: None,
+            txid: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn activity_keeps_one_entry_per_id_from_the_most_specific_source() {
+        let response = ListActivityResponse::new(
+            vec![
+                activity("ln-in-aa", ActivitySource::Invoice, Some(10)),
+                activity("ln-in-aa", ActivitySource::Keysend, Some(10)),
+                activity("ln-in-aa", ActivitySource::Invoice, Some(10)),
+                activity("ln-out-bb", ActivitySource::Payment, Some(20)),
+                activity("ln-out-bb", ActivitySource::Payment, Some(20)),
+            ]
+            .into_iter(),
+            None,
+        );
+
+        let entries: Vec<_> = response
+            .entries
+            .iter()
+            .map(|e| (e.id.as_str(), e.source))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("ln-out-bb", ActivitySource::Payment),
+                ("ln-in-aa", ActivitySource::Keysend),
+            ]
+        );
+    }
+
+    #[test]
+    fn activity_limit_keeps_the_newest_on_chain_entries_too() {
+        let response = ListActivityResponse::new(
+            vec![
+                activity("ln-in-aa", ActivitySource::Invoice, Some(10)),
+                activity("chain-cc", ActivitySource::Onchain, Some(30)),
+                activity("ln-out-bb", ActivitySource::Payment, Some(20)),
+            ]
+            .into_iter(),
+            Some(2),
+        );
+
+        let ids: Vec<_> = response.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["chain-cc", "ln-out-bb"]);
+    }
+
+    fn transaction(kind: TransactionKind, outputs: &[(u64, bool)]) -> Transaction {
+        Transaction {
+            txid: "ab".repeat(32),
+            rawtx: String::new(),
+            blockheight: Some(100),
+            txindex: 0,
+            locktime: 0,
+            version: 2,
+            inputs: vec![],
+            outputs: outputs
+                .iter()
+                .enumerate()
+                .map(|(index, &(amount_msat, ours))| TransactionOutput {
+                    index: index as u32,
+                    amount_msat: Some(amount_msat),
+                    scriptpubkey: String::new(),
+                    ours,
+                })
+                .collect(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn withdrawal_activity_leaves_out_change() {
+        let tx = transaction(TransactionKind::Withdrawal, &[(5000, false), (3000, true)]);
+        let entry = ActivityEntry::from_transaction(tx, 1).unwrap();
+        assert!(!entry.incoming);
+        assert_eq!(entry.amount_msat, Some(5000));
+
+        let tx = transaction(TransactionKind::Deposit, &[(5000, false), (3000, true)]);
+        let entry = ActivityEntry::from_transaction(tx, 1).unwrap();
+        assert!(entry.incoming);
+        assert_eq!(entry.amount_msat, Some(3000));
+    }
+
+    #[test]
+    fn channel_transactions_are_not_activity() {
+        for kind in [
+            TransactionKind::ChannelOpen,
+            TransactionKind::ChannelClose,
+            TransactionKind::Unknown,
+        ] {
+            assert!(ActivityEntry::from_transaction(transaction(kind, &[(1, true)]), 1).is_none());
+        }
+    }
+
+    #[test]
+    fn block_time_is_estimated_from_depth() {
+        assert_eq!(estimated_block_time(Some(100), 106, 10_000), 6_400);
+        assert_eq!(estimated_block_time(None, 106, 10_000), 10_000);
+        assert_eq!(estimated_block_time(Some(107), 106, 10_000), 10_000);
+    }
+
+    #[test]
+    fn invoice_limits_cap_unset_expiry_only_beyond_the_default() {
+        let limits = InvoiceLimits {
+            min_amount_msat: Some(1000),
+            max_amount_msat: None,
+            max_expiry_secs: Some(3600),
+        };
+
+        let mut expiry = None;
+        limits.apply(None, &mut expiry, None).unwrap();
+        assert_eq!(expiry, Some(3600));
+
+        let mut expiry = None;
+        limits.apply(Some(1000), &mut expiry, Some(600)).unwrap();
+        assert_eq!(expiry, None);
+
+        assert!(matches!(
+            limits.apply(Some(999), &mut None, None),
+            Err(SdkError::InvoiceAmountBelowMinimum { .. })
+        ));
+        assert!(matches!(
+            limits.apply(None, &mut Some(3601), None),
+            Err(SdkError::InvoiceExpiryTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn lease_fee_includes_the_funding_weight() {
+        let will_fund = cln::ListnodesNodesOptionWillFund {
+            lease_fee_base_msat: Some(cln::Amount { msat: 2_000_000 }),
+            lease_fee_basis: 100,
+            funding_weight: 666,
+            ..Default::default()
+        };
+
+        // 2000 sat base, 1% of 1_000_000 sat and 666 * 2500 / 1000 sat.
+        assert_eq!(
+            lease_fee_msat(&will_fund, 1_000_000_000, 2500),
+            2_000_000 + 10_000_000 + 1_665_000
+        );
+    }
+
+    #[test]
+    fn erring_channel_is_read_from_the_status_details() {
+        let error = br#"{"code":210,"message":"Ran out of routes to try","data":{"erring_index":1,"erring_channel":"103x1x0","erring_direction":1}}"#;
+        let status = tonic::Status::with_details(
+            tonic::Code::Unknown,
+            "Error calling method Pay",
+            error.to_vec().into(),
+        );
+
+        assert_eq!(
+            erring_channel(&anyhow::Error::new(status).context("failed to pay invoice")),
+            Some(String::from("103x1x0/1"))
+        );
+    }
+
+    #[test]
+    fn erring_channel_is_read_from_a_json_message() {
+        let status = tonic::Status::new(
+            tonic::Code::Unknown,
+            r#"Error calling method Pay: {"code":204,"message":"failed","data":{"erring_channel":"7x2x1","erring_direction":0}} (attempt 2)"#,
+        );
+
+        assert_eq!(
+            erring_channel(&anyhow::Error::new(status)),
+            Some(String::from("7x2x1/0"))
+        );
+    }
+
+    #[test]
+    fn erring_channel_needs_a_failing_hop() {
+        let without_data = tonic::Status::new(
+            tonic::Code::Unknown,
+            r#"Error calling method Pay: {"code":207,"message":"Invoice expired"}"#,
+        );
+        assert_eq!(erring_channel(&anyhow::Error::new(without_data)), None);
+
+        let not_json = tonic::Status::new(tonic::Code::Unknown, "Error calling method Pay");
+        assert_eq!(erring_channel(&anyhow::Error::new(not_json)), None);
+
+        assert_eq!(erring_channel(&anyhow::anyhow!("connection reset")), None);
+    }
+
+    fn cltv_info(blockheight: u32, syncing: bool) -> cln::GetinfoResponse {
+        cln::GetinfoResponse {
+            blockheight,
+            warning_bitcoind_sync: syncing.then(|| String::from("Bitcoind is not up-to-date")),
+            ..Default::default()
+        }
+    }
+
+    fn channel_with_htlc(expiry: u32) -> cln::ListpeerchannelsChannels {
+        cln::ListpeerchannelsChannels {
+            htlcs: vec![cln::ListpeerchannelsChannelsHtlcs {
+                expiry: Some(expiry),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cltv_recommendation_follows_peer_deltas() {
+        let info = cltv_info(800_000, false);
+
+        // MIN_FINAL_CLTV + BLOCK_MARGIN when peers ask for less.
+        assert_eq!(CltvRecommendation::new(&info, &[], &[]).cltv, 24);
+        assert_eq!(CltvRecommendation::new(&info, &[], &[6, 12]).cltv, 24);
+        // The largest peer delta + BLOCK_MARGIN.
+        assert_eq!(CltvRecommendation::new(&info, &[], &[34, 40]).cltv, 46);
+        // Outliers are capped at MAX_PEER_DELTA.
+        assert_eq!(CltvRecommendation::new(&info, &[], &[40, 5000]).cltv, 150);
+    }
+
+    #[test]
+    fn cltv_recommendation_adds_margins_for_sync_and_pending_htlcs() {
+        let block_height = 800_000;
+
+        let syncing = CltvRecommendation::new(&cltv_info(block_height, true), &[], &[34]);
+        assert_eq!(syncing.block_height, block_height);
+        assert_eq!(syncing.cltv, 46);
+
+        let info = cltv_info(block_height, false);
+        // An HTLC expiring well after the delta doesn't matter.
+        let relaxed = [channel_with_htlc(block_height + 100)];
+        assert_eq!(CltvRecommendation::new(&info, &relaxed, &[34]).cltv, 40);
+        // One expiring 10 blocks inside the delta adds those 10 blocks.
+        let tight = [
+            channel_with_htlc(block_height + 100),
+            channel_with_htlc(block_height + 30),
+        ];
+        assert_eq!(CltvRecommendation::new(&info, &tight, &[34]).cltv, 50);
+        // The shortfall counts for at most MIN_FINAL_CLTV.
+        let expiring = [channel_with_htlc(block_height)];
+        assert_eq!(CltvRecommendation::new(&info, &expiring, &[34]).cltv, 58);
+    }
+
+    fn stats_channel(id: u8, state: cln::ChannelState) -> ListPeerChannelsChannel {
+        cln::ListpeerchannelsChannels {
+            peer_id: Some(vec![id; 33]),
+            peer_connected: Some(true),
+            state: Some(state as i32),
+            channel_id: Some(vec![id; 32]),
+            total_msat: Some(cln::Amount { msat: 1000 }),
+            to_us_msat: Some(cln::Amount { msat: 400 }),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    fn closed_channel(id: u8) -> cln::ListclosedchannelsClosedchannels {
+        cln::ListclosedchannelsClosedchannels {
+            channel_id: vec![id; 32],
+            total_msat: Some(cln::Amount { msat: 1000 }),
+            final_to_us_msat: Some(cln::Amount { msat: 300 }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn channel_stats_counts_closed_states_once() {
+        use cln::ChannelState;
+
+        let stats = ChannelStats::new(
+            vec![
+                stats_channel(1, ChannelState::ChanneldNormal),
+                stats_channel(2, ChannelState::ChanneldAwaitingLockin),
+                stats_channel(3, ChannelState::ClosingdSigexchange),
+                stats_channel(4, ChannelState::ClosingdComplete),
+                stats_channel(5, ChannelState::Onchain),
+            ],
+            // 4 is still in listpeerchannels, 6 was forgotten.
+            vec![closed_channel(4), closed_channel(6)],
+        );
+
+        assert_eq!(stats.active.count, 1);
+        assert_eq!(stats.pending_open.count, 1);
+        assert_eq!(stats.pending_close.count, 1);
+        assert_eq!(stats.closed.count, 3);
+        assert_eq!(stats.closed.total_msat, 3000);
+        assert_eq!(stats.closed.to_us_msat, 400 + 400 + 300);
+        assert_eq!(stats.peers, 5);
+    }
+
+    fn counted_invoice(
+        created_index: u64,
+        updated_index: Option<u64>,
+        status: cln::listinvoices_invoices::ListinvoicesInvoicesStatus,
+    ) -> cln::ListinvoicesInvoices {
+        cln::ListinvoicesInvoices {
+            created_index: Some(created_index),
+            updated_index,
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn invoice_counts_follow_updates_of_unpaid_invoices() {
+        use cln::listinvoices_invoices::ListinvoicesInvoicesStatus::{Expired, Paid, Unpaid};
+
+        let mut counts = InvoiceCounts::default();
+        counts.add(&counted_invoice(1, None, Unpaid));
+        counts.add(&counted_invoice(2, None, Unpaid));
+        counts.add(&counted_invoice(3, Some(1), Paid));
+        assert_eq!(counts.created, 3);
+        assert_eq!(counts.count(None), 3);
+        assert_eq!(counts.count(Some(Unpaid as i32)), 2);
+
+        // 3 was already counted as paid when it was listed.
+        counts.update(&counted_invoice(3, Some(1), Paid));
+        counts.update(&counted_invoice(1, Some(2), Paid));
+        counts.update(&counted_invoice(2, Some(3), Expired));
+        assert_eq!(counts.updated, 3);
+        assert_eq!(counts.count(Some(Unpaid as i32)), 0);
+        assert_eq!(counts.count(Some(Paid as i32)), 2);
+        assert_eq!(counts.count(Some(Expired as i32)), 1);
+        assert_eq!(counts.count(None), 3);
+    }
+    #[test]
+    fn wait_timeouts_are_detected_by_code() {
+        let timed_out = tonic::Status::new(
+            tonic::Code::Unknown,
+            r#"Error calling method WaitAnyInvoice: {"code":904,"message":"Timed out"}"#,
+        );
+        assert!(is_wait_timeout(
+            &anyhow::Error::new(timed_out).context("failed to wait for invoices")
+        ));
+
+        let deadline = tonic::Status::new(tonic::Code::DeadlineExceeded, "deadline exceeded");
+        assert!(is_wait_timeout(&anyhow::Error::new(deadline)));
+
+        let other = tonic::Status::new(
+            tonic::Code::Unknown,
+            r#"Error calling method WaitAnyInvoice: {"code":-32602,"message":"Timed out is not a number"}"#,
+        );
+        assert!(!is_wait_timeout(&anyhow::Error::new(other)));
+    }
+
+    #[test]
+    fn close_reasons_from_cln_status_lines() {
+        use cln::ChannelState;
+
+        let reason = |state: ChannelState, status: &[&str]| {
+            let status: Vec<String> = status.iter().map(|s| s.to_string()).collect();
+            ChannelCloseReason::from_status(Some(state as i32), &status)
+        };
+
+        assert_eq!(
+            reason(
+                ChannelState::ClosingdComplete,
+                &["CLOSINGD_SIGEXCHANGE:We agreed on a closing fee of 183 satoshi for tx:6b1b..."]
+            ),
+            ChannelCloseReason::Mutual
+        );
+        assert_eq!(
+            reason(
+                ChannelState::Onchain,
+                &[
+                    "CLOSINGD_SIGEXCHANGE:We agreed on a closing fee of 183 satoshi for tx:6b1b...",
+                    "ONCHAIN:Tracking mutual close transaction",
+                    "ONCHAIN:All outputs resolved: waiting 90 more blocks before forgetting channel",
+                ]
+            ),
+            ChannelCloseReason::Mutual
+        );
+        assert_eq!(
+            reason(
+                ChannelState::AwaitingUnilateral,
+                &["CHANNELD_NORMAL:Reconnected, and reestablished."]
+            ),
+            ChannelCloseReason::LocalForce
+        );
+        assert_eq!(
+            reason(
+                ChannelState::Onchain,
+                &[
+                    "ONCHAIN:Tracking our own unilateral close",
+                    "ONCHAIN:2 outputs unresolved: in 4 blocks will spend DELAYED_OUTPUT_TO_US (9e2b...:0) using OUR_DELAYED_RETURN_TO_WALLET",
+                ]
+            ),
+            ChannelCloseReason::LocalForce
+        );
+        assert_eq!(
+            reason(
+                ChannelState::FundingSpendSeen,
+                &["ONCHAIN:Tracking their unilateral close"]
+            ),
+            ChannelCloseReason::RemoteForce
+        );
+        assert_eq!(
+            reason(
+                ChannelState::Onchain,
+                &["ONCHAIN:Tracking their illegal close: taking all funds"]
+            ),
+            ChannelCloseReason::OnchainBreach
+        );
+        assert_eq!(
+            reason(ChannelState::FundingSpendSeen, &[]),
+            ChannelCloseReason::Unknown
+        );
+    }
+
+    #[test]
+    fn pays_in_hash_order_follows_the_requested_hashes() {
+        let pay = |hash: u8, created_at: u64| cln::ListpaysPays {
+            payment_hash: vec![hash; 32],
+            created_at,
+            ..Default::default()
+        };
+        let pays = vec![pay(1, 10), pay(2, 20), pay(3, 30), pay(2, 40)];
+        let hashes = vec![vec![3; 32], vec![9; 32], vec![2; 32], vec![1; 32]];
+
+        let ordered: Vec<_> = pays_in_hash_order(pays, &hashes)
+            .into_iter()
+            .map(|p| (p.payment_hash[0], p.created_at))
+            .collect();
+        assert_eq!(ordered, vec![(3, 30), (2, 20), (2, 40), (1, 10)]);
+    }
+
+    fn fit_description(description: String) -> (String, Option<String>) {
+        let mut req = MakeInvoiceRequest {
+            amount_msat: 1_000,
+            description,
+            label: String::from("label"),
+            expiry: None,
+            fallbacks: None,
+            preimage: None,
+            cltv: None,
+            deschashonly: None,
+            truncate_description: true,
+        };
+        let warning = req.fit_description();
+        assert!(req.description.len() <= MAX_BOLT11_DESCRIPTION_BYTES);
+        (req.description, warning)
+    }
+
+    #[test]
+    fn fit_description_cuts_before_a_character_across_the_limit() {
+        let (description, warning) = fit_description("a".repeat(634) + "\u{1f600}tail");
+
+        assert_eq!(description, "a".repeat(634) + ELLIPSIS);
+        assert_eq!(
+            warning.as_deref(),
+            Some("description truncated from 642 to 637 bytes to fit the invoice")
+        );
+    }
+
+    #[test]
+    fn fit_description_keeps_emoji_sequences_whole() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let (description, _) = fit_description("a".repeat(630) + family + "tail");
+        assert_eq!(description, "a".repeat(630) + ELLIPSIS);
+
+        let thumbs_up = "\u{1f44d}\u{1f3fd}";
+        let (description, _) = fit_description("a".repeat(630) + thumbs_up + "tail");
+        assert_eq!(description, "a".repeat(630) + ELLIPSIS);
+
+        let (description, _) = fit_description("a".repeat(628) + thumbs_up + "tail");
+        assert_eq!(description, "a".repeat(628) + thumbs_up + ELLIPSIS);
+    }
+
+    #[test]
+    fn fit_description_leaves_fitting_descriptions_alone() {
+        let (description, warning) = fit_description("a".repeat(MAX_BOLT11_DESCRIPTION_BYTES));
+        assert_eq!(description, "a".repeat(MAX_BOLT11_DESCRIPTION_BYTES));
+        assert_eq!(warning, None);
+
+        let (description, warning) = fit_description("a".repeat(MAX_BOLT11_DESCRIPTION_BYTES + 1));
+        assert!(description.ends_with(ELLIPSIS));
+        assert_eq!(description.len(), MAX_BOLT11_DESCRIPTION_BYTES);
+        assert!(warning.is_some());
+    }
 }
@@ -0,0 +1,312 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher, EventListener};
+use crate::greenlight_alby_client::{is_wait_timeout, ListInvoicesInvoice};
+
+const PAGE_SIZE: u32 = 100;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct PaidInvoice {
+    pub label: String,
+    pub payment_hash: String,
+    pub amount_received_msat: Option<u64>,
+    pub paid_at: Option<u64>,
+    /// Persist this and pass it back to `subscribe_invoices` to resume.
+    pub pay_index: u64,
+}
+
+/// Host-implemented receiver for `subscribe_invoices`. Called from the
+/// client's runtime, so implementations should return quickly.
+pub trait InvoiceListener: Send + Sync {
+    fn on_invoice_paid(&self, invoice: PaidInvoice);
+}
+
+impl PaidInvoice {
+    fn new(invoice: ListInvoicesInvoice) -> Option<Self> {
+        Some(PaidInvoice {
+            pay_index: invoice.pay_index?,
+            label: invoice.label,
+            payment_hash: invoice.payment_hash,
+            amount_received_msat: invoice.amount_received_msat,
+            paid_at: invoice.paid_at,
+        })
+    }
+}
+
+/// An `InvoiceListener` fed from `InvoicePaid` events. Each invoice is
+/// handed over once and in pay order; events that arrive while invoices paid
+/// earlier are still being caught up on are held back until then.
+pub(crate) struct Subscription {
+    listener: Box<dyn InvoiceListener>,
+    state: Mutex<SubscriptionState>,
+}
+
+struct SubscriptionState {
+    last_pay_index: u64,
+    /// `None` once caught up.
+    held: Option<Vec<PaidInvoice>>,
+}
+
+impl Subscription {
+    pub fn new(listener: Box<dyn InvoiceListener>, last_pay_index: u64) -> Arc<Self> {
+        Arc::new(Subscription {
+            listener,
+            state: Mutex::new(SubscriptionState {
+                last_pay_index,
+                held: Some(Vec::new()),
+            }),
+        })
+    }
+
+    fn last_pay_index(&self) -> u64 {
+        self.state.lock().unwrap().last_pay_index
+    }
+
+    fn caught_up(&self, mut invoices: Vec<PaidInvoice>) {
+        let mut state = self.state.lock().unwrap();
+        invoices.extend(state.held.take().unwrap_or_default());
+        invoices.sort_by_key(|invoice| invoice.pay_index);
+        for invoice in invoices {
+            self.deliver(&mut state, invoice);
+        }
+    }
+
+    // Called with the state locked, so deliveries never interleave.
+    fn deliver(&self, state: &mut SubscriptionState, invoice: PaidInvoice) {
+        if invoice.pay_index > state.last_pay_index {
+            state.last_pay_index = invoice.pay_index;
+            self.listener.on_invoice_paid(invoice);
+        }
+    }
+}
+
+impl EventListener for Arc<Subscription> {
+    fn on_event(&self, event: ClientEvent) {
+        let ClientEvent::InvoicePaid { invoice, .. } = event else {
+            return;
+        };
+        let Some(invoice) = PaidInvoice::new(invoice) else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        match state.held.as_mut() {
+            Some(held) => held.push(invoice),
+            None => self.deliver(&mut state, invoice),
+        }
+    }
+}
+
+/// Hands `subscription` the invoices paid after its last pay index, then
+/// lets it take over from the `InvoicePaid` events it has held back.
+pub(crate) async fn catch_up(node: gl_client::node::ClnClient, subscription: Arc<Subscription>) {
+    loop {
+        match paid_since(node.clone(), subscription.last_pay_index()).await {
+            Ok(invoices) => return subscription.caught_up(invoices),
+            Err(e) => {
+                eprintln!("Invoice subscription failed to list invoices: {:?}", e);
+                time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn paid_since(
+    node: gl_client::node::ClnClient,
+    last_pay_index: u64,
+) -> anyhow::Result<Vec<PaidInvoice>> {
+    let Some(start) = first_paid_after(node.clone(), last_pay_index, 0).await? else {
+        return Ok(Vec::new());
+    };
+
+    let mut paid = Vec::new();
+    list_updated(node, start, |invoice| {
+        if let Some(invoice) = PaidInvoice::new(ListInvoicesInvoice::from(invoice)) {
+            if invoice.pay_index > last_pay_index {
+                paid.push(invoice);
+            }
+        }
+    })
+    .await?;
+    Ok(paid)
+}
+
+/// The `updated` index of the first invoice paid after `last_pay_index`,
+/// waiting up to `timeout_secs` for one. Payments move invoices up the
+/// updated index in pay order, so invoices paid later come after it. `None`
+/// when nothing was paid in time.
+pub(crate) async fn first_paid_after(
+    mut node: gl_client::node::ClnClient,
+    last_pay_index: u64,
+    timeout_secs: u64,
+) -> anyhow::Result<Option<u64>> {
+    let result = node
+        .wait_any_invoice(cln::WaitanyinvoiceRequest {
+            lastpay_index: Some(last_pay_index),
+            timeout: Some(timeout_secs),
+        })
+        .await
+        .map_err(anyhow::Error::new);
+
+    match result {
+        Ok(invoice) => Ok(Some(invoice.into_inner().updated_index.unwrap_or(0))),
+        Err(e) if is_wait_timeout(&e) => Ok(None),
+        Err(e) => Err(e.context("failed to wait for invoices")),
+    }
+}
+
+/// Emits an `InvoicePaid` event for every invoice settled at or after
+/// `start`, an index into the node's `updated` invoice index. Invoices paid
+/// before the task started are replayed first, then new ones are followed as
+/// they arrive. Hosts persist the index carried by each event and resume from
+/// the one after it, so nothing is missed across restarts.
+pub(crate) async fn follow(
+    node: gl_client::node::ClnClient,
+    start: u64,
+    events: Arc<EventDispatcher>,
+) {
+    let mut next = start;
+    loop {
+        match replay(node.clone(), next, &events).await {
+            Ok(after) => next = after,
+            Err(e) => {
+                eprintln!("Invoice events failed to list invoices: {:?}", e);
+                time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        }
+
+        // Blocks until an invoice reaches the `next` updated index.
+        if let Err(e) = node
+            .clone()
+            .wait(cln::WaitRequest {
+                subsystem: cln::wait_request::WaitSubsystem::Invoices as i32,
+                indexname: cln::wait_request::WaitIndexname::Updated as i32,
+                nextvalue: next,
+            })
+            .await
+        {
+            eprintln!("Invoice events failed to wait for invoices: {:?}", e);
+            time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Emits events for every paid invoice from `start` on and returns the index
+/// to continue from.
+async fn replay(
+    node: gl_client::node::ClnClient,
+    start: u64,
+    events: &EventDispatcher,
+) -> anyhow::Result<u64> {
+    list_updated(node, start, |invoice| {
+        if invoice.status == cln::listinvoices_invoices::ListinvoicesInvoicesStatus::Paid as i32 {
+            if let Some(updated_index) = invoice.updated_index {
+                events.emit(ClientEvent::InvoicePaid {
+                    invoice: ListInvoicesInvoice::from(invoice),
+                    updated_index,
+                });
+            }
+        }
+    })
+    .await
+}
+
+/// Calls `f` with every invoice from the updated index `start` on, a page at
+/// a time, and returns the index to continue from.
+async fn list_updated(
+    mut node: gl_client::node::ClnClient,
+    start: u64,
+    mut f: impl FnMut(cln::ListinvoicesInvoices),
+) -> anyhow::Result<u64> {
+    let mut next = start;
+    loop {
+        let page_start = next;
+        let invoices = node
+            .list_invoices(cln::ListinvoicesRequest {
+                index: Some(cln::listinvoices_request::ListinvoicesIndex::Updated as i32),
+                start: Some(next),
+                limit: Some(PAGE_SIZE),
+                ..Default::default()
+            })
+            .await
+            .context("failed to list invoices")?
+            .into_inner()
+            .invoices;
+
+        let count = invoices.len();
+        for invoice in invoices {
+            let Some(updated_index) = invoice.updated_index else {
+                continue;
+            };
+            next = next.max(updated_index + 1);
+            f(invoice);
+        }
+
+        if count < PAGE_SIZE as usize || next == page_start {
+            return Ok(next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Arc<Mutex<Vec<u64>>>);
+
+    impl InvoiceListener for Recorder {
+        fn on_invoice_paid(&self, invoice: PaidInvoice) {
+            self.0.lock().unwrap().push(invoice.pay_index);
+        }
+    }
+
+    fn paid(pay_index: u64) -> PaidInvoice {
+        PaidInvoice {
+            label: format!("invoice-{}", pay_index),
+            payment_hash: String::new(),
+            amount_received_msat: None,
+            paid_at: None,
+            pay_index,
+        }
+    }
+
+    fn paid_event(pay_index: u64) -> ClientEvent {
+        ClientEvent::InvoicePaid {
+            invoice: ListInvoicesInvoice::from(cln::ListinvoicesInvoices {
+                label: format!("invoice-{}", pay_index),
+                status: cln::listinvoices_invoices::ListinvoicesInvoicesStatus::Paid as i32,
+                pay_index: Some(pay_index),
+                ..Default::default()
+            }),
+            updated_index: pay_index,
+        }
+    }
+
+    #[test]
+    fn subscription_delivers_each_invoice_once_in_pay_order() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let subscription = Subscription::new(Box::new(Recorder(delivered.clone())), 2);
+
+        // Held back until the catch-up is in.
+        subscription.on_event(paid_event(5));
+        subscription.on_event(paid_event(4));
+        assert!(delivered.lock().unwrap().is_empty());
+
+        subscription.caught_up(vec![paid(3), paid(4)]);
+        assert_eq!(*delivered.lock().unwrap(), vec![3, 4, 5]);
+
+        // Replays of what was delivered are dropped.
+        subscription.on_event(paid_event(5));
+        subscription.on_event(paid_event(2));
+        subscription.on_event(paid_event(6));
+        assert_eq!(*delivered.lock().unwrap(), vec![3, 4, 5, 6]);
+        assert_eq!(subscription.last_pay_index(), 6);
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+const NAMESPACE: &str = "journal";
+
+/// A mutating operation recorded in the journal before it is sent to the node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JournalOperation {
+    Pay {
+        bolt11: String,
+    },
+    KeySend {
+        destination: String,
+        amount_msat: Option<u64>,
+    },
+    Withdraw {
+        destination: String,
+        amount_msat: Option<u64>,
+    },
+    FundChannel {
+        id: String,
+        amount_msat: Option<u64>,
+    },
+    MultiFundChannel {
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationResolution {
+    Succeeded,
+    Failed,
+    InFlight,
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingOperation {
+    pub id: u64,
+    pub started_at: u64,
+    pub operation: JournalOperation,
+    pub resolution: OperationResolution,
+}
+
+#[derive(Serialize, Deserialize)]
+enum JournalRecord {
+    Started {
+        id: u64,
+        timestamp: u64,
+        operation: JournalOperation,
+    },
+    Finished {
+        id: u64,
+        timestamp: u64,
+        resolution: OperationResolution,
+        detail: Option<String>,
+    },
+}
+
+/// Append-only, line-delimited JSON log of mutating operations. Every
+/// operation gets a `Started` record before the RPC is issued and a
+/// `Finished` record once its outcome is known, so anything without the
+/// latter was interrupted (e.g. by the app crashing mid-payment).
+///
+/// Kept in a `Storage` instead, only unfinished operations are stored: the
+/// `Started` record is put under the operation id and deleted once it
+/// finishes.
+pub(crate) struct Journal {
+    state: Mutex<JournalState>,
+}
+
+struct JournalState {
+    backend: Backend,
+    next_id: u64,
+}
+
+enum Backend {
+    File { path: PathBuf, file: File },
+    Storage(Arc<dyn Storage>),
+}
+
+impl Journal {
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let next_id = Self::next_id(&Self::read_records(&path)?);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open journal at {}", path.display()))?;
+        Self::end_torn_line(&mut file)?;
+
+        Ok(Journal {
+            state: Mutex::new(JournalState {
+                backend: Backend::File { path, file },
+                next_id,
+            }),
+        })
+    }
+
+    pub fn in_storage(storage: Arc<dyn Storage>) -> Self {
+        // Ids of finished operations may be reused, which is fine as they
+        // are no longer referenced anywhere.
+        let next_id = Self::next_id(&Self::stored_records(storage.as_ref()));
+        Journal {
+            state: Mutex::new(JournalState {
+                backend: Backend::Storage(storage),
+                next_id,
+            }),
+        }
+    }
+
+    fn next_id(records: &[JournalRecord]) -> u64 {
+        records
+            .iter()
+            .map(|r| match r {
+                JournalRecord::Started { id, .. } | JournalRecord::Finished { id, .. } => *id + 1,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn start(&self, operation: JournalOperation) -> anyhow::Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        let record = JournalRecord::Started {
+            id,
+            timestamp: now(),
+            operation,
+        };
+        match &mut state.backend {
+            Backend::File { file, .. } => Self::append(file, &record)?,
+            Backend::Storage(storage) => storage.put(
+                NAMESPACE.to_string(),
+                id.to_string(),
+                serde_json::to_string(&record).context("failed to encode journal record")?,
+            ),
+        }
+        state.next_id += 1;
+        Ok(id)
+    }
+
+    pub fn finish(
+        &self,
+        id: u64,
+        resolution: OperationResolution,
+        detail: Option<String>,
+    ) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match &mut state.backend {
+            Backend::File { file, .. } => Self::append(
+                file,
+                &JournalRecord::Finished {
+                    id,
+                    timestamp: now(),
+                    resolution,
+                    detail,
+                },
+            ),
+            Backend::Storage(storage) => {
+                storage.delete(NAMESPACE.to_string(), id.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Operations that were started but never finished, oldest first.
+    pub fn unfinished(&self) -> anyhow::Result<Vec<PendingOperation>> {
+        let state = self.state.lock().unwrap();
+        let records = match &state.backend {
+            Backend::File { path, .. } => Self::read_records(path)?,
+            Backend::Storage(storage) => Self::stored_records(storage.as_ref()),
+        };
+
+        let mut pending: Vec<PendingOperation> = Vec::new();
+        for record in records {
+            match record {
+                JournalRecord::Started {
+                    id,
+                    timestamp,
+                    operation,
+                } => pending.push(PendingOperation {
+                    id,
+                    started_at: timestamp,
+                    operation,
+                    resolution: OperationResolution::Unknown,
+                }),
+                JournalRecord::Finished { id, .. } => pending.retain(|p| p.id != id),
+            }
+        }
+        pending.sort_by_key(|p| p.id);
+        Ok(pending)
+    }
+
+    fn append(file: &mut File, record: &JournalRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record).context("failed to encode journal record")?;
+        line.push(b'\n');
+        file.write_all(&line)
+            .context("failed to write journal record")?;
+        file.sync_data().context("failed to sync journal")
+    }
+
+    /// Terminates a torn final line, so the next record isn't appended to it
+    /// and lost along with it.
+    fn end_torn_line(file: &mut File) -> anyhow::Result<()> {
+        let len = file.metadata().context("failed to read journal")?.len();
+        if len == 0 {
+            return Ok(());
+        }
+
+        let mut last = [0u8];
+        file.seek(SeekFrom::Start(len - 1))
+            .and_then(|_| file.read_exact(&mut last))
+            .context("failed to read journal")?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")
+                .context("failed to write journal record")?;
+        }
+        Ok(())
+    }
+
+    fn read_records(path: &PathBuf) -> anyhow::Result<Vec<JournalRecord>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("failed to read journal"),
+        };
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.context("failed to read journal")?;
+            // A torn final line means we crashed while appending; the
+            // operation was never sent, so it is safe to ignore.
+            if let Ok(record) = serde_json::from_str(&line) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    fn stored_records(storage: &dyn Storage) -> Vec<JournalRecord> {
+        storage
+            .list(NAMESPACE.to_string())
+            .into_iter()
+            .filter_map(|key| storage.get(NAMESPACE.to_string(), key))
+            .filter_map(|value| serde_json::from_str(&value).ok())
+            .collect()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn journal_path() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "glalby-journal-{}.jsonl",
+            hex::encode(rand::random::<[u8; 8]>())
+        ))
+    }
+
+    fn pay(bolt11: &str) -> JournalOperation {
+        JournalOperation::Pay {
+            bolt11: bolt11.to_string(),
+        }
+    }
+
+    fn pending_ids(journal: &Journal) -> Vec<u64> {
+        journal
+            .unfinished()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect()
+    }
+
+    #[test]
+    fn unfinished_lists_operations_started_but_not_finished() {
+        let path = journal_path();
+        let journal = Journal::open(path.clone()).unwrap();
+
+        let first = journal.start(pay("lnbc1first")).unwrap();
+        let second = journal.start(pay("lnbc1second")).unwrap();
+        let third = journal.start(pay("lnbc1third")).unwrap();
+        journal
+            .finish(second, OperationResolution::Succeeded, None)
+            .unwrap();
+
+        let pending = journal.unfinished().unwrap();
+        assert_eq!(
+            pending.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![first, third]
+        );
+        assert!(pending
+            .iter()
+            .all(|p| p.resolution == OperationResolution::Unknown));
+        assert!(matches!(
+            &pending[0].operation,
+            JournalOperation::Pay { bolt11 } if bolt11 == "lnbc1first"
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_torn_last_line_is_skipped() {
+        let path = journal_path();
+        let started = serde_json::to_string(&JournalRecord::Started {
+            id: 0,
+            timestamp: 1,
+            operation: pay("lnbc1first"),
+        })
+        .unwrap();
+        let torn = &started[..started.len() / 2];
+        std::fs::write(&path, format!("{}\n{}", started, torn)).unwrap();
+
+        assert_eq!(Journal::read_records(&path).unwrap().len(), 1);
+        let journal = Journal::open(path.clone()).unwrap();
+        assert_eq!(pending_ids(&journal), vec![0]);
+        assert_eq!(journal.start(pay("lnbc1second")).unwrap(), 1);
+        assert_eq!(pending_ids(&journal), vec![0, 1]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn ids_keep_counting_after_a_reopen() {
+        let path = journal_path();
+        {
+            let journal = Journal::open(path.clone()).unwrap();
+            journal.start(pay("lnbc1first")).unwrap();
+            let second = journal.start(pay("lnbc1second")).unwrap();
+            journal
+                .finish(second, OperationResolution::Failed, None)
+                .unwrap();
+        }
+
+        let journal = Journal::open(path.clone()).unwrap();
+        assert_eq!(journal.start(pay("lnbc1third")).unwrap(), 2);
+        assert_eq!(pending_ids(&journal), vec![0, 2]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn storage_keeps_only_unfinished_operations() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        let journal = Journal::in_storage(storage.clone());
+
+        let first = journal.start(pay("lnbc1first")).unwrap();
+        let second = journal.start(pay("lnbc1second")).unwrap();
+        journal
+            .finish(first, OperationResolution::Succeeded, None)
+            .unwrap();
+
+        assert_eq!(
+            storage.list(NAMESPACE.to_string()),
+            vec![second.to_string()]
+        );
+        assert_eq!(pending_ids(&journal), vec![second]);
+        assert_eq!(
+            pending_ids(&Journal::in_storage(storage.clone())),
+            vec![second]
+        );
+    }
+}
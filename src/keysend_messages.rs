@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::{self, cln, incoming_payment};
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+/// TLV type used by wallets to attach a text message to a keysend.
+pub(crate) const KEYSEND_MESSAGE_TLV: u64 = 34349334;
+
+const DATASTORE_KEY: [&str; 2] = ["glalby", "keysend_message"];
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Follows the node's incoming payment stream and records the message TLV of
+/// every keysend in the datastore, keyed by the label of the invoice the node
+/// creates for it.
+pub(crate) async fn record(
+    gl_node: gl_client::node::Client,
+    node: gl_client::node::ClnClient,
+    events: Arc<EventDispatcher>,
+) {
+    loop {
+        if let Err(e) = follow(gl_node.clone(), node.clone(), &events).await {
+            eprintln!("Incoming payment stream failed: {:?}", e);
+        }
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn follow(
+    mut gl_node: gl_client::node::Client,
+    mut node: gl_client::node::ClnClient,
+    events: &EventDispatcher,
+) -> anyhow::Result<()> {
+    let mut stream = gl_node
+        .stream_incoming(pb::StreamIncomingFilter {})
+        .await
+        .context("failed to subscribe to incoming payments")?
+        .into_inner();
+
+    while let Some(payment) = stream
+        .message()
+        .await
+        .context("failed to read incoming payment")?
+    {
+        let Some(incoming_payment::Details::Offchain(payment)) = payment.details else {
+            continue;
+        };
+        // Keysends are the only incoming payments without an invoice.
+        if !payment.bolt11.is_empty() {
+            continue;
+        }
+
+        let message = payment
+            .extratlvs
+            .iter()
+            .find(|tlv| tlv.r#type == KEYSEND_MESSAGE_TLV)
+            .and_then(|tlv| String::from_utf8(tlv.value.clone()).ok());
+
+        if let Some(message) = &message {
+            node.datastore(cln::DatastoreRequest {
+                key: datastore_key(&payment.label),
+                string: Some(message.clone()),
+                ..Default::default()
+            })
+            .await
+            .context("failed to store keysend message")?;
+        }
+
+        events.emit(ClientEvent::KeysendReceived {
+            label: payment.label,
+            payment_hash: hex::encode(payment.payment_hash),
+            amount_msat: payment.amount.and_then(amount_msat),
+            message,
+        });
+    }
+
+    Ok(())
+}
+
+/// All recorded keysend messages, by invoice label.
+pub(crate) async fn load(
+    mut node: gl_client::node::ClnClient,
+) -> anyhow::Result<HashMap<String, String>> {
+    let datastore = node
+        .list_datastore(cln::ListdatastoreRequest {
+            key: DATASTORE_KEY.iter().map(|k| k.to_string()).collect(),
+        })
+        .await
+        .context("failed to load keysend messages")?
+        .into_inner()
+        .datastore;
+
+    Ok(datastore
+        .into_iter()
+        .filter_map(|entry| Some((entry.key.last()?.clone(), entry.string?)))
+        .collect())
+}
+
+fn datastore_key(label: &str) -> Vec<String> {
+    DATASTORE_KEY
+        .iter()
+        .map(|k| k.to_string())
+        .chain(std::iter::once(label.to_string()))
+        .collect()
+}
+
+fn amount_msat(amount: pb::Amount) -> Option<u64> {
+    match amount.unit? {
+        pb::amount::Unit::Millisatoshi(msat) => Some(msat),
+        pb::amount::Unit::Satoshi(sat) => sat.checked_mul(1_000),
+        pb::amount::Unit::Bitcoin(btc) => btc.checked_mul(100_000_000_000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(unit: pb::amount::Unit) -> pb::Amount {
+        pb::Amount { unit: Some(unit) }
+    }
+
+    #[test]
+    fn amounts_that_overflow_are_dropped() {
+        use pb::amount::Unit;
+
+        assert_eq!(amount_msat(amount(Unit::Satoshi(21))), Some(21_000));
+        assert_eq!(amount_msat(amount(Unit::Bitcoin(1))), Some(100_000_000_000));
+        assert_eq!(amount_msat(amount(Unit::Satoshi(u64::MAX))), None);
+        assert_eq!(amount_msat(amount(Unit::Bitcoin(u64::MAX / 1_000))), None);
+    }
+}
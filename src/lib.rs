@@ -1,103 +1,939 @@
+use std::future::Future;
 use std::sync::Arc;
 
 use once_cell::sync::Lazy;
 
+mod address_history;
+mod backup_monitor;
+mod balance_alerts;
+mod bolt11;
+mod channel_closes;
+mod channel_policy;
+mod deposits;
+mod events;
+mod features;
 mod greenlight_alby_client;
+mod invoice_events;
+mod journal;
+mod keysend_messages;
+mod lsps0;
+mod mnemonic;
+mod node_version;
+mod payment_queue;
+mod recovery;
+mod refunds;
+mod session_cache;
+mod signer_supervisor;
+mod snapshot_cache;
+mod state_export;
+mod storage;
+mod tls;
 use greenlight_alby_client::{
-    new_greenlight_alby_client, GreenlightAlbyClient, GreenlightCredentials, Result, SdkError,
+    new_greenlight_alby_client, new_greenlight_alby_client_with_store, CredentialStore,
+    GreenlightAlbyClient, GreenlightCredentials, Result, SdkError, Session, TracedResponse,
 };
 
 pub use greenlight_alby_client::{
-    AmountOrAll, CloseRequest, CloseResponse, ConnectPeerRequest, ConnectPeerResponse,
-    FundChannelRequest, FundChannelResponse, GetInfoResponse, KeySendRequest, KeySendResponse,
-    ListFundsChannel, ListFundsOutput, ListFundsRequest, ListFundsResponse, ListInvoicesIndex,
-    ListInvoicesInvoice, ListInvoicesInvoicePaidOutpoint, ListInvoicesRequest,
-    ListInvoicesResponse, ListPaymentsPayment, ListPaymentsRequest, ListPaymentsResponse,
-    ListPaymentsStatus, MakeInvoiceRequest, MakeInvoiceResponse, NewAddressRequest,
-    NewAddressResponse, NewAddressType, PayRequest, PayResponse, ShutdownResponse,
-    SignMessageRequest, SignMessageResponse, TlvEntry, WithdrawRequest, WithdrawResponse,
+    ActivityEntry, ActivitySource, AmountOrAll, ChainStatus, ChannelCloseCause, ChannelCloseReason,
+    ChannelEarnings, ChannelEarningsRequest, ChannelEarningsResponse, ChannelOpenEstimate,
+    ChannelSide, ChannelStateStats, ChannelStats, CloseRequest, CloseResponse, ClosedChannel,
+    CltvRecommendation, ConnectPeerRequest, ConnectPeerResponse, CreateInvoiceRequestRequest,
+    CreateInvoiceRequestResponse, CreateOfferRequest, CreateOfferResponse, DashboardSnapshot,
+    DecodeResponse, DisableInvoiceRequestRequest, DisableInvoiceRequestResponse,
+    DisableOfferRequest, DisableOfferResponse, FeeNegotiationStep, FeerateEstimate, Feerates,
+    FeeratesResponse, FeeratesStyle, FetchInvoiceChanges, FetchInvoiceRequest,
+    FetchInvoiceResponse, Forward, ForwardStatus, FundChannelRequest, FundChannelResponse,
+    FunderPolicy, FunderUpdateRequest, FunderUpdateResponse, GetInfoResponse, GetRouteResponse,
+    HtlcDirection, InvoiceLimits, InvoiceWarning, InvstringKind, KeySendRequest, KeySendResponse,
+    LatencyPercentiles, LeaseChannelRequest, LeaseChannelResponse, ListActivityResponse,
+    ListAddressesAddress, ListAddressesResponse, ListClosedChannelsRequest,
+    ListClosedChannelsResponse, ListForwardsRequest, ListFundsChannel, ListFundsOutput,
+    ListFundsOutputStatus, ListFundsRequest, ListFundsResponse, ListHtlcsHtlc, ListHtlcsRequest,
+    ListHtlcsResponse, ListInvoiceRequestsInvoiceRequest, ListInvoiceRequestsRequest,
+    ListInvoiceRequestsResponse, ListInvoicesIndex, ListInvoicesInvoice,
+    ListInvoicesInvoicePaidOutpoint, ListInvoicesRequest, ListInvoicesResponse, ListInvoicesStatus,
+    ListOffersOffer, ListOffersRequest, ListOffersResponse, ListPaymentsPayment,
+    ListPaymentsRequest, ListPaymentsResponse, ListPaymentsStatus, ListPeerChannelsChannel,
+    ListPeerChannelsRequest, ListPeerChannelsResponse, ListPeersPeer, ListPeersRequest,
+    ListPeersResponse, ListTransactionsResponse, Lsps0Request, Lsps0Response, MakeInvoiceRequest,
+    MakeInvoiceResponse, MultiFundChannelChannel, MultiFundChannelDestination,
+    MultiFundChannelRequest, MultiFundChannelResponse, Network, NewAddressRequest,
+    NewAddressResponse, NewAddressType, NodeLimits, OnchainFeeEstimates, Outpoint, Page, PageIndex,
+    Paged, PagedForwards, PagedInvoices, PagedPayments, PayRequest, PayResponse, PayStatus,
+    PayWithRetryRequest, PayWithRetryResponse, PaymentAttempt, PendingChannel, PendingChannelState,
+    PollInvoiceUpdatesResponse, RouteHint, RouteHintHop, RouteHop, SchedulerLatency,
+    SendPayRequest, SendPayResponse, SetAppearanceRequest, SetChannelChannel, SetChannelRequest,
+    SetChannelResponse, SetPsbtVersionRequest, SetPsbtVersionResponse, ShutdownOutcome,
+    ShutdownResponse, SignInvoiceRequest, SignInvoiceResponse, SignMessageRequest,
+    SignMessageResponse, TlvEntry, Transaction, TransactionInput, TransactionKind,
+    TransactionOutput, UnifiedReceiveRequest, UnifiedReceiveResponse, VoidInvoiceRequest,
+    VoidInvoiceResponse, WaitBlockHeightRequest, WaitBlockHeightResponse, WaitSendPayResponse,
+    WithdrawRequest, WithdrawResponse,
 };
 
+pub use backup_monitor::BackupStatus;
+pub use balance_alerts::{BalanceAlertThresholds, BalanceKind, BalanceSummary};
+pub use bolt11::DecodedInvoice;
+pub use channel_policy::ChannelOpenPolicy;
+pub use events::{ClientEvent, EventListener};
+pub use features::Feature;
+pub use invoice_events::{InvoiceListener, PaidInvoice};
+pub use journal::{JournalOperation, OperationResolution, PendingOperation};
+pub use mnemonic::MnemonicValidation;
+pub use payment_queue::QueuedPayment;
+pub use recovery::{CancellationToken, RecoverProgress, RecoverStage};
+pub use refunds::Refund;
+pub use snapshot_cache::{CachedSnapshot, SnapshotStore};
+pub use storage::{MemoryStorage, Storage};
+
+/// The async client the blocking one wraps, for Rust users already running
+/// tokio. Everything here runs on the caller's runtime.
+pub mod nonblocking {
+    pub use crate::greenlight_alby_client::{
+        new_greenlight_alby_client, new_greenlight_alby_client_with_store, recover,
+        recover_with_progress, recover_with_store, register, register_with_store,
+        GreenlightAlbyClient, Session,
+    };
+}
+
 static RT: Lazy<tokio::runtime::Runtime> = Lazy::new(|| tokio::runtime::Runtime::new().unwrap());
 
 pub struct BlockingGreenlightAlbyClient {
     greenlight_alby_client: Arc<GreenlightAlbyClient>,
+    runtime: tokio::runtime::Handle,
 }
 
 impl BlockingGreenlightAlbyClient {
     pub fn shutdown(&self) -> Result<ShutdownResponse> {
-        rt().block_on(self.greenlight_alby_client.shutdown())
+        self.block_on("shutdown", self.greenlight_alby_client.shutdown())
+    }
+
+    pub fn lock(&self) {
+        self.runtime.block_on(self.greenlight_alby_client.lock())
+    }
+
+    pub fn unlock(&self, mnemonic: String) -> Result<()> {
+        self.block_on("unlock", self.greenlight_alby_client.unlock(mnemonic))
     }
 
     pub fn get_info(&self) -> Result<GetInfoResponse> {
-        rt().block_on(self.greenlight_alby_client.get_info())
+        self.block_on("get_info", self.greenlight_alby_client.get_info())
     }
 
     pub fn make_invoice(&self, req: MakeInvoiceRequest) -> Result<MakeInvoiceResponse> {
-        rt().block_on(self.greenlight_alby_client.make_invoice(req))
+        self.block_on_response(
+            "make_invoice",
+            self.greenlight_alby_client.make_invoice(req),
+        )
     }
 
     pub fn pay(&self, req: PayRequest) -> Result<PayResponse> {
-        rt().block_on(self.greenlight_alby_client.pay(req))
+        self.block_on_response("pay", self.greenlight_alby_client.pay(req))
+    }
+
+    pub fn resume_payment(&self, payment_hash: String) -> Result<PayResponse> {
+        self.block_on_response(
+            "resume_payment",
+            self.greenlight_alby_client.resume_payment(payment_hash),
+        )
     }
 
     pub fn key_send(&self, req: KeySendRequest) -> Result<KeySendResponse> {
-        rt().block_on(self.greenlight_alby_client.key_send(req))
+        self.block_on_response("key_send", self.greenlight_alby_client.key_send(req))
     }
 
     pub fn list_funds(&self, req: ListFundsRequest) -> Result<ListFundsResponse> {
-        rt().block_on(self.greenlight_alby_client.list_funds(req))
+        self.block_on("list_funds", self.greenlight_alby_client.list_funds(req))
     }
 
     pub fn connect_peer(&self, req: ConnectPeerRequest) -> Result<ConnectPeerResponse> {
-        rt().block_on(self.greenlight_alby_client.connect_peer(req))
+        self.block_on(
+            "connect_peer",
+            self.greenlight_alby_client.connect_peer(req),
+        )
     }
 
     pub fn fund_channel(&self, req: FundChannelRequest) -> Result<FundChannelResponse> {
-        rt().block_on(self.greenlight_alby_client.fund_channel(req))
+        self.block_on_response(
+            "fund_channel",
+            self.greenlight_alby_client.fund_channel(req),
+        )
+    }
+
+    pub fn multi_fund_channel(
+        &self,
+        req: MultiFundChannelRequest,
+    ) -> Result<MultiFundChannelResponse> {
+        self.block_on_response(
+            "multi_fund_channel",
+            self.greenlight_alby_client.multi_fund_channel(req),
+        )
     }
 
     pub fn new_address(&self, req: NewAddressRequest) -> Result<NewAddressResponse> {
-        rt().block_on(self.greenlight_alby_client.new_address(req))
+        self.block_on("new_address", self.greenlight_alby_client.new_address(req))
     }
 
     pub fn list_invoices(&self, req: ListInvoicesRequest) -> Result<ListInvoicesResponse> {
-        rt().block_on(self.greenlight_alby_client.list_invoices(req))
+        self.block_on(
+            "list_invoices",
+            self.greenlight_alby_client.list_invoices(req),
+        )
     }
 
     pub fn list_payments(&self, req: ListPaymentsRequest) -> Result<ListPaymentsResponse> {
-        rt().block_on(self.greenlight_alby_client.list_payments(req))
+        self.block_on(
+            "list_payments",
+            self.greenlight_alby_client.list_payments(req),
+        )
+    }
+
+    pub fn decode(&self, invstring: String) -> Result<DecodeResponse> {
+        self.block_on("decode", self.greenlight_alby_client.decode(invstring))
     }
 
     pub fn sign_message(&self, req: SignMessageRequest) -> Result<SignMessageResponse> {
-        rt().block_on(self.greenlight_alby_client.sign_message(req))
+        self.block_on(
+            "sign_message",
+            self.greenlight_alby_client.sign_message(req),
+        )
+    }
+
+    pub fn set_psbt_version(&self, req: SetPsbtVersionRequest) -> Result<SetPsbtVersionResponse> {
+        self.block_on(
+            "set_psbt_version",
+            self.greenlight_alby_client.set_psbt_version(req),
+        )
     }
 
     pub fn withdraw(&self, req: WithdrawRequest) -> Result<WithdrawResponse> {
-        rt().block_on(self.greenlight_alby_client.withdraw(req))
+        self.block_on_response("withdraw", self.greenlight_alby_client.withdraw(req))
     }
 
     pub fn close(&self, req: CloseRequest) -> Result<CloseResponse> {
-        rt().block_on(self.greenlight_alby_client.close(req))
+        self.block_on_response("close", self.greenlight_alby_client.close(req))
+    }
+
+    pub fn subscribe_events(&self, listener: Box<dyn EventListener>) {
+        self.greenlight_alby_client.subscribe_events(listener)
+    }
+
+    pub fn set_channel_open_policy(&self, policy: Option<ChannelOpenPolicy>) {
+        self.runtime
+            .block_on(self.greenlight_alby_client.set_channel_open_policy(policy))
+    }
+
+    pub fn set_balance_alerts(&self, thresholds: Option<BalanceAlertThresholds>) {
+        self.runtime
+            .block_on(self.greenlight_alby_client.set_balance_alerts(thresholds))
+    }
+
+    pub fn subscribe_invoices(
+        &self,
+        listener: Box<dyn InvoiceListener>,
+        last_pay_index: Option<u64>,
+    ) -> Result<()> {
+        self.block_on(
+            "subscribe_invoices",
+            self.greenlight_alby_client
+                .subscribe_invoices(listener, last_pay_index),
+        )
+    }
+
+    pub fn subscribe_invoice_events(&self, start_index: Option<u64>) -> Result<()> {
+        self.block_on(
+            "subscribe_invoice_events",
+            self.greenlight_alby_client
+                .subscribe_invoice_events(start_index),
+        )
+    }
+
+    pub fn set_backup_monitor(&self, max_age_secs: Option<u64>) {
+        self.runtime
+            .block_on(self.greenlight_alby_client.set_backup_monitor(max_age_secs))
+    }
+
+    pub fn set_deposit_events(&self, target_confirmations: Option<u32>) {
+        self.runtime.block_on(
+            self.greenlight_alby_client
+                .set_deposit_events(target_confirmations),
+        )
+    }
+
+    pub fn set_channel_close_events(&self, enabled: bool) {
+        self.runtime.block_on(
+            self.greenlight_alby_client
+                .set_channel_close_events(enabled),
+        )
+    }
+
+    pub fn set_privacy_mode(&self, enabled: bool) {
+        self.greenlight_alby_client.set_privacy_mode(enabled)
+    }
+
+    pub fn set_invoice_limits(&self, limits: Option<InvoiceLimits>) {
+        self.greenlight_alby_client.set_invoice_limits(limits)
+    }
+
+    pub fn set_byte_fields(&self, enabled: bool) {
+        self.greenlight_alby_client.set_byte_fields(enabled)
+    }
+
+    pub fn set_snapshot_store(&self, store: Box<dyn SnapshotStore>) {
+        self.greenlight_alby_client.set_snapshot_store(store)
+    }
+
+    pub fn set_storage(&self, storage: Box<dyn Storage>) {
+        self.greenlight_alby_client.set_storage(storage)
+    }
+
+    pub fn set_keysend_descriptions(&self, enabled: bool) {
+        self.runtime.block_on(
+            self.greenlight_alby_client
+                .set_keysend_descriptions(enabled),
+        )
+    }
+
+    pub fn export_state(&self) -> Result<String> {
+        self.block_on("export_state", self.greenlight_alby_client.export_state())
+    }
+
+    pub fn restore_state(&self, state: String) -> Result<()> {
+        self.block_on(
+            "restore_state",
+            self.greenlight_alby_client.restore_state(state),
+        )
+    }
+
+    pub fn enable_journal(&self, path: String) -> Result<()> {
+        self.block_on(
+            "enable_journal",
+            self.greenlight_alby_client.enable_journal(path),
+        )
+    }
+
+    pub fn enable_journal_in_storage(&self) {
+        self.runtime
+            .block_on(self.greenlight_alby_client.enable_journal_in_storage())
+    }
+
+    pub fn recover_pending_operations(&self) -> Result<Vec<PendingOperation>> {
+        self.block_on(
+            "recover_pending_operations",
+            self.greenlight_alby_client.recover_pending_operations(),
+        )
+    }
+
+    pub fn enable_payment_queue(&self, path: String) -> Result<()> {
+        self.block_on(
+            "enable_payment_queue",
+            self.greenlight_alby_client.enable_payment_queue(path),
+        )
+    }
+
+    pub fn enable_payment_queue_in_storage(&self) -> Result<()> {
+        self.block_on(
+            "enable_payment_queue_in_storage",
+            self.greenlight_alby_client
+                .enable_payment_queue_in_storage(),
+        )
+    }
+
+    pub fn enqueue_payment(&self, req: PayRequest) -> Result<u64> {
+        self.block_on(
+            "enqueue_payment",
+            self.greenlight_alby_client.enqueue_payment(req),
+        )
+    }
+
+    pub fn list_queued_payments(&self) -> Result<Vec<QueuedPayment>> {
+        self.block_on(
+            "list_queued_payments",
+            self.greenlight_alby_client.list_queued_payments(),
+        )
+    }
+
+    pub fn create_offer(&self, req: CreateOfferRequest) -> Result<CreateOfferResponse> {
+        self.block_on(
+            "create_offer",
+            self.greenlight_alby_client.create_offer(req),
+        )
+    }
+
+    pub fn list_offers(&self, req: ListOffersRequest) -> Result<ListOffersResponse> {
+        self.block_on("list_offers", self.greenlight_alby_client.list_offers(req))
+    }
+
+    pub fn disable_offer(&self, req: DisableOfferRequest) -> Result<DisableOfferResponse> {
+        self.block_on(
+            "disable_offer",
+            self.greenlight_alby_client.disable_offer(req),
+        )
+    }
+
+    pub fn create_invoice_request(
+        &self,
+        req: CreateInvoiceRequestRequest,
+    ) -> Result<CreateInvoiceRequestResponse> {
+        self.block_on(
+            "create_invoice_request",
+            self.greenlight_alby_client.create_invoice_request(req),
+        )
+    }
+
+    pub fn list_invoice_requests(
+        &self,
+        req: ListInvoiceRequestsRequest,
+    ) -> Result<ListInvoiceRequestsResponse> {
+        self.block_on(
+            "list_invoice_requests",
+            self.greenlight_alby_client.list_invoice_requests(req),
+        )
+    }
+
+    pub fn disable_invoice_request(
+        &self,
+        req: DisableInvoiceRequestRequest,
+    ) -> Result<DisableInvoiceRequestResponse> {
+        self.block_on(
+            "disable_invoice_request",
+            self.greenlight_alby_client.disable_invoice_request(req),
+        )
+    }
+
+    pub fn funder_update(&self, req: FunderUpdateRequest) -> Result<FunderUpdateResponse> {
+        self.block_on(
+            "funder_update",
+            self.greenlight_alby_client.funder_update(req),
+        )
+    }
+
+    pub fn lease_channel_from(&self, req: LeaseChannelRequest) -> Result<LeaseChannelResponse> {
+        self.block_on(
+            "lease_channel_from",
+            self.greenlight_alby_client.lease_channel_from(req),
+        )
+    }
+
+    pub fn set_appearance(&self, req: SetAppearanceRequest) -> Result<GetInfoResponse> {
+        self.block_on(
+            "set_appearance",
+            self.greenlight_alby_client.set_appearance(req),
+        )
+    }
+
+    pub fn fetch_invoice(&self, req: FetchInvoiceRequest) -> Result<FetchInvoiceResponse> {
+        self.block_on(
+            "fetch_invoice",
+            self.greenlight_alby_client.fetch_invoice(req),
+        )
+    }
+
+    pub fn pay_offer(&self, req: FetchInvoiceRequest) -> Result<PayResponse> {
+        self.block_on_response("pay_offer", self.greenlight_alby_client.pay_offer(req))
+    }
+
+    pub fn count_invoices(&self, status: Option<ListInvoicesStatus>) -> Result<u64> {
+        self.block_on(
+            "count_invoices",
+            self.greenlight_alby_client.count_invoices(status),
+        )
+    }
+
+    pub fn count_payments(&self, status: Option<ListPaymentsStatus>) -> Result<u64> {
+        self.block_on(
+            "count_payments",
+            self.greenlight_alby_client.count_payments(status),
+        )
+    }
+
+    pub fn list_invoices_paged(&self, page: Page) -> Result<PagedInvoices> {
+        self.block_on(
+            "list_invoices_paged",
+            self.greenlight_alby_client.list_invoices_paged(page),
+        )
+    }
+
+    pub fn list_payments_paged(&self, page: Page) -> Result<PagedPayments> {
+        self.block_on(
+            "list_payments_paged",
+            self.greenlight_alby_client.list_payments_paged(page),
+        )
+    }
+
+    pub fn get_route_hints(&self) -> Result<Vec<RouteHint>> {
+        self.block_on(
+            "get_route_hints",
+            self.greenlight_alby_client.get_route_hints(),
+        )
+    }
+
+    pub fn list_forwards(&self, req: ListForwardsRequest, page: Page) -> Result<PagedForwards> {
+        self.block_on(
+            "list_forwards",
+            self.greenlight_alby_client.list_forwards(req, page),
+        )
+    }
+
+    pub fn list_closed_channels(
+        &self,
+        req: ListClosedChannelsRequest,
+    ) -> Result<ListClosedChannelsResponse> {
+        self.block_on(
+            "list_closed_channels",
+            self.greenlight_alby_client.list_closed_channels(req),
+        )
+    }
+
+    pub fn list_peer_channels(
+        &self,
+        req: ListPeerChannelsRequest,
+    ) -> Result<ListPeerChannelsResponse> {
+        self.block_on(
+            "list_peer_channels",
+            self.greenlight_alby_client.list_peer_channels(req),
+        )
+    }
+
+    pub fn list_peers(&self, req: ListPeersRequest) -> Result<ListPeersResponse> {
+        self.block_on("list_peers", self.greenlight_alby_client.list_peers(req))
+    }
+
+    pub fn estimate_channel_open(
+        &self,
+        amount_sat: u64,
+        feerate_perkw: Option<u32>,
+    ) -> Result<ChannelOpenEstimate> {
+        self.block_on(
+            "estimate_channel_open",
+            self.greenlight_alby_client
+                .estimate_channel_open(amount_sat, feerate_perkw),
+        )
+    }
+
+    pub fn wait_block_height(
+        &self,
+        req: WaitBlockHeightRequest,
+    ) -> Result<WaitBlockHeightResponse> {
+        self.block_on(
+            "wait_block_height",
+            self.greenlight_alby_client.wait_block_height(req),
+        )
+    }
+
+    pub fn chain_status(&self) -> Result<ChainStatus> {
+        self.block_on("chain_status", self.greenlight_alby_client.chain_status())
+    }
+
+    pub fn list_addresses(&self) -> Result<ListAddressesResponse> {
+        self.block_on(
+            "list_addresses",
+            self.greenlight_alby_client.list_addresses(),
+        )
+    }
+
+    pub fn create_unified_receive(
+        &self,
+        req: UnifiedReceiveRequest,
+    ) -> Result<UnifiedReceiveResponse> {
+        self.block_on(
+            "create_unified_receive",
+            self.greenlight_alby_client.create_unified_receive(req),
+        )
+    }
+
+    pub fn sign_invoice(&self, req: SignInvoiceRequest) -> Result<SignInvoiceResponse> {
+        self.block_on(
+            "sign_invoice",
+            self.greenlight_alby_client.sign_invoice(req),
+        )
+    }
+
+    pub fn backup_status(&self) -> Result<BackupStatus> {
+        self.block_on("backup_status", self.greenlight_alby_client.backup_status())
+    }
+
+    pub fn get_balances(&self) -> Result<BalanceSummary> {
+        self.block_on("get_balances", self.greenlight_alby_client.get_balances())
+    }
+
+    pub fn void_invoice(&self, req: VoidInvoiceRequest) -> Result<VoidInvoiceResponse> {
+        self.block_on(
+            "void_invoice",
+            self.greenlight_alby_client.void_invoice(req),
+        )
+    }
+
+    pub fn measure_scheduler_latency(&self, samples: Option<u32>) -> Result<SchedulerLatency> {
+        self.block_on(
+            "measure_scheduler_latency",
+            self.greenlight_alby_client
+                .measure_scheduler_latency(samples),
+        )
+    }
+
+    pub fn channel_earnings(&self, req: ChannelEarningsRequest) -> Result<ChannelEarningsResponse> {
+        self.block_on(
+            "channel_earnings",
+            self.greenlight_alby_client.channel_earnings(req),
+        )
+    }
+
+    pub fn pay_with_retry(&self, req: PayWithRetryRequest) -> Result<PayWithRetryResponse> {
+        self.block_on(
+            "pay_with_retry",
+            self.greenlight_alby_client.pay_with_retry(req),
+        )
+    }
+
+    pub fn list_htlcs(&self, req: ListHtlcsRequest) -> Result<ListHtlcsResponse> {
+        self.block_on("list_htlcs", self.greenlight_alby_client.list_htlcs(req))
+    }
+
+    pub fn recommend_invoice_cltv(&self) -> Result<CltvRecommendation> {
+        self.block_on(
+            "recommend_invoice_cltv",
+            self.greenlight_alby_client.recommend_invoice_cltv(),
+        )
+    }
+
+    pub fn lsps0_request(&self, req: Lsps0Request) -> Result<Lsps0Response> {
+        self.block_on(
+            "lsps0_request",
+            self.greenlight_alby_client.lsps0_request(req),
+        )
+    }
+
+    pub fn lsps0_list_protocols(&self, peer_id: String) -> Result<Vec<u32>> {
+        self.block_on(
+            "lsps0_list_protocols",
+            self.greenlight_alby_client.lsps0_list_protocols(peer_id),
+        )
     }
+
+    pub fn poll_invoice_updates(
+        &self,
+        last_pay_index: u64,
+        timeout_secs: u64,
+    ) -> Result<PollInvoiceUpdatesResponse> {
+        self.block_on(
+            "poll_invoice_updates",
+            self.greenlight_alby_client
+                .poll_invoice_updates(last_pay_index, timeout_secs),
+        )
+    }
+
+    pub fn list_activity(&self, limit: Option<u32>) -> Result<ListActivityResponse> {
+        self.block_on(
+            "list_activity",
+            self.greenlight_alby_client.list_activity(limit),
+        )
+    }
+
+    pub fn list_refunds(&self) -> Result<Vec<Refund>> {
+        self.block_on("list_refunds", self.greenlight_alby_client.list_refunds())
+    }
+
+    pub fn set_refund_events(&self, enabled: bool) {
+        self.runtime
+            .block_on(self.greenlight_alby_client.set_refund_events(enabled))
+    }
+
+    pub fn node_version(&self) -> Result<String> {
+        self.block_on("node_version", self.greenlight_alby_client.node_version())
+    }
+
+    pub fn supports(&self, feature: Feature) -> Result<bool> {
+        self.block_on("supports", self.greenlight_alby_client.supports(feature))
+    }
+
+    pub fn channel_stats(&self) -> Result<ChannelStats> {
+        self.block_on("channel_stats", self.greenlight_alby_client.channel_stats())
+    }
+
+    pub fn list_transactions(&self) -> Result<ListTransactionsResponse> {
+        self.block_on(
+            "list_transactions",
+            self.greenlight_alby_client.list_transactions(),
+        )
+    }
+
+    pub fn get_node_limits(&self) -> Result<NodeLimits> {
+        self.block_on(
+            "get_node_limits",
+            self.greenlight_alby_client.get_node_limits(),
+        )
+    }
+
+    pub fn set_channel(&self, req: SetChannelRequest) -> Result<SetChannelResponse> {
+        self.block_on("set_channel", self.greenlight_alby_client.set_channel(req))
+    }
+
+    pub fn get_payments_by_hashes(
+        &self,
+        payment_hashes: Vec<String>,
+    ) -> Result<Vec<ListPaymentsPayment>> {
+        self.block_on(
+            "get_payments_by_hashes",
+            self.greenlight_alby_client
+                .get_payments_by_hashes(payment_hashes),
+        )
+    }
+
+    pub fn fee_rates(&self, style: FeeratesStyle) -> Result<FeeratesResponse> {
+        self.block_on("fee_rates", self.greenlight_alby_client.fee_rates(style))
+    }
+
+    pub fn get_route(
+        &self,
+        destination: String,
+        amount_msat: u64,
+        riskfactor: u64,
+        exclude: Vec<String>,
+    ) -> Result<GetRouteResponse> {
+        self.block_on(
+            "get_route",
+            self.greenlight_alby_client
+                .get_route(destination, amount_msat, riskfactor, exclude),
+        )
+    }
+
+    pub fn send_pay(&self, req: SendPayRequest) -> Result<SendPayResponse> {
+        self.block_on_response("send_pay", self.greenlight_alby_client.send_pay(req))
+    }
+
+    pub fn wait_send_pay(
+        &self,
+        payment_hash: String,
+        timeout: Option<u32>,
+        partid: Option<u64>,
+    ) -> Result<WaitSendPayResponse> {
+        self.block_on(
+            "wait_send_pay",
+            self.greenlight_alby_client
+                .wait_send_pay(payment_hash, timeout, partid),
+        )
+    }
+
+    pub fn get_dashboard_snapshot(&self, recent_limit: u32) -> Result<DashboardSnapshot> {
+        self.block_on(
+            "get_dashboard_snapshot",
+            self.greenlight_alby_client
+                .get_dashboard_snapshot(recent_limit),
+        )
+    }
+
+    fn block_on<T>(&self, method: &'static str, f: impl Future<Output = Result<T>>) -> Result<T> {
+        self.runtime
+            .block_on(self.greenlight_alby_client.traced(method, f))
+    }
+
+    fn block_on_response<T: TracedResponse>(
+        &self,
+        method: &'static str,
+        f: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.runtime
+            .block_on(self.greenlight_alby_client.traced_response(method, f))
+    }
+}
+
+pub fn recover(mnemonic: String, network: Network) -> Result<GreenlightCredentials> {
+    rt().block_on(greenlight_alby_client::recover(mnemonic, network))
+}
+
+pub fn recover_with_progress(
+    mnemonic: String,
+    network: Network,
+    progress: Box<dyn RecoverProgress>,
+    cancel: Arc<CancellationToken>,
+) -> Result<GreenlightCredentials> {
+    rt().block_on(greenlight_alby_client::recover_with_progress(
+        mnemonic,
+        network,
+        progress.as_ref(),
+        &cancel,
+    ))
+}
+
+pub fn register(
+    mnemonic: String,
+    network: Network,
+    invite_code: String,
+) -> Result<GreenlightCredentials> {
+    rt().block_on(greenlight_alby_client::register(
+        mnemonic,
+        network,
+        invite_code,
+    ))
 }
 
-pub fn recover(mnemonic: String) -> Result<GreenlightCredentials> {
-    rt().block_on(greenlight_alby_client::recover(mnemonic))
+pub fn recover_with_store(
+    mnemonic: String,
+    network: Network,
+    store: Box<dyn CredentialStore>,
+) -> Result<GreenlightCredentials> {
+    rt().block_on(greenlight_alby_client::recover_with_store(
+        mnemonic,
+        network,
+        store.as_ref(),
+    ))
+}
+
+pub fn register_with_store(
+    mnemonic: String,
+    network: Network,
+    invite_code: String,
+    store: Box<dyn CredentialStore>,
+) -> Result<GreenlightCredentials> {
+    rt().block_on(greenlight_alby_client::register_with_store(
+        mnemonic,
+        network,
+        invite_code,
+        store.as_ref(),
+    ))
+}
+
+pub fn import_state(mnemonic: String, state: String) -> Result<GreenlightCredentials> {
+    greenlight_alby_client::import_state(mnemonic, state)
+}
+
+pub fn verify_preimage(payment_hash: String, preimage: String) -> Result<bool> {
+    greenlight_alby_client::verify_preimage(payment_hash, preimage)
+}
+
+pub fn decode_bolt11(bolt11: String) -> Result<DecodedInvoice> {
+    greenlight_alby_client::decode_bolt11(bolt11)
+}
+
+pub fn get_cached_snapshot(store: Box<dyn SnapshotStore>) -> Option<CachedSnapshot> {
+    greenlight_alby_client::get_cached_snapshot(store.as_ref())
+}
+
+pub fn get_cached_snapshot_from_storage(storage: Box<dyn Storage>) -> Option<CachedSnapshot> {
+    greenlight_alby_client::get_cached_snapshot_from_storage(storage.into())
+}
+
+pub fn validate_mnemonic(phrase: String) -> MnemonicValidation {
+    greenlight_alby_client::validate_mnemonic(phrase)
+}
+
+pub fn generate_mnemonic(word_count: u32) -> Result<String> {
+    greenlight_alby_client::generate_mnemonic(word_count)
+}
+
+pub fn set_tls_ca_certificate(ca_certificate: Option<String>) -> Result<()> {
+    greenlight_alby_client::set_tls_ca_certificate(ca_certificate)
+}
+
+pub fn set_session_cache(
+    storage: Option<Box<dyn Storage>>,
+    max_age_secs: Option<u64>,
+) -> Result<()> {
+    greenlight_alby_client::set_session_cache(storage.map(Into::into), max_age_secs)
 }
 
-pub fn register(mnemonic: String, invite_code: String) -> Result<GreenlightCredentials> {
-    rt().block_on(greenlight_alby_client::register(mnemonic, invite_code))
+pub fn invalidate_session_cache() {
+    greenlight_alby_client::invalidate_session_cache()
+}
+
+pub struct BlockingSession {
+    session: Session,
+}
+
+impl BlockingSession {
+    pub fn recover(&self) -> Result<GreenlightCredentials> {
+        rt().block_on(self.session.recover())
+    }
+
+    pub fn register(&self, invite_code: String) -> Result<GreenlightCredentials> {
+        rt().block_on(self.session.register(invite_code))
+    }
+
+    pub fn new_client(
+        &self,
+        credentials: GreenlightCredentials,
+    ) -> Result<Arc<BlockingGreenlightAlbyClient>> {
+        rt().block_on(async move {
+            let greenlight_alby_client = self.session.new_client(credentials).await?;
+            Ok(Arc::new(BlockingGreenlightAlbyClient {
+                greenlight_alby_client,
+                runtime: rt().handle().clone(),
+            }))
+        })
+    }
+
+    pub fn new_client_with_store(
+        &self,
+        store: Box<dyn CredentialStore>,
+    ) -> Result<Arc<BlockingGreenlightAlbyClient>> {
+        rt().block_on(async move {
+            let greenlight_alby_client = self.session.new_client_with_store(store.as_ref()).await?;
+            Ok(Arc::new(BlockingGreenlightAlbyClient {
+                greenlight_alby_client,
+                runtime: rt().handle().clone(),
+            }))
+        })
+    }
+}
+
+pub fn new_session(mnemonic: String, network: Network) -> Result<Arc<BlockingSession>> {
+    rt().block_on(async move {
+        let session = Session::new(mnemonic, network).await?;
+        Ok(Arc::new(BlockingSession { session }))
+    })
 }
 
 pub fn new_blocking_greenlight_alby_client(
     mnemonic: String,
+    network: Network,
     credentials: GreenlightCredentials,
 ) -> Result<Arc<BlockingGreenlightAlbyClient>> {
     rt().block_on(async move {
-        let greenlight_alby_client = new_greenlight_alby_client(mnemonic, credentials).await?;
+        let greenlight_alby_client =
+            new_greenlight_alby_client(mnemonic, network, credentials).await?;
+        let blocking_greenlight_alby_client = Arc::new(BlockingGreenlightAlbyClient {
+            greenlight_alby_client,
+            runtime: rt().handle().clone(),
+        });
+
+        Ok(blocking_greenlight_alby_client)
+    })
+}
+
+/// Like `new_blocking_greenlight_alby_client`, but runs the client and its
+/// background tasks on `runtime` instead of the crate's own runtime, for Rust
+/// users that already have one. Blocking calls must not be made from that
+/// runtime's worker threads; use `spawn_blocking` there.
+pub fn new_blocking_greenlight_alby_client_with_runtime(
+    mnemonic: String,
+    network: Network,
+    credentials: GreenlightCredentials,
+    runtime: tokio::runtime::Handle,
+) -> Result<Arc<BlockingGreenlightAlbyClient>> {
+    runtime.block_on(async {
+        let greenlight_alby_client =
+            new_greenlight_alby_client(mnemonic, network, credentials).await?;
+        let blocking_greenlight_alby_client = Arc::new(BlockingGreenlightAlbyClient {
+            greenlight_alby_client,
+            runtime: runtime.clone(),
+        });
+
+        Ok(blocking_greenlight_alby_client)
+    })
+}
+
+pub fn new_blocking_greenlight_alby_client_with_store(
+    mnemonic: String,
+    network: Network,
+    store: Box<dyn CredentialStore>,
+) -> Result<Arc<BlockingGreenlightAlbyClient>> {
+    rt().block_on(async move {
+        let greenlight_alby_client =
+            new_greenlight_alby_client_with_store(mnemonic, network, store.as_ref()).await?;
         let blocking_greenlight_alby_client = Arc::new(BlockingGreenlightAlbyClient {
             greenlight_alby_client,
+            runtime: rt().handle().clone(),
         });
 
         Ok(blocking_greenlight_alby_client)
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use gl_client::pb::{self, cln};
+use serde_json::{json, Value};
+use tokio::sync::{oneshot, watch};
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+/// Custom message type LSPS0 sends its JSON-RPC messages under.
+pub(crate) const LSPS0_MESSAGE_TYPE: u16 = 37913;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+type PendingRequests = Mutex<HashMap<String, (Vec<u8>, oneshot::Sender<Value>)>>;
+
+/// JSON-RPC over peer custom messages, as specified by LSPS0. Requests are
+/// correlated with their responses by id, so several can be in flight with
+/// the same or different peers. LSP protocols (LSPS1, LSPS2, ...) are layered
+/// on top by picking the method and params. Messages from a peer that aren't
+/// responses are emitted as `ClientEvent::Lsps0Notification`.
+pub(crate) struct Lsps0Transport {
+    pending: PendingRequests,
+    /// Whether the custom message stream is open; responses sent while it
+    /// isn't would be lost.
+    subscribed: watch::Sender<bool>,
+    events: Arc<EventDispatcher>,
+}
+
+impl Lsps0Transport {
+    pub fn new(events: Arc<EventDispatcher>) -> Self {
+        Lsps0Transport {
+            pending: Mutex::default(),
+            subscribed: watch::channel(false).0,
+            events,
+        }
+    }
+
+    /// Waits until the custom message stream is open, for at most `timeout`.
+    pub async fn wait_subscribed(&self, timeout: Duration) -> anyhow::Result<()> {
+        let mut subscribed = self.subscribed.subscribe();
+        time::timeout(timeout, subscribed.wait_for(|subscribed| *subscribed))
+            .await
+            .map_err(|_| anyhow!("custom message stream is not open"))?
+            .map_err(|_| anyhow!("transport stopped"))?;
+        Ok(())
+    }
+
+    pub async fn request(
+        &self,
+        mut node: gl_client::node::ClnClient,
+        peer_id: Vec<u8>,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> anyhow::Result<Value> {
+        self.wait_subscribed(timeout).await?;
+
+        let id = hex::encode(rand::random::<[u8; 8]>());
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(id.clone(), (peer_id.clone(), tx));
+
+        let mut msg = LSPS0_MESSAGE_TYPE.to_be_bytes().to_vec();
+        msg.extend(serde_json::to_vec(&request).context("failed to encode request")?);
+
+        let sent = node
+            .send_custom_msg(cln::SendcustommsgRequest {
+                node_id: peer_id,
+                msg,
+            })
+            .await
+            .context("failed to send request to peer");
+        let response = match sent {
+            Ok(_) => time::timeout(timeout, rx).await,
+            Err(e) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(e);
+            }
+        };
+        self.pending.lock().unwrap().remove(&id);
+
+        let mut response = response
+            .map_err(|_| anyhow!("peer did not respond to {} in time", method))?
+            .map_err(|_| anyhow!("transport stopped"))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!(
+                "peer returned error {}: {}",
+                error["code"],
+                error["message"].as_str().unwrap_or_default()
+            ));
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Hands a message from `peer_id` to the request it answers, or to the
+    /// host when the peer sent a request or notification of its own.
+    fn receive(&self, peer_id: &[u8], message: Value) {
+        match message["method"].as_str() {
+            Some(method) => self.events.emit(ClientEvent::Lsps0Notification {
+                peer_id: hex::encode(peer_id),
+                method: method.to_string(),
+                params: message["params"].to_string(),
+            }),
+            None => self.resolve(peer_id, message),
+        }
+    }
+
+    fn resolve(&self, peer_id: &[u8], response: Value) {
+        let Some(id) = response["id"].as_str() else {
+            return;
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        // Only the peer a request went to may answer it.
+        if pending.get(id).is_some_and(|(peer, _)| peer == peer_id) {
+            if let Some((_, tx)) = pending.remove(id) {
+                let _ = tx.send(response);
+            }
+        }
+    }
+}
+
+/// Hands LSPS0 messages from the node's custom message stream to the
+/// requests waiting for them or to the host.
+pub(crate) async fn listen(transport: Arc<Lsps0Transport>, gl_node: gl_client::node::Client) {
+    loop {
+        if let Err(e) = follow(&transport, gl_node.clone()).await {
+            eprintln!("Custom message stream failed: {:?}", e);
+        }
+        transport.subscribed.send_replace(false);
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn follow(
+    transport: &Lsps0Transport,
+    mut gl_node: gl_client::node::Client,
+) -> anyhow::Result<()> {
+    let mut stream = gl_node
+        .stream_custommsg(pb::StreamCustommsgRequest {})
+        .await
+        .context("failed to subscribe to custom messages")?
+        .into_inner();
+    transport.subscribed.send_replace(true);
+
+    while let Some(message) = stream
+        .message()
+        .await
+        .context("failed to read custom message")?
+    {
+        let Some(body) = message
+            .payload
+            .strip_prefix(&LSPS0_MESSAGE_TYPE.to_be_bytes()[..])
+        else {
+            continue;
+        };
+
+        match serde_json::from_slice(body) {
+            Ok(body) => transport.receive(&message.peer_id, body),
+            Err(e) => eprintln!("Ignoring malformed LSPS0 message: {:?}", e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(transport: &Lsps0Transport, id: &str, peer_id: &[u8]) -> oneshot::Receiver<Value> {
+        let (tx, rx) = oneshot::channel();
+        transport
+            .pending
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), (peer_id.to_vec(), tx));
+        rx
+    }
+
+    #[test]
+    fn resolves_a_request_only_from_its_peer_and_id() {
+        let transport = Lsps0Transport::new(Arc::default());
+        let mut first = pending(&transport, "aa", &[1]);
+        let mut second = pending(&transport, "bb", &[1]);
+
+        transport.resolve(&[2], json!({"jsonrpc": "2.0", "id": "aa", "result": {}}));
+        assert!(first.try_recv().is_err());
+
+        transport.resolve(&[1], json!({"jsonrpc": "2.0", "id": "cc", "result": {}}));
+        transport.resolve(&[1], json!({"jsonrpc": "2.0", "id": "bb", "result": 2}));
+        assert_eq!(second.try_recv().unwrap()["result"], 2);
+        assert!(first.try_recv().is_err());
+
+        transport.resolve(&[1], json!({"jsonrpc": "2.0", "id": "aa", "result": 1}));
+        assert_eq!(first.try_recv().unwrap()["result"], 1);
+        assert!(transport.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn emits_messages_that_are_not_responses() {
+        struct Recorder(Arc<Mutex<Vec<ClientEvent>>>);
+
+        impl crate::events::EventListener for Recorder {
+            fn on_event(&self, event: ClientEvent) {
+                self.0.lock().unwrap().push(event);
+            }
+        }
+
+        let events = Arc::new(EventDispatcher::default());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        events.subscribe(Box::new(Recorder(received.clone())));
+        let transport = Lsps0Transport::new(events);
+        let mut request = pending(&transport, "aa", &[1]);
+
+        transport.receive(
+            &[1],
+            json!({"jsonrpc": "2.0", "method": "lsps5.payment_incoming", "params": {"n": 1}}),
+        );
+
+        assert!(request.try_recv().is_err());
+        match received.lock().unwrap().as_slice() {
+            [ClientEvent::Lsps0Notification {
+                peer_id,
+                method,
+                params,
+            }] => {
+                assert_eq!(peer_id, "01");
+                assert_eq!(method, "lsps5.payment_incoming");
+                assert_eq!(params, r#"{"n":1}"#);
+            }
+            events => panic!("unexpected events: {:?}", events),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bip39::{Language, Mnemonic};
+
+/// Why a phrase would be rejected by `recover`, `register` and the signer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MnemonicValidation {
+    Valid,
+    /// BIP39 phrases have 12, 15, 18, 21 or 24 words.
+    BadWordCount {
+        word_count: u32,
+    },
+    UnknownWord {
+        index: u32,
+        word: String,
+    },
+    InvalidChecksum,
+    /// None of the words are from a supported word list.
+    UnsupportedLanguage,
+}
+
+/// Parses `phrase` the same way the client does before deriving the node
+/// secret, so a phrase reported as valid is one the signer accepts.
+pub(crate) fn validate(phrase: &str) -> MnemonicValidation {
+    match Mnemonic::from_str(phrase) {
+        Ok(_) => MnemonicValidation::Valid,
+        Err(bip39::Error::BadWordCount(word_count)) => MnemonicValidation::BadWordCount {
+            word_count: word_count as u32,
+        },
+        Err(bip39::Error::UnknownWord(index)) => {
+            let mut words = phrase.split_whitespace();
+            if words
+                .clone()
+                .all(|w| Language::English.find_word(w).is_none())
+            {
+                MnemonicValidation::UnsupportedLanguage
+            } else {
+                MnemonicValidation::UnknownWord {
+                    index: index as u32,
+                    word: words.nth(index).unwrap_or_default().to_string(),
+                }
+            }
+        }
+        Err(bip39::Error::InvalidChecksum) => MnemonicValidation::InvalidChecksum,
+        // The language couldn't be told apart from the words.
+        Err(_) => MnemonicValidation::UnsupportedLanguage,
+    }
+}
+
+pub(crate) fn generate(word_count: u32) -> anyhow::Result<String> {
+    Mnemonic::generate_in_with(
+        &mut rand::thread_rng(),
+        Language::English,
+        word_count as usize,
+    )
+    .map(|mnemonic| mnemonic.to_string())
+    .map_err(|_| anyhow!("word count must be 12, 15, 18, 21 or 24"))
+}
@@ -0,0 +1,36 @@
+use anyhow::Context;
+use gl_client::pb::cln;
+
+const DATASTORE_KEY: [&str; 2] = ["glalby", "node_version"];
+
+/// Stores `version` as the last CLN version this crate saw the node run and
+/// returns the previous one if it differs. Greenlight upgrades nodes between
+/// sessions, so the datastore is the only place the old version survives.
+pub(crate) async fn record(
+    mut node: gl_client::node::ClnClient,
+    version: &str,
+) -> anyhow::Result<Option<String>> {
+    let key: Vec<String> = DATASTORE_KEY.iter().map(|k| k.to_string()).collect();
+    let previous = node
+        .list_datastore(cln::ListdatastoreRequest { key: key.clone() })
+        .await
+        .context("failed to load node version")?
+        .into_inner()
+        .datastore
+        .into_iter()
+        .find_map(|entry| entry.string);
+
+    if previous.as_deref() == Some(version) {
+        return Ok(None);
+    }
+
+    node.datastore(cln::DatastoreRequest {
+        key,
+        string: Some(version.to_string()),
+        mode: Some(cln::datastore_request::DatastoreMode::CreateOrReplace as i32),
+        ..Default::default()
+    })
+    .await
+    .context("failed to store node version")?;
+    Ok(previous)
+}
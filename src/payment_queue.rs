@@ -0,0 +1,328 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+use crate::storage::Storage;
+
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+const NAMESPACE: &str = "payment_queue";
+const OUTBOX_KEY: &str = "outbox";
+
+/// A payment waiting in the outbox.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedPayment {
+    pub id: u64,
+    pub bolt11: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub queued_at: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Outbox {
+    next_id: u64,
+    payments: Vec<QueuedPayment>,
+}
+
+/// Payments to send once the node is reachable. The outbox is rewritten as a
+/// whole on every change, so a payment is only forgotten after its outcome
+/// has been reported.
+pub(crate) struct PaymentQueue {
+    backend: Backend,
+    outbox: Mutex<Outbox>,
+    wake: Notify,
+}
+
+enum Backend {
+    File(PathBuf),
+    Storage(Arc<dyn Storage>),
+}
+
+impl PaymentQueue {
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let outbox = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).context("failed to decode outbox")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Outbox::default(),
+            Err(e) => return Err(e).context("failed to read outbox"),
+        };
+
+        Ok(PaymentQueue {
+            backend: Backend::File(path),
+            outbox: Mutex::new(outbox),
+            wake: Notify::new(),
+        })
+    }
+
+    pub fn in_storage(storage: Arc<dyn Storage>) -> anyhow::Result<Self> {
+        let outbox = match storage.get(NAMESPACE.to_string(), OUTBOX_KEY.to_string()) {
+            Some(outbox) => serde_json::from_str(&outbox).context("failed to decode outbox")?,
+            None => Outbox::default(),
+        };
+
+        Ok(PaymentQueue {
+            backend: Backend::Storage(storage),
+            outbox: Mutex::new(outbox),
+            wake: Notify::new(),
+        })
+    }
+
+    pub fn enqueue(&self, bolt11: String, description: Option<String>) -> anyhow::Result<u64> {
+        let mut outbox = self.outbox.lock().unwrap();
+        let id = outbox.next_id;
+        outbox.next_id += 1;
+        outbox.payments.push(QueuedPayment {
+            id,
+            bolt11,
+            description,
+            queued_at: now(),
+        });
+        self.save(&outbox)?;
+        drop(outbox);
+
+        self.wake.notify_one();
+        Ok(id)
+    }
+
+    /// Makes the worker go through the outbox now instead of at its next
+    /// retry.
+    pub(crate) fn retry_now(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn payments(&self) -> Vec<QueuedPayment> {
+        self.outbox.lock().unwrap().payments.clone()
+    }
+
+    fn remove(&self, id: u64) -> anyhow::Result<()> {
+        let mut outbox = self.outbox.lock().unwrap();
+        outbox.payments.retain(|p| p.id != id);
+        self.save(&outbox)
+    }
+
+    fn save(&self, outbox: &Outbox) -> anyhow::Result<()> {
+        let encoded = serde_json::to_string(outbox).context("failed to encode outbox")?;
+        match &self.backend {
+            Backend::File(path) => {
+                let tmp = path.with_extension("tmp");
+                fs::write(&tmp, encoded).context("failed to write outbox")?;
+                fs::rename(&tmp, path).context("failed to replace outbox")
+            }
+            Backend::Storage(storage) => {
+                storage.put(NAMESPACE.to_string(), OUTBOX_KEY.to_string(), encoded);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Sends queued payments in order. While the node can't be reached or the
+/// wallet is `locked` the payments stay queued and are retried, as are
+/// payments the node still has HTLCs in flight for; once a payment has
+/// completed or failed, its outcome is final and reported as an event.
+pub(crate) async fn work(
+    queue: Arc<PaymentQueue>,
+    node: gl_client::node::ClnClient,
+    locked: Arc<AtomicBool>,
+    events: Arc<EventDispatcher>,
+) {
+    loop {
+        for payment in queue.payments() {
+            if locked.load(Ordering::SeqCst) || !reachable(node.clone()).await {
+                break;
+            }
+
+            let result = node
+                .clone()
+                .pay(cln::PayRequest {
+                    bolt11: payment.bolt11.clone(),
+                    description: payment.description.clone(),
+                    ..Default::default()
+                })
+                .await;
+
+            // Losing the connection mid-payment says nothing about its
+            // outcome. Retrying is safe, pay won't pay an invoice twice.
+            if result.is_err() && !reachable(node.clone()).await {
+                break;
+            }
+
+            let event = match result {
+                Ok(response) => ClientEvent::QueuedPaymentSucceeded {
+                    id: payment.id,
+                    bolt11: payment.bolt11.clone(),
+                    preimage: hex::encode(response.into_inner().payment_preimage),
+                },
+                Err(e) => match settle(node.clone(), &payment.bolt11).await {
+                    Ok(Settled::Succeeded { preimage }) => ClientEvent::QueuedPaymentSucceeded {
+                        id: payment.id,
+                        bolt11: payment.bolt11.clone(),
+                        preimage: hex::encode(preimage),
+                    },
+                    Ok(Settled::Pending) => continue,
+                    Ok(Settled::Failed) => ClientEvent::QueuedPaymentFailed {
+                        id: payment.id,
+                        bolt11: payment.bolt11.clone(),
+                        error: e.message().to_string(),
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to look up queued payment: {:?}", e);
+                        break;
+                    }
+                },
+            };
+
+            if let Err(e) = queue.remove(payment.id) {
+                eprintln!("Failed to remove payment from outbox: {:?}", e);
+            }
+            events.emit(event);
+        }
+
+        tokio::select! {
+            _ = queue.wake.notified() => {}
+            _ = time::sleep(RETRY_DELAY) => {}
+        }
+    }
+}
+
+enum Settled {
+    Succeeded { preimage: Vec<u8> },
+    Pending,
+    Failed,
+}
+
+/// A failed pay call doesn't mean the payment failed: the call may have
+/// timed out with HTLCs still in flight, or even after the payment
+/// completed. The node's record of the payment has the final word.
+async fn settle(mut node: gl_client::node::ClnClient, bolt11: &str) -> anyhow::Result<Settled> {
+    use cln::listpays_pays::ListpaysPaysStatus;
+
+    // An invoice that can't be decoded can't have been paid either.
+    let Some(payment_hash) = crate::bolt11::decode(bolt11)
+        .ok()
+        .and_then(|invoice| hex::decode(invoice.payment_hash).ok())
+    else {
+        return Ok(Settled::Failed);
+    };
+    let pays = node
+        .list_pays(cln::ListpaysRequest {
+            payment_hash: Some(payment_hash),
+            ..Default::default()
+        })
+        .await
+        .context("failed to list payments")?
+        .into_inner()
+        .pays;
+
+    if let Some(pay) = pays
+        .iter()
+        .find(|p| p.status == ListpaysPaysStatus::Complete as i32)
+    {
+        return Ok(Settled::Succeeded {
+            preimage: pay.preimage.clone().unwrap_or_default(),
+        });
+    }
+    Ok(
+        if pays
+            .iter()
+            .any(|p| p.status == ListpaysPaysStatus::Pending as i32)
+        {
+            Settled::Pending
+        } else {
+            Settled::Failed
+        },
+    )
+}
+
+async fn reachable(mut node: gl_client::node::ClnClient) -> bool {
+    node.getinfo(cln::GetinfoRequest::default()).await.is_ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn outbox_path() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "glalby-outbox-{}.json",
+            hex::encode(rand::random::<[u8; 8]>())
+        ))
+    }
+
+    fn bolt11s(queue: &PaymentQueue) -> Vec<String> {
+        queue.payments().into_iter().map(|p| p.bolt11).collect()
+    }
+
+    #[test]
+    fn file_outbox_survives_a_reopen() {
+        let path = outbox_path();
+        {
+            let queue = PaymentQueue::open(path.clone()).unwrap();
+            queue.enqueue(String::from("lnbc1first"), None).unwrap();
+            let second = queue
+                .enqueue(String::from("lnbc1second"), Some(String::from("coffee")))
+                .unwrap();
+            queue.enqueue(String::from("lnbc1third"), None).unwrap();
+            queue.remove(second).unwrap();
+        }
+
+        let queue = PaymentQueue::open(path.clone()).unwrap();
+        assert_eq!(bolt11s(&queue), vec!["lnbc1first", "lnbc1third"]);
+        assert_eq!(queue.enqueue(String::from("lnbc1fourth"), None).unwrap(), 3);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn storage_outbox_survives_a_reopen() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        {
+            let queue = PaymentQueue::in_storage(storage.clone()).unwrap();
+            queue
+                .enqueue(String::from("lnbc1first"), Some(String::from("coffee")))
+                .unwrap();
+            queue.enqueue(String::from("lnbc1second"), None).unwrap();
+        }
+
+        let queue = PaymentQueue::in_storage(storage).unwrap();
+        assert_eq!(bolt11s(&queue), vec!["lnbc1first", "lnbc1second"]);
+        assert_eq!(queue.payments()[0].description.as_deref(), Some("coffee"));
+        assert_eq!(queue.enqueue(String::from("lnbc1third"), None).unwrap(), 2);
+    }
+
+    #[test]
+    fn save_replaces_the_outbox_through_a_temporary_file() {
+        let path = outbox_path();
+        let tmp = path.with_extension("tmp");
+        // Left over from a crash between writing and renaming.
+        fs::write(&tmp, "{\"next_id\":").unwrap();
+
+        let queue = PaymentQueue::open(path.clone()).unwrap();
+        queue.enqueue(String::from("lnbc1first"), None).unwrap();
+
+        assert!(!tmp.exists());
+        let outbox: Outbox = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(outbox.next_id, 1);
+        assert_eq!(outbox.payments.len(), 1);
+        assert_eq!(outbox.payments[0].bolt11, "lnbc1first");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+/// Steps of a credential recovery, reported as each one starts. gl-client
+/// authenticates the signer and returns the credentials in a single
+/// scheduler call, so both happen during `Recovering`.
+#[derive(Clone, Debug)]
+pub enum RecoverStage {
+    DerivingKeys,
+    /// The node id is known from here on, before recovery has finished.
+    ConnectingScheduler {
+        node_id: String,
+    },
+    Recovering,
+    Completed,
+}
+
+/// Host-implemented receiver for recovery progress.
+pub trait RecoverProgress: Send + Sync {
+    fn on_stage(&self, stage: RecoverStage);
+}
+
+/// Lets a host abort a long running call from another thread.
+#[derive(Default)]
+pub struct CancellationToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called.
+    pub(crate) async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use gl_client::pb::cln;
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+use crate::greenlight_alby_client::ListPaymentsPayment;
+
+const PAGE_SIZE: u32 = 100;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A BOLT12 refund: an invoice_request we handed out, and the payment the
+/// node made once the holder answered it with an invoice. CLN records that
+/// payment under the invoice request's label, which is what links the two;
+/// hosts put a reference to the original payment in that label.
+#[derive(Clone, Debug)]
+pub struct Refund {
+    pub invreq_id: String,
+    pub label: Option<String>,
+    pub bolt12: String,
+    pub active: bool,
+    pub payment: Option<ListPaymentsPayment>,
+}
+
+/// Lists every invoice request with the payment that settled it, if any.
+pub(crate) async fn list(mut node: gl_client::node::ClnClient) -> anyhow::Result<Vec<Refund>> {
+    let invoice_requests = node
+        .list_invoice_requests(cln::ListinvoicerequestsRequest::default())
+        .await
+        .context("failed to list invoice requests")?
+        .into_inner()
+        .invoicerequests;
+
+    let mut payments: HashMap<String, ListPaymentsPayment> = node
+        .list_pays(cln::ListpaysRequest {
+            status: Some(cln::listpays_request::ListpaysStatus::Complete as i32),
+            ..Default::default()
+        })
+        .await
+        .context("failed to list payments")?
+        .into_inner()
+        .pays
+        .into_iter()
+        .filter_map(|p| Some((p.label.clone()?, ListPaymentsPayment::from(p))))
+        .collect();
+
+    Ok(invoice_requests
+        .into_iter()
+        .map(|invreq| Refund {
+            invreq_id: hex::encode(invreq.invreq_id),
+            payment: invreq
+                .used
+                .then(|| invreq.label.as_ref().and_then(|l| payments.remove(l)))
+                .flatten(),
+            label: invreq.label,
+            bolt12: invreq.bolt12,
+            active: invreq.active,
+        })
+        .collect())
+}
+
+/// Emits `RefundPaid` for every invoice request the node pays after the task
+/// started, following the `updated` index of its payment parts. Refunds paid
+/// earlier are left to `list_refunds`.
+pub(crate) async fn watch(node: gl_client::node::ClnClient, events: Arc<EventDispatcher>) {
+    let mut next = loop {
+        match wait_updated(node.clone(), 0).await {
+            Ok(current) => break current + 1,
+            Err(e) => {
+                eprintln!("Refund watcher failed to get the payment index: {:?}", e);
+                time::sleep(RETRY_DELAY).await;
+            }
+        }
+    };
+
+    let mut reported = HashSet::new();
+    loop {
+        match poll(node.clone(), next, &mut reported, &events).await {
+            Ok(after) => next = after,
+            Err(e) => {
+                eprintln!("Refund watcher failed to list payments: {:?}", e);
+                time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        }
+
+        // Blocks until a payment part reaches the `next` updated index.
+        if let Err(e) = wait_updated(node.clone(), next).await {
+            eprintln!("Refund watcher failed to wait for payments: {:?}", e);
+            time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// The `updated` index of payment parts once it reaches `nextvalue`; with 0
+/// the current value.
+async fn wait_updated(mut node: gl_client::node::ClnClient, nextvalue: u64) -> anyhow::Result<u64> {
+    Ok(node
+        .wait(cln::WaitRequest {
+            subsystem: cln::wait_request::WaitSubsystem::Sendpays as i32,
+            indexname: cln::wait_request::WaitIndexname::Updated as i32,
+            nextvalue,
+        })
+        .await
+        .context("failed to wait for payments")?
+        .into_inner()
+        .updated
+        .unwrap_or(0))
+}
+
+/// Emits events for the refunds among the payments completed from the
+/// updated index `start` on and returns the index to continue from.
+async fn poll(
+    mut node: gl_client::node::ClnClient,
+    start: u64,
+    reported: &mut HashSet<Vec<u8>>,
+    events: &EventDispatcher,
+) -> anyhow::Result<u64> {
+    use cln::listsendpays_payments::ListsendpaysPaymentsStatus;
+    use cln::listsendpays_request::ListsendpaysIndex;
+
+    let mut next = start;
+    // A payment split into several parts completes once per part.
+    let mut completed: HashMap<Vec<u8>, String> = HashMap::new();
+    loop {
+        let page_start = next;
+        let parts = node
+            .list_send_pays(cln::ListsendpaysRequest {
+                index: Some(ListsendpaysIndex::Updated as i32),
+                start: Some(next),
+                limit: Some(PAGE_SIZE),
+                ..Default::default()
+            })
+            .await
+            .context("failed to list payment parts")?
+            .into_inner()
+            .payments;
+
+        let count = parts.len();
+        for part in parts {
+            let Some(updated_index) = part.updated_index else {
+                continue;
+            };
+            next = next.max(updated_index + 1);
+
+            // Refunds are paid under the invoice request's label.
+            if part.status == ListsendpaysPaymentsStatus::Complete as i32
+                && !reported.contains(&part.payment_hash)
+            {
+                if let Some(label) = part.label {
+                    completed.insert(part.payment_hash, label);
+                }
+            }
+        }
+
+        if count < PAGE_SIZE as usize || next == page_start {
+            break;
+        }
+    }
+    if completed.is_empty() {
+        return Ok(next);
+    }
+
+    let invoice_requests = node
+        .list_invoice_requests(cln::ListinvoicerequestsRequest::default())
+        .await
+        .context("failed to list invoice requests")?
+        .into_inner()
+        .invoicerequests;
+
+    for (payment_hash, label) in completed {
+        let Some(invreq) = invoice_requests
+            .iter()
+            .find(|r| r.label.as_ref() == Some(&label))
+        else {
+            continue;
+        };
+
+        let payment = node
+            .list_pays(cln::ListpaysRequest {
+                payment_hash: Some(payment_hash.clone()),
+                status: Some(cln::listpays_request::ListpaysStatus::Complete as i32),
+                ..Default::default()
+            })
+            .await
+            .context("failed to list payments")?
+            .into_inner()
+            .pays
+            .into_iter()
+            .next();
+        if let Some(payment) = payment {
+            reported.insert(payment_hash);
+            events.emit(ClientEvent::RefundPaid {
+                invreq_id: hex::encode(&invreq.invreq_id),
+                label: Some(label),
+                payment: ListPaymentsPayment::from(payment),
+            });
+        }
+    }
+
+    Ok(next)
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+const SESSION_NAMESPACE: &str = "session";
+
+/// How long a cached node address is tried before going through the
+/// scheduler again. Greenlight keeps a node on the same host while it runs,
+/// but reschedules it after it has been idle for a while.
+pub(crate) const DEFAULT_MAX_AGE_SECS: u64 = 3600;
+
+struct SessionCache {
+    storage: Arc<dyn Storage>,
+    max_age_secs: u64,
+}
+
+/// Where the last session's node was reached, if the host enabled the cache.
+static SESSION_CACHE: Lazy<RwLock<Option<SessionCache>>> = Lazy::new(|| RwLock::new(None));
+
+/// The node address reached at `saved_at` (unix seconds).
+#[derive(Serialize, Deserialize)]
+struct Session {
+    grpc_uri: String,
+    saved_at: u64,
+}
+
+impl SessionCache {
+    /// The cached address of the node `node_id`, unless it was older than
+    /// the maximum age at `now`.
+    fn load(&self, node_id: &[u8], now: u64) -> Option<String> {
+        let session = self
+            .storage
+            .get(SESSION_NAMESPACE.to_string(), hex::encode(node_id))?;
+        let session: Session = serde_json::from_str(&session).ok()?;
+        (now.saturating_sub(session.saved_at) < self.max_age_secs).then_some(session.grpc_uri)
+    }
+
+    fn save(&self, node_id: &[u8], grpc_uri: String, now: u64) {
+        let session = Session {
+            grpc_uri,
+            saved_at: now,
+        };
+        match serde_json::to_string(&session) {
+            Ok(session) => {
+                self.storage
+                    .put(SESSION_NAMESPACE.to_string(), hex::encode(node_id), session)
+            }
+            Err(e) => eprintln!("Failed to encode session: {:?}", e),
+        }
+    }
+
+    fn invalidate(&self, node_id: Option<&[u8]>) {
+        let keys = match node_id {
+            Some(node_id) => vec![hex::encode(node_id)],
+            None => self.storage.list(SESSION_NAMESPACE.to_string()),
+        };
+        for key in keys {
+            self.storage.delete(SESSION_NAMESPACE.to_string(), key);
+        }
+    }
+}
+
+/// Keeps node addresses in `storage` from now on, or turns the cache off
+/// when `None`. Applies to connections made after the call.
+pub(crate) fn configure(storage: Option<Arc<dyn Storage>>, max_age_secs: u64) {
+    *SESSION_CACHE.write().unwrap() = storage.map(|storage| SessionCache {
+        storage,
+        max_age_secs,
+    });
+}
+
+/// The cached address of the node `node_id`, unless it is older than the
+/// configured maximum age.
+pub(crate) fn load(node_id: &[u8]) -> Option<String> {
+    SESSION_CACHE.read().unwrap().as_ref()?.load(node_id, now())
+}
+
+pub(crate) fn save(node_id: &[u8], grpc_uri: String) {
+    if let Some(cache) = SESSION_CACHE.read().unwrap().as_ref() {
+        cache.save(node_id, grpc_uri, now());
+    }
+}
+
+/// Drops the cached address of `node_id`, or of every node when `None`.
+pub(crate) fn invalidate(node_id: Option<&[u8]>) {
+    if let Some(cache) = SESSION_CACHE.read().unwrap().as_ref() {
+        cache.invalidate(node_id);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn cache() -> SessionCache {
+        SessionCache {
+            storage: Arc::new(MemoryStorage::default()),
+            max_age_secs: 60,
+        }
+    }
+
+    #[test]
+    fn loads_what_was_saved_until_it_expires() {
+        let cache = cache();
+        cache.save(&[1], String::from("https://node.example:443"), 1000);
+
+        assert_eq!(
+            cache.load(&[1], 1059).as_deref(),
+            Some("https://node.example:443")
+        );
+        assert_eq!(cache.load(&[1], 1060), None);
+        assert_eq!(cache.load(&[2], 1000), None);
+    }
+
+    #[test]
+    fn invalidates_one_node_or_all() {
+        let cache = cache();
+        cache.save(&[1], String::from("https://one.example:443"), 1000);
+        cache.save(&[2], String::from("https://two.example:443"), 1000);
+
+        cache.invalidate(Some(&[1]));
+        assert_eq!(cache.load(&[1], 1000), None);
+        assert!(cache.load(&[2], 1000).is_some());
+
+        cache.invalidate(None);
+        assert_eq!(cache.load(&[2], 1000), None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use gl_client::signer::Signer;
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::time;
+
+use crate::events::{ClientEvent, EventDispatcher};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_RESTARTS: u32 = 8;
+/// A signer that stayed up this long is considered healthy again, so a later
+/// crash starts over with a fresh restart budget.
+const HEALTHY_AFTER: Duration = Duration::from_secs(300);
+
+/// Runs the signer until `shutdown` fires, restarting it with exponential
+/// backoff whenever it stops on its own. Without a signer the node can't sign
+/// anything, so once the restart budget is used up a `SignerFailed` event is
+/// emitted instead of carrying on silently.
+pub(crate) async fn supervise(
+    signer: Signer,
+    mut shutdown: Receiver<()>,
+    events: Arc<EventDispatcher>,
+) {
+    let mut attempt = 0;
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        println!("Run forever started");
+        let started = Instant::now();
+        let (stop, stop_rx) = mpsc::channel(1);
+        let run = signer.run_forever(stop_rx);
+        tokio::pin!(run);
+
+        let error = tokio::select! {
+            result = &mut run => match result {
+                Ok(()) => String::from("signer stopped unexpectedly"),
+                Err(e) => format!("{:?}", e),
+            },
+            _ = shutdown.recv() => {
+                // Let the signer wind down its streams before returning.
+                let _ = stop.send(()).await;
+                if let Err(e) = run.await {
+                    eprintln!("Run forever error: {:?}", e);
+                }
+                println!("Run forever finished");
+                return;
+            }
+        };
+        eprintln!("Run forever error: {}", error);
+
+        if started.elapsed() >= HEALTHY_AFTER {
+            attempt = 0;
+            backoff = INITIAL_BACKOFF;
+        }
+        if attempt == MAX_RESTARTS {
+            events.emit(ClientEvent::SignerFailed { error });
+            return;
+        }
+        attempt += 1;
+
+        events.emit(ClientEvent::SignerRestarted {
+            attempt,
+            delay_ms: backoff.as_millis() as u64,
+            error,
+        });
+
+        tokio::select! {
+            _ = time::sleep(backoff) => {}
+            _ = shutdown.recv() => return,
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::greenlight_alby_client::{GetInfoResponse, ListPeerChannelsChannel};
+
+/// Host-provided persistence for the last known node snapshot. The snapshot
+/// is an opaque string to the host.
+pub trait SnapshotStore: Send + Sync {
+    fn save(&self, snapshot: String);
+    fn load(&self) -> Option<String>;
+}
+
+/// What the node looked like when `saved_at` (unix seconds), for showing
+/// something while a fresh client is still being scheduled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedSnapshot {
+    pub info: GetInfoResponse,
+    pub channels: Vec<ListPeerChannelsChannel>,
+    pub saved_at: u64,
+}
+
+pub(crate) fn save(
+    store: &dyn SnapshotStore,
+    info: &GetInfoResponse,
+    channels: &[ListPeerChannelsChannel],
+) {
+    let snapshot = CachedSnapshot {
+        info: info.clone(),
+        channels: channels.to_vec(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    };
+
+    match serde_json::to_string(&snapshot) {
+        Ok(snapshot) => store.save(snapshot),
+        Err(e) => eprintln!("Failed to encode snapshot: {:?}", e),
+    }
+}
+
+/// A snapshot that can't be decoded, e.g. one written by an older version,
+/// is treated as missing.
+pub(crate) fn load(store: &dyn SnapshotStore) -> Option<CachedSnapshot> {
+    serde_json::from_str(&store.load()?).ok()
+}
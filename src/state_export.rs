@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Context};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const FORMAT_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Everything this crate keeps about a wallet besides the mnemonic.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct WalletState {
+    pub credentials: String,
+    pub datastore: Vec<DatastoreEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DatastoreEntry {
+    pub key: Vec<String>,
+    pub string: Option<String>,
+    pub hex: Option<String>,
+}
+
+/// Derives the export key from the node secret, so an export can only be
+/// opened by someone who also holds the mnemonic.
+pub(crate) fn derive_key(secret: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"glalby/state-export");
+    hasher.update(secret);
+    hasher.finalize().into()
+}
+
+pub(crate) fn seal(key: &[u8; 32], state: &WalletState) -> anyhow::Result<String> {
+    let plaintext = serde_json::to_vec(state).context("failed to encode wallet state")?;
+
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow!("failed to encrypt wallet state"))?;
+
+    let mut blob = vec![FORMAT_VERSION];
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(hex::encode(blob))
+}
+
+pub(crate) fn open(key: &[u8; 32], blob: &str) -> anyhow::Result<WalletState> {
+    let blob = hex::decode(blob).context("wallet state contains invalid hex value")?;
+    let (version, rest) = blob
+        .split_first()
+        .ok_or_else(|| anyhow!("wallet state is empty"))?;
+    if *version != FORMAT_VERSION {
+        return Err(anyhow!("unsupported wallet state version {}", version));
+    }
+    if rest.len() < NONCE_LEN {
+        return Err(anyhow!("wallet state is truncated"));
+    }
+
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("failed to decrypt wallet state, wrong mnemonic?"))?;
+
+    serde_json::from_slice(&plaintext).context("failed to decode wallet state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> WalletState {
+        WalletState {
+            credentials: String::from("creds"),
+            datastore: vec![DatastoreEntry {
+                key: vec![String::from("glalby"), String::from("refunds")],
+                string: Some(String::from("{}")),
+                hex: None,
+            }],
+        }
+    }
+
+    fn open_error(key: &[u8; 32], blob: &str) -> String {
+        match open(key, blob) {
+            Ok(_) => panic!("opened a bad blob"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn opens_what_was_sealed() {
+        let key = derive_key(b"secret");
+        let opened = open(&key, &seal(&key, &state()).unwrap()).unwrap();
+
+        assert_eq!(opened.credentials, "creds");
+        assert_eq!(opened.datastore.len(), 1);
+        assert_eq!(opened.datastore[0].key, vec!["glalby", "refunds"]);
+        assert_eq!(opened.datastore[0].string.as_deref(), Some("{}"));
+        assert_eq!(opened.datastore[0].hex, None);
+    }
+
+    #[test]
+    fn rejects_another_key() {
+        let blob = seal(&derive_key(b"secret"), &state()).unwrap();
+        assert!(open_error(&derive_key(b"other"), &blob).contains("failed to decrypt"));
+    }
+
+    #[test]
+    fn rejects_truncated_blobs() {
+        let key = derive_key(b"secret");
+        let blob = seal(&key, &state()).unwrap();
+
+        assert!(open_error(&key, "").contains("empty"));
+        assert!(open_error(&key, &blob[..2 * NONCE_LEN]).contains("truncated"));
+        assert!(open_error(&key, &blob[..blob.len() - 2]).contains("failed to decrypt"));
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let key = derive_key(b"secret");
+        let mut blob = hex::decode(seal(&key, &state()).unwrap()).unwrap();
+        blob[0] = FORMAT_VERSION + 1;
+
+        assert_eq!(
+            open_error(&key, &hex::encode(blob)),
+            format!("unsupported wallet state version {}", FORMAT_VERSION + 1)
+        );
+    }
+}
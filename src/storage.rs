@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::snapshot_cache::SnapshotStore;
+
+/// Host-provided key-value persistence shared by the optional storage
+/// features (snapshot cache, journal, payment queue). Each feature keeps its
+/// entries in its own namespace; values are opaque strings to the host.
+pub trait Storage: Send + Sync {
+    fn get(&self, namespace: String, key: String) -> Option<String>;
+    fn put(&self, namespace: String, key: String, value: String);
+    fn delete(&self, namespace: String, key: String);
+    /// The keys in `namespace`, in any order.
+    fn list(&self, namespace: String) -> Vec<String>;
+}
+
+/// Keeps everything in memory. This is what the client uses until the host
+/// sets a storage, so nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<(String, String), String>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, namespace: String, key: String) -> Option<String> {
+        self.entries.lock().unwrap().get(&(namespace, key)).cloned()
+    }
+
+    fn put(&self, namespace: String, key: String, value: String) {
+        self.entries.lock().unwrap().insert((namespace, key), value);
+    }
+
+    fn delete(&self, namespace: String, key: String) {
+        self.entries.lock().unwrap().remove(&(namespace, key));
+    }
+
+    fn list(&self, namespace: String) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(ns, _)| *ns == namespace)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+}
+
+const SNAPSHOT_NAMESPACE: &str = "snapshot";
+const SNAPSHOT_KEY: &str = "latest";
+
+/// Keeps the snapshot cache in a `Storage`.
+pub(crate) struct StorageSnapshotStore(pub Arc<dyn Storage>);
+
+impl SnapshotStore for StorageSnapshotStore {
+    fn save(&self, snapshot: String) {
+        self.0.put(
+            SNAPSHOT_NAMESPACE.to_string(),
+            SNAPSHOT_KEY.to_string(),
+            snapshot,
+        )
+    }
+
+    fn load(&self) -> Option<String> {
+        self.0
+            .get(SNAPSHOT_NAMESPACE.to_string(), SNAPSHOT_KEY.to_string())
+    }
+}
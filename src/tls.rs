@@ -0,0 +1,37 @@
+use std::sync::RwLock;
+
+use anyhow::anyhow;
+use gl_client::credentials::{Device, Nobody};
+use once_cell::sync::Lazy;
+
+/// CA root used instead of the one bundled with gl-client, if set.
+static CA_CERTIFICATE: Lazy<RwLock<Option<Vec<u8>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Replaces the CA root trusted for the scheduler and node connections with
+/// `ca_certificate` (PEM). Only certificates issued by that root are accepted
+/// afterwards, which pins connections to it. Passing `None` restores the
+/// default Greenlight root. Applies to connections made after the call.
+pub(crate) fn set_ca_certificate(ca_certificate: Option<String>) -> anyhow::Result<()> {
+    if let Some(pem) = &ca_certificate {
+        if !pem.contains("-----BEGIN CERTIFICATE-----") {
+            return Err(anyhow!("CA certificate is not a PEM encoded certificate"));
+        }
+    }
+
+    *CA_CERTIFICATE.write().unwrap() = ca_certificate.map(String::into_bytes);
+    Ok(())
+}
+
+pub(crate) fn nobody() -> Nobody {
+    match CA_CERTIFICATE.read().unwrap().clone() {
+        Some(ca) => Nobody::new().with_ca(ca),
+        None => Nobody::new(),
+    }
+}
+
+pub(crate) fn device(device: Device) -> Device {
+    match CA_CERTIFICATE.read().unwrap().clone() {
+        Some(ca) => device.with_ca(ca),
+        None => device,
+    }
+}
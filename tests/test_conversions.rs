@@ -0,0 +1,1385 @@
+//! Tests for the mapping between the binding types and the CLN gRPC types.
+//!
+//! Responses are compared against golden files in `tests/golden`. After an
+//! intentional change to a mapping, regenerate them with
+//! `UPDATE_GOLDEN=1 cargo test --test test_conversions` and review the diff.
+
+use std::fs;
+use std::path::PathBuf;
+
+use gl_client::pb::cln;
+use gl_client::signer::model::greenlight::scheduler;
+use glalby_bindings::*;
+use proptest::prelude::*;
+
+fn assert_golden(name: &str, value: &impl std::fmt::Debug) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", name));
+    let actual = format!("{:#?}\n", value);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).expect("failed to write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read {}: {} (run with UPDATE_GOLDEN=1 to create it)",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(actual, expected, "{} differs from its golden file", name);
+}
+
+fn bytes(byte: u8, len: usize) -> Vec<u8> {
+    vec![byte; len]
+}
+
+fn hex_of(byte: u8, len: usize) -> String {
+    hex::encode(bytes(byte, len))
+}
+
+fn amount(msat: u64) -> Option<cln::Amount> {
+    Some(cln::Amount { msat })
+}
+
+fn is_invalid_argument<T, E: std::fmt::Display>(result: std::result::Result<T, E>) -> bool {
+    match result {
+        Ok(_) => false,
+        Err(e) => e.to_string().starts_with("invalid argument"),
+    }
+}
+
+// Responses
+
+#[test]
+fn credentials_from_scheduler() {
+    let registration = scheduler::RegistrationResponse {
+        creds: bytes(0x01, 4),
+        ..Default::default()
+    };
+    assert_eq!(
+        GreenlightCredentials::from(registration).gl_creds,
+        "01010101"
+    );
+
+    let recovery = scheduler::RecoveryResponse {
+        creds: bytes(0x02, 4),
+        ..Default::default()
+    };
+    assert_eq!(GreenlightCredentials::from(recovery).gl_creds, "02020202");
+}
+
+#[test]
+fn get_info_response() {
+    let info = cln::GetinfoResponse {
+        id: bytes(0x02, 33),
+        alias: Some(String::from("alby")),
+        color: vec![0xff, 0xc0, 0x00],
+        network: String::from("bitcoin"),
+        blockheight: 800_000,
+        ..Default::default()
+    };
+    assert_golden("get_info_response", &GetInfoResponse::from(info));
+}
+
+#[test]
+fn get_info_response_without_alias() {
+    let info = cln::GetinfoResponse::default();
+    let info = GetInfoResponse::from(info);
+    assert_eq!(info.alias, "");
+    assert_eq!(info.color, "#");
+}
+
+#[test]
+fn make_invoice_response() {
+    let invoice = cln::InvoiceResponse {
+        bolt11: String::from("lnbc10u1test"),
+        payment_hash: bytes(0xaa, 32),
+        payment_secret: bytes(0xbb, 32),
+        expires_at: 1_700_003_600,
+        created_index: Some(7),
+        warning_capacity: Some(String::from("insufficient incoming capacity")),
+        ..Default::default()
+    };
+    assert_golden("make_invoice_response", &MakeInvoiceResponse::from(invoice));
+}
+
+#[test]
+fn pay_response() {
+    let pay = cln::PayResponse {
+        payment_preimage: bytes(0xcc, 32),
+        payment_hash: bytes(0xaa, 32),
+        destination: Some(bytes(0x03, 33)),
+        created_at: 1_700_000_000.25,
+        parts: 2,
+        amount_msat: amount(1_000_000),
+        amount_sent_msat: amount(1_000_100),
+        status: cln::pay_response::PayStatus::Complete as i32,
+        ..Default::default()
+    };
+    assert_golden("pay_response", &PayResponse::from(pay));
+}
+
+#[test]
+fn key_send_response() {
+    let pay = cln::KeysendResponse {
+        payment_preimage: bytes(0xcc, 32),
+        ..Default::default()
+    };
+    assert_golden("key_send_response", &KeySendResponse::from(pay));
+}
+
+#[test]
+fn list_funds_response() {
+    let response = cln::ListfundsResponse {
+        outputs: vec![cln::ListfundsOutputs {
+            txid: bytes(0x11, 32),
+            output: 1,
+            amount_msat: amount(100_000_000),
+            scriptpubkey: bytes(0x51, 4),
+            address: Some(String::from("bc1qtest")),
+            status: 1,
+            blockheight: Some(799_990),
+            ..Default::default()
+        }],
+        channels: vec![cln::ListfundsChannels {
+            peer_id: bytes(0x03, 33),
+            our_amount_msat: amount(40_000_000),
+            amount_msat: amount(50_000_000),
+            funding_txid: bytes(0x22, 32),
+            connected: true,
+            state: 2,
+            channel_id: Some(bytes(0x33, 32)),
+            short_channel_id: Some(String::from("799990x1x0")),
+            ..Default::default()
+        }],
+    };
+    assert_golden("list_funds_response", &ListFundsResponse::from(response));
+}
+
+#[test]
+fn connect_peer_response() {
+    let response = cln::ConnectResponse {
+        id: bytes(0x03, 33),
+        ..Default::default()
+    };
+    assert_golden(
+        "connect_peer_response",
+        &ConnectPeerResponse::from(response),
+    );
+}
+
+#[test]
+fn fund_channel_response() {
+    let response = cln::FundchannelResponse {
+        txid: bytes(0x22, 32),
+        ..Default::default()
+    };
+    assert_golden(
+        "fund_channel_response",
+        &FundChannelResponse::from(response),
+    );
+}
+
+#[test]
+fn new_address_response() {
+    let response = cln::NewaddrResponse {
+        bech32: Some(String::from("bc1qtest")),
+        ..Default::default()
+    };
+    assert_golden("new_address_response", &NewAddressResponse::from(response));
+}
+
+#[test]
+fn list_invoices_response() {
+    let response = cln::ListinvoicesResponse {
+        invoices: vec![
+            cln::ListinvoicesInvoices {
+                label: String::from("paid"),
+                description: Some(String::from("coffee")),
+                payment_hash: bytes(0xaa, 32),
+                status: 1,
+                expires_at: 1_700_003_600,
+                amount_msat: amount(1_000_000),
+                bolt11: Some(String::from("lnbc10u1test")),
+                created_index: Some(1),
+                updated_index: Some(2),
+                pay_index: Some(1),
+                amount_received_msat: amount(1_000_000),
+                paid_at: Some(1_700_000_100),
+                paid_outpoint: Some(cln::ListinvoicesInvoicesPaidOutpoint {
+                    txid: Some(bytes(0x44, 32)),
+                    outnum: Some(3),
+                }),
+                payment_preimage: Some(bytes(0xcc, 32)),
+                ..Default::default()
+            },
+            cln::ListinvoicesInvoices {
+                label: String::from("offer"),
+                payment_hash: bytes(0xab, 32),
+                bolt12: Some(String::from("lni1test")),
+                local_offer_id: Some(bytes(0x55, 32)),
+                invreq_payer_note: Some(String::from("thanks")),
+                ..Default::default()
+            },
+        ],
+    };
+    assert_golden(
+        "list_invoices_response",
+        &ListInvoicesResponse::from(response),
+    );
+}
+
+#[test]
+fn list_payments_response() {
+    let response = cln::ListpaysResponse {
+        pays: vec![cln::ListpaysPays {
+            payment_hash: bytes(0xaa, 32),
+            status: 1,
+            destination: Some(bytes(0x03, 33)),
+            created_at: 1_700_000_000,
+            completed_at: Some(1_700_000_005),
+            label: Some(String::from("rent")),
+            bolt11: Some(String::from("lnbc10u1test")),
+            amount_msat: amount(1_000_000),
+            amount_sent_msat: amount(1_000_100),
+            preimage: Some(bytes(0xcc, 32)),
+            number_of_parts: Some(2),
+            erroronion: Some(bytes(0xee, 2)),
+            ..Default::default()
+        }],
+    };
+    assert_golden(
+        "list_payments_response",
+        &ListPaymentsResponse::from(response),
+    );
+}
+
+#[test]
+fn sign_message_response() {
+    let response = cln::SignmessageResponse {
+        signature: vec![0x01, 0x02],
+        zbase: String::from("d7test"),
+        ..Default::default()
+    };
+    assert_golden(
+        "sign_message_response",
+        &SignMessageResponse::from(response),
+    );
+}
+
+#[test]
+fn withdraw_response() {
+    let response = cln::WithdrawResponse {
+        tx: bytes(0x02, 4),
+        txid: bytes(0x22, 32),
+        ..Default::default()
+    };
+    assert_golden("withdraw_response", &WithdrawResponse::from(response));
+}
+
+#[test]
+fn close_response() {
+    let response = cln::CloseResponse {
+        tx: Some(bytes(0x02, 4)),
+        txid: Some(bytes(0x22, 32)),
+        ..Default::default()
+    };
+    assert_golden("close_response", &CloseResponse::from(response));
+}
+
+#[test]
+fn list_offers_response() {
+    let response = cln::ListoffersResponse {
+        offers: vec![cln::ListoffersOffers {
+            offer_id: bytes(0x55, 32),
+            active: true,
+            single_use: false,
+            bolt12: String::from("lno1test"),
+            used: true,
+            label: Some(String::from("tips")),
+            ..Default::default()
+        }],
+    };
+    assert_golden("list_offers_response", &ListOffersResponse::from(response));
+}
+
+#[test]
+fn create_offer_response() {
+    let response = cln::OfferResponse {
+        offer_id: bytes(0x55, 32),
+        active: true,
+        bolt12: String::from("lno1test"),
+        created: true,
+        ..Default::default()
+    };
+    assert_golden(
+        "create_offer_response",
+        &CreateOfferResponse::from(response),
+    );
+}
+
+#[test]
+fn disable_offer_response() {
+    let response = cln::DisableofferResponse {
+        offer_id: bytes(0x55, 32),
+        active: false,
+        bolt12: String::from("lno1test"),
+        ..Default::default()
+    };
+    assert_golden(
+        "disable_offer_response",
+        &DisableOfferResponse::from(response),
+    );
+}
+
+#[test]
+fn create_invoice_request_response() {
+    let response = cln::InvoicerequestResponse {
+        invreq_id: bytes(0x66, 32),
+        active: true,
+        single_use: true,
+        bolt12: String::from("lnr1test"),
+        ..Default::default()
+    };
+    assert_golden(
+        "create_invoice_request_response",
+        &CreateInvoiceRequestResponse::from(response),
+    );
+}
+
+#[test]
+fn list_invoice_requests_response() {
+    let response = cln::ListinvoicerequestsResponse {
+        invoicerequests: vec![cln::ListinvoicerequestsInvoicerequests {
+            invreq_id: bytes(0x66, 32),
+            active: true,
+            bolt12: String::from("lnr1test"),
+            label: Some(String::from("withdrawal")),
+            ..Default::default()
+        }],
+    };
+    assert_golden(
+        "list_invoice_requests_response",
+        &ListInvoiceRequestsResponse::from(response),
+    );
+}
+
+#[test]
+fn disable_invoice_request_response() {
+    let response = cln::DisableinvoicerequestResponse {
+        invreq_id: bytes(0x66, 32),
+        used: true,
+        bolt12: String::from("lnr1test"),
+        ..Default::default()
+    };
+    assert_golden(
+        "disable_invoice_request_response",
+        &DisableInvoiceRequestResponse::from(response),
+    );
+}
+
+#[test]
+fn funder_update_response() {
+    let response = cln::FunderupdateResponse {
+        summary: String::from("match (100%)"),
+        policy: 0,
+        policy_mod: 100,
+        leases_only: true,
+        min_their_funding_msat: amount(10_000_000),
+        reserve_tank_msat: amount(0),
+        fuzz_percent: 0,
+        fund_probability: 100,
+        lease_fee_base_msat: amount(2_000_000),
+        lease_fee_basis: Some(65),
+        compact_lease: Some(bytes(0x77, 4)),
+        ..Default::default()
+    };
+    assert_golden(
+        "funder_update_response",
+        &FunderUpdateResponse::from(response),
+    );
+}
+
+#[test]
+fn list_peer_channels_channel() {
+    let channel = cln::ListpeerchannelsChannels {
+        peer_id: Some(bytes(0x03, 33)),
+        peer_connected: Some(true),
+        state: Some(2),
+        short_channel_id: Some(String::from("799990x1x0")),
+        channel_id: Some(bytes(0x33, 32)),
+        funding_txid: Some(bytes(0x22, 32)),
+        to_us_msat: amount(40_000_000),
+        total_msat: amount(50_000_000),
+        spendable_msat: amount(39_000_000),
+        receivable_msat: amount(9_000_000),
+        opener: Some(cln::ChannelSide::Remote as i32),
+        private: Some(false),
+        features: vec![String::from("option_anchors_zero_fee_htlc_tx")],
+        fee_base_msat: amount(1_000),
+        fee_proportional_millionths: Some(10),
+        our_reserve_msat: amount(500_000),
+        their_reserve_msat: amount(500_000),
+        htlcs: vec![Default::default(), Default::default()],
+        max_accepted_htlcs: Some(483),
+        ..Default::default()
+    };
+    assert_golden(
+        "list_peer_channels_channel",
+        &ListPeerChannelsChannel::from(channel),
+    );
+}
+
+#[test]
+fn decode_bolt11_response() {
+    let decoded = cln::DecodeResponse {
+        item_type: cln::decode_response::DecodeType::Bolt11Invoice as i32,
+        valid: true,
+        amount_msat: amount(150_000),
+        description: Some(String::from("coffee")),
+        payee: Some(bytes(0x02, 33)),
+        created_at: Some(1_700_000_000),
+        expiry: Some(3600),
+        payment_hash: Some(bytes(0x11, 32)),
+        payment_secret: Some(bytes(0x44, 32)),
+        min_final_cltv_expiry: Some(18),
+        // var_onion_optin, payment_secret and basic_mpp, all supported.
+        features: Some(vec![0x02, 0x82, 0x00]),
+        routes: Some(cln::DecodeRoutehintList {
+            hints: vec![cln::DecodeRoutehint {
+                hops: vec![cln::DecodeRoutehop {
+                    pubkey: bytes(0x03, 33),
+                    short_channel_id: String::from("799990x1x0"),
+                    fee_base_msat: amount(1_000),
+                    fee_proportional_millionths: 100,
+                    cltv_expiry_delta: 144,
+                }],
+            }],
+        }),
+        ..Default::default()
+    };
+    assert_golden("decode_bolt11_response", &DecodeResponse::from(decoded));
+}
+
+#[test]
+fn decode_bolt11_offline() {
+    // "1 cup coffee" from the BOLT11 test vectors.
+    let bolt11 = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+    assert_golden(
+        "decode_bolt11_offline",
+        &decode_bolt11(bolt11.to_string()).unwrap(),
+    );
+}
+
+#[test]
+fn decode_bolt11_rejects_bad_checksum() {
+    let bolt11 = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rq";
+    assert!(is_invalid_argument(decode_bolt11(bolt11.to_string())));
+}
+
+#[test]
+fn decode_bolt11_rejects_forged_payee() {
+    // The invoice from `decode_bolt11_offline` with an `n` field naming a
+    // payee that didn't sign it, and the checksum recomputed.
+    let bolt11 = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgqnp4qgg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zuk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpezva4g";
+    assert!(is_invalid_argument(decode_bolt11(bolt11.to_string())));
+}
+
+#[test]
+fn set_session_cache_rejects_zero_max_age() {
+    assert!(is_invalid_argument(set_session_cache(None, Some(0))));
+    assert!(set_session_cache(None, None).is_ok());
+}
+
+#[test]
+fn transaction() {
+    let tx = cln::ListtransactionsTransactions {
+        hash: bytes(0x11, 32),
+        rawtx: bytes(0x22, 4),
+        blockheight: 0,
+        txindex: 0,
+        locktime: 800_000,
+        version: 2,
+        inputs: vec![cln::ListtransactionsTransactionsInputs {
+            txid: bytes(0x33, 32),
+            index: 1,
+            sequence: 0xfffffffd,
+            ..Default::default()
+        }],
+        outputs: vec![cln::ListtransactionsTransactionsOutputs {
+            index: 0,
+            amount_msat: amount(50_000_000),
+            script_pub_key: bytes(0x44, 22),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    assert_golden("transaction", &Transaction::from(tx));
+}
+
+#[test]
+fn set_channel_request() {
+    let req = cln::SetchannelRequest::from(SetChannelRequest {
+        id: None,
+        fee_base_msat: Some(1000),
+        fee_proportional_millionths: Some(250),
+        htlc_minimum_msat: None,
+        htlc_maximum_msat: Some(50_000_000),
+        enforce_delay_secs: None,
+    });
+    assert_eq!(req.id, "all");
+    assert_eq!(req.feebase, amount(1000));
+    assert_eq!(req.feeppm, Some(250));
+    assert_eq!(req.htlcmin, None);
+    assert_eq!(req.htlcmax, amount(50_000_000));
+}
+
+#[test]
+fn send_pay_request() {
+    let hop = RouteHop {
+        id: hex_of(0x02, 33),
+        channel: String::from("800000x1x0"),
+        direction: 1,
+        amount_msat: Some(10_001),
+        delay: 150,
+    };
+    let req = cln::SendpayRequest::try_from(SendPayRequest {
+        route: vec![hop.clone()],
+        payment_hash: hex_of(0xaa, 32),
+        label: None,
+        amount_msat: None,
+        bolt11: None,
+        payment_secret: Some(hex_of(0xbb, 32)),
+        partid: None,
+        groupid: None,
+    })
+    .unwrap();
+    assert_eq!(
+        req.route,
+        vec![cln::SendpayRoute {
+            id: bytes(0x02, 33),
+            channel: String::from("800000x1x0"),
+            amount_msat: amount(10_001),
+            delay: 150,
+        }]
+    );
+    assert_eq!(req.payment_secret, Some(bytes(0xbb, 32)));
+
+    let req = SendPayRequest {
+        route: vec![RouteHop {
+            amount_msat: None,
+            ..hop
+        }],
+        payment_hash: hex_of(0xaa, 32),
+        label: None,
+        amount_msat: None,
+        bolt11: None,
+        payment_secret: None,
+        partid: None,
+        groupid: None,
+    };
+    assert!(is_invalid_argument(cln::SendpayRequest::try_from(req)));
+}
+
+#[test]
+fn forward() {
+    let forward = cln::ListforwardsForwards {
+        in_channel: String::from("799990x1x0"),
+        in_htlc_id: Some(4),
+        out_channel: Some(String::from("799991x2x1")),
+        out_htlc_id: Some(7),
+        status: cln::listforwards_forwards::ListforwardsForwardsStatus::Settled as i32,
+        in_msat: amount(1_001_000),
+        out_msat: amount(1_000_000),
+        fee_msat: amount(1_000),
+        received_time: 1_700_000_000.5,
+        resolved_time: Some(1_700_000_001.75),
+        created_index: Some(12),
+        ..Default::default()
+    };
+    assert_golden("forward", &Forward::from(forward));
+}
+
+#[test]
+fn closed_channel() {
+    use cln::listclosedchannels_closedchannels::ListclosedchannelsClosedchannelsCloseCause;
+
+    let channel = cln::ListclosedchannelsClosedchannels {
+        peer_id: Some(bytes(0x03, 33)),
+        channel_id: bytes(0x33, 32),
+        short_channel_id: Some(String::from("799990x1x0")),
+        opener: cln::ChannelSide::Local as i32,
+        closer: Some(cln::ChannelSide::Remote as i32),
+        funding_txid: bytes(0x22, 32),
+        total_msat: amount(50_000_000),
+        final_to_us_msat: amount(20_000_000),
+        last_commitment_txid: Some(bytes(0x44, 32)),
+        last_commitment_fee_msat: amount(2_000_000),
+        close_cause: ListclosedchannelsClosedchannelsCloseCause::Remote as i32,
+        ..Default::default()
+    };
+    assert_golden("closed_channel", &ClosedChannel::from(channel));
+}
+
+#[test]
+fn fetch_invoice_changes() {
+    let changes = cln::FetchinvoiceChanges {
+        description_appended: Some(String::from(" (discounted)")),
+        amount_msat: amount(900_000),
+        ..Default::default()
+    };
+    assert_golden("fetch_invoice_changes", &FetchInvoiceChanges::from(changes));
+}
+
+#[test]
+fn list_peers_response() {
+    let response = cln::ListpeersResponse {
+        peers: vec![cln::ListpeersPeers {
+            id: bytes(0x03, 33),
+            connected: true,
+            netaddr: vec![String::from("127.0.0.1:9735")],
+            features: Some(vec![0x80, 0x00, 0x08, 0x8a]),
+            ..Default::default()
+        }],
+    };
+    assert_golden("list_peers_response", &ListPeersResponse::from(response));
+}
+
+#[test]
+fn list_htlcs_response() {
+    let response = cln::ListhtlcsResponse {
+        htlcs: vec![cln::ListhtlcsHtlcs {
+            short_channel_id: String::from("103x1x0"),
+            expiry: 800144,
+            amount_msat: amount(21_000),
+            direction: cln::listhtlcs_htlcs::ListhtlcsHtlcsDirection::In as i32,
+            payment_hash: bytes(0x11, 32),
+            state: cln::HtlcState::RcvdRemoveAckRevocation as i32,
+            ..Default::default()
+        }],
+    };
+    assert_golden("list_htlcs_response", &ListHtlcsResponse::from(response));
+}
+
+#[test]
+fn verify_preimage_checks_hash() {
+    // sha256 of 32 zero bytes.
+    let payment_hash = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+    let preimage = hex_of(0x00, 32);
+
+    assert!(verify_preimage(payment_hash.to_string(), preimage.clone()).unwrap());
+    assert!(!verify_preimage(hex_of(0x66, 32), preimage.clone()).unwrap());
+    assert!(is_invalid_argument(verify_preimage(
+        payment_hash.to_string(),
+        String::from("zz")
+    )));
+    assert!(is_invalid_argument(verify_preimage(
+        hex_of(0x66, 31),
+        preimage
+    )));
+}
+
+#[test]
+fn chain_status() {
+    let status = ChainStatus::from(cln::GetinfoResponse {
+        blockheight: 800_000,
+        warning_bitcoind_sync: Some(String::from("Bitcoind is not up-to-date")),
+        ..Default::default()
+    });
+    assert_eq!(status.block_height, 800_000);
+    assert!(!status.synced);
+
+    let status = ChainStatus::from(cln::GetinfoResponse::default());
+    assert!(status.synced);
+}
+
+// Requests
+
+#[test]
+fn make_invoice_request() {
+    let req = MakeInvoiceRequest {
+        amount_msat: 1_000_000,
+        description: String::from("coffee"),
+        label: String::from("order-1"),
+        expiry: Some(3600),
+        fallbacks: None,
+        preimage: Some(hex_of(0xcc, 32)),
+        cltv: Some(80),
+        deschashonly: None,
+        truncate_description: false,
+    };
+    assert_eq!(
+        cln::InvoiceRequest::try_from(req).unwrap(),
+        cln::InvoiceRequest {
+            label: String::from("order-1"),
+            amount_msat: Some(cln::AmountOrAny {
+                value: Some(cln::amount_or_any::Value::Amount(cln::Amount {
+                    msat: 1_000_000
+                })),
+            }),
+            description: String::from("coffee"),
+            expiry: Some(3600),
+            fallbacks: Vec::new(),
+            preimage: Some(bytes(0xcc, 32)),
+            cltv: Some(80),
+            deschashonly: None,
+        }
+    );
+}
+
+#[test]
+fn make_invoice_request_rejects_invalid_preimage() {
+    let req = MakeInvoiceRequest {
+        amount_msat: 1_000_000,
+        description: String::from("coffee"),
+        label: String::from("order-1"),
+        expiry: None,
+        fallbacks: None,
+        preimage: Some(String::from("not hex")),
+        cltv: None,
+        deschashonly: None,
+        truncate_description: false,
+    };
+    assert!(is_invalid_argument(cln::InvoiceRequest::try_from(req)));
+}
+
+#[test]
+fn make_invoice_request_rejects_oversized_description() {
+    let req = MakeInvoiceRequest {
+        amount_msat: 1_000_000,
+        description: "\u{2615}".repeat(214),
+        label: String::from("order-1"),
+        expiry: None,
+        fallbacks: None,
+        preimage: None,
+        cltv: None,
+        deschashonly: None,
+        truncate_description: false,
+    };
+    assert!(is_invalid_argument(cln::InvoiceRequest::try_from(req)));
+}
+
+#[test]
+fn pay_request() {
+    let req = PayRequest {
+        bolt11: String::from("lnbc10u1test"),
+        description: Some(String::from("[[\"text/plain\",\"coffee\"]]")),
+        amount_msat: Some(5_000),
+        maxfee_msat: Some(100),
+        maxfeepercent: None,
+        exemptfee_msat: Some(10),
+        retry_for: Some(30),
+        maxdelay: Some(2016),
+        label: Some(String::from("coffee")),
+    };
+    assert_eq!(
+        cln::PayRequest::from(req),
+        cln::PayRequest {
+            bolt11: String::from("lnbc10u1test"),
+            description: Some(String::from("[[\"text/plain\",\"coffee\"]]")),
+            amount_msat: amount(5_000),
+            maxfee: amount(100),
+            exemptfee: amount(10),
+            retry_for: Some(30),
+            maxdelay: Some(2016),
+            label: Some(String::from("coffee")),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn key_send_request() {
+    let req = KeySendRequest {
+        destination: hex_of(0x03, 33),
+        amount_msat: Some(1_000),
+        label: Some(String::from("tip")),
+        extra_tlvs: Some(vec![TlvEntry {
+            ty: 34349334,
+            value: hex::encode("hello"),
+        }]),
+        route_hints: Some(vec![RouteHint {
+            hops: vec![RouteHintHop {
+                id: hex_of(0x02, 33),
+                short_channel_id: String::from("799990x1x0"),
+                fee_base_msat: 1_000,
+                fee_proportional_millionths: 100,
+                cltv_expiry_delta: 40,
+            }],
+        }]),
+        maxfee_msat: Some(50),
+        maxfeepercent: Some(0.5),
+        exemptfee_msat: Some(5_000),
+        retry_for: Some(60),
+        maxdelay: Some(2016),
+    };
+    assert_eq!(
+        cln::KeysendRequest::try_from(req).unwrap(),
+        cln::KeysendRequest {
+            destination: bytes(0x03, 33),
+            amount_msat: amount(1_000),
+            label: Some(String::from("tip")),
+            extratlvs: Some(cln::TlvStream {
+                entries: vec![cln::TlvEntry {
+                    r#type: 34349334,
+                    value: b"hello".to_vec(),
+                }],
+            }),
+            routehints: Some(cln::RoutehintList {
+                hints: vec![cln::Routehint {
+                    hops: vec![cln::RouteHop {
+                        id: bytes(0x02, 33),
+                        short_channel_id: String::from("799990x1x0"),
+                        feebase: amount(1_000),
+                        feeprop: 100,
+                        expirydelta: 40,
+                    }],
+                }],
+            }),
+            maxfee: amount(50),
+            maxfeepercent: Some(0.5),
+            exemptfee: amount(5_000),
+            retry_for: Some(60),
+            maxdelay: Some(2016),
+        }
+    );
+}
+
+#[test]
+fn key_send_request_rejects_invalid_hex() {
+    let req = KeySendRequest {
+        destination: hex_of(0x03, 33),
+        amount_msat: Some(1_000),
+        label: None,
+        extra_tlvs: None,
+        route_hints: Some(vec![RouteHint {
+            hops: vec![RouteHintHop {
+                id: String::from("zz"),
+                short_channel_id: String::from("799990x1x0"),
+                fee_base_msat: 0,
+                fee_proportional_millionths: 0,
+                cltv_expiry_delta: 0,
+            }],
+        }]),
+        maxfee_msat: None,
+        maxfeepercent: None,
+        exemptfee_msat: None,
+        retry_for: None,
+        maxdelay: None,
+    };
+    assert!(is_invalid_argument(cln::KeysendRequest::try_from(
+        req.clone()
+    )));
+
+    let req = KeySendRequest {
+        destination: String::from("03"),
+        route_hints: None,
+        extra_tlvs: Some(vec![TlvEntry {
+            ty: 1,
+            value: String::from("abc"),
+        }]),
+        ..req
+    };
+    assert!(is_invalid_argument(cln::KeysendRequest::try_from(req)));
+}
+
+#[test]
+fn simple_requests() {
+    assert_eq!(
+        cln::ListfundsRequest::from(ListFundsRequest {
+            spent: Some(true),
+            enrich: false,
+        }),
+        cln::ListfundsRequest { spent: Some(true) }
+    );
+
+    assert_eq!(
+        cln::ConnectRequest::from(ConnectPeerRequest {
+            id: hex_of(0x03, 33),
+            host: Some(String::from("127.0.0.1")),
+            port: Some(9735),
+        }),
+        cln::ConnectRequest {
+            id: hex_of(0x03, 33),
+            host: Some(String::from("127.0.0.1")),
+            port: Some(9735),
+        }
+    );
+
+    assert_eq!(
+        cln::SignmessageRequest::from(SignMessageRequest {
+            message: String::from("hello"),
+        }),
+        cln::SignmessageRequest {
+            message: String::from("hello"),
+        }
+    );
+
+    assert_eq!(
+        cln::DisableinvoicerequestRequest::from(DisableInvoiceRequestRequest {
+            invreq_id: hex_of(0x66, 32),
+        }),
+        cln::DisableinvoicerequestRequest {
+            invreq_id: hex_of(0x66, 32),
+        }
+    );
+
+    assert_eq!(
+        cln::DelinvoiceRequest::from(VoidInvoiceRequest {
+            label: String::from("order-1"),
+        }),
+        cln::DelinvoiceRequest {
+            label: String::from("order-1"),
+            status: cln::delinvoice_request::DelinvoiceStatus::Unpaid as i32,
+            desconly: None,
+        }
+    );
+}
+
+#[test]
+fn fund_channel_request() {
+    let req = FundChannelRequest {
+        id: hex_of(0x03, 33),
+        amount_msat: Some(50_000_000),
+        announce: Some(false),
+        minconf: Some(1),
+        utxos: vec![Outpoint {
+            txid: hex_of(0x22, 32),
+            outnum: 1,
+        }],
+    };
+    assert_eq!(
+        cln::FundchannelRequest::try_from(req).unwrap(),
+        cln::FundchannelRequest {
+            id: bytes(0x03, 33),
+            amount: Some(cln::AmountOrAll {
+                value: Some(cln::amount_or_all::Value::Amount(cln::Amount {
+                    msat: 50_000_000
+                })),
+            }),
+            announce: Some(false),
+            minconf: Some(1),
+            utxos: vec![cln::Outpoint {
+                txid: bytes(0x22, 32),
+                outnum: 1,
+            }],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn fund_channel_request_rejects_invalid_utxo() {
+    let req = FundChannelRequest {
+        id: hex_of(0x03, 33),
+        amount_msat: Some(50_000_000),
+        announce: None,
+        minconf: None,
+        utxos: vec![Outpoint {
+            txid: String::from("not hex"),
+            outnum: 0,
+        }],
+    };
+    assert!(is_invalid_argument(cln::FundchannelRequest::try_from(req)));
+}
+
+#[test]
+fn new_address_request() {
+    use cln::newaddr_request::NewaddrAddresstype;
+
+    for (address_type, expected) in [
+        (NewAddressType::Bech32, NewaddrAddresstype::Bech32),
+        (NewAddressType::P2tr, NewaddrAddresstype::P2tr),
+        (NewAddressType::All, NewaddrAddresstype::All),
+    ] {
+        assert_eq!(
+            cln::NewaddrRequest::from(NewAddressRequest {
+                address_type: Some(address_type),
+            }),
+            cln::NewaddrRequest {
+                addresstype: Some(expected as i32),
+            }
+        );
+    }
+
+    assert_eq!(
+        cln::NewaddrRequest::from(NewAddressRequest { address_type: None }),
+        cln::NewaddrRequest { addresstype: None }
+    );
+}
+
+#[test]
+fn list_invoices_request() {
+    use cln::listinvoices_request::ListinvoicesIndex;
+
+    for (index, expected) in [
+        (ListInvoicesIndex::Created, ListinvoicesIndex::Created),
+        (ListInvoicesIndex::Updated, ListinvoicesIndex::Updated),
+    ] {
+        let req = ListInvoicesRequest {
+            label: None,
+            invstring: None,
+            payment_hash: Some(hex_of(0xaa, 32)),
+            offer_id: None,
+            index: Some(index),
+            start: Some(10),
+            limit: Some(20),
+            created_after: None,
+            paid_after: None,
+            include_sensitive: false,
+        };
+        assert_eq!(
+            cln::ListinvoicesRequest::try_from(req).unwrap(),
+            cln::ListinvoicesRequest {
+                label: None,
+                invstring: None,
+                payment_hash: Some(bytes(0xaa, 32)),
+                offer_id: None,
+                index: Some(expected as i32),
+                start: Some(10),
+                limit: Some(20),
+            }
+        );
+    }
+}
+
+#[test]
+fn list_payments_request() {
+    use cln::listpays_request::ListpaysStatus;
+
+    for (status, expected) in [
+        (ListPaymentsStatus::Pending, ListpaysStatus::Pending),
+        (ListPaymentsStatus::Complete, ListpaysStatus::Complete),
+        (ListPaymentsStatus::Failed, ListpaysStatus::Failed),
+    ] {
+        let req = ListPaymentsRequest {
+            bolt11: None,
+            payment_hash: Some(hex_of(0xaa, 32)),
+            status: Some(status),
+            include_sensitive: false,
+        };
+        assert_eq!(
+            cln::ListpaysRequest::try_from(req).unwrap(),
+            cln::ListpaysRequest {
+                bolt11: None,
+                payment_hash: Some(bytes(0xaa, 32)),
+                status: Some(expected as i32),
+            }
+        );
+    }
+
+    let req = ListPaymentsRequest {
+        bolt11: None,
+        payment_hash: Some(String::from("xyz")),
+        status: None,
+        include_sensitive: false,
+    };
+    assert!(is_invalid_argument(cln::ListpaysRequest::try_from(req)));
+}
+
+#[test]
+fn withdraw_request() {
+    let req = WithdrawRequest {
+        destination: String::from("bc1qtest"),
+        amount: Some(AmountOrAll::Amount { msat: 21_000 }),
+        minconf: Some(2),
+        feerate_perkw: Some(2500),
+    };
+    assert_eq!(
+        cln::WithdrawRequest::from(req),
+        cln::WithdrawRequest {
+            destination: String::from("bc1qtest"),
+            satoshi: Some(cln::AmountOrAll {
+                value: Some(cln::amount_or_all::Value::Amount(cln::Amount {
+                    msat: 21_000
+                })),
+            }),
+            minconf: Some(2),
+            feerate: Some(cln::Feerate {
+                style: Some(cln::feerate::Style::Perkw(2500)),
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(
+        cln::AmountOrAll::from(AmountOrAll::All),
+        cln::AmountOrAll {
+            value: Some(cln::amount_or_all::Value::All(true)),
+        }
+    );
+}
+
+#[test]
+fn set_psbt_version_request() {
+    let req = SetPsbtVersionRequest {
+        psbt: String::from("cHNidP8B"),
+        version: 2,
+    };
+    assert_eq!(
+        cln::SetpsbtversionRequest::try_from(req).unwrap(),
+        cln::SetpsbtversionRequest {
+            psbt: String::from("cHNidP8B"),
+            version: 2,
+        }
+    );
+
+    let req = SetPsbtVersionRequest {
+        psbt: String::from("cHNidP8B"),
+        version: 1,
+    };
+    assert!(is_invalid_argument(cln::SetpsbtversionRequest::try_from(
+        req
+    )));
+}
+
+#[test]
+fn close_request() {
+    let req = CloseRequest {
+        id: hex_of(0x33, 32),
+        unilateral_timeout: Some(60),
+        destination: None,
+        fee_negotiation_step: Some(FeeNegotiationStep::Percent { percent: 50 }),
+        force_lease_closed: None,
+    };
+    assert_eq!(
+        cln::CloseRequest::try_from(req).unwrap(),
+        cln::CloseRequest {
+            id: hex_of(0x33, 32),
+            unilateraltimeout: Some(60),
+            fee_negotiation_step: Some(String::from("50%")),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn offer_requests() {
+    assert_eq!(
+        cln::ListoffersRequest::try_from(ListOffersRequest {
+            offer_id: Some(hex_of(0x55, 32)),
+            active_only: Some(true),
+        })
+        .unwrap(),
+        cln::ListoffersRequest {
+            offer_id: Some(bytes(0x55, 32)),
+            active_only: Some(true),
+        }
+    );
+
+    assert_eq!(
+        cln::DisableofferRequest::try_from(DisableOfferRequest {
+            offer_id: hex_of(0x55, 32),
+        })
+        .unwrap(),
+        cln::DisableofferRequest {
+            offer_id: bytes(0x55, 32),
+        }
+    );
+
+    assert!(is_invalid_argument(cln::DisableofferRequest::try_from(
+        DisableOfferRequest {
+            offer_id: String::from("lno1test"),
+        }
+    )));
+}
+
+#[test]
+fn invoice_request_requests() {
+    assert_eq!(
+        cln::InvoicerequestRequest::from(CreateInvoiceRequestRequest {
+            amount_msat: 5_000,
+            description: String::from("withdrawal"),
+            issuer: None,
+            label: Some(String::from("w-1")),
+            absolute_expiry: Some(1_700_003_600),
+            single_use: Some(true),
+        }),
+        cln::InvoicerequestRequest {
+            amount: amount(5_000),
+            description: String::from("withdrawal"),
+            issuer: None,
+            label: Some(String::from("w-1")),
+            absolute_expiry: Some(1_700_003_600),
+            single_use: Some(true),
+        }
+    );
+
+    assert_eq!(
+        cln::ListinvoicerequestsRequest::try_from(ListInvoiceRequestsRequest {
+            invreq_id: Some(hex_of(0x66, 32)),
+            active_only: None,
+        })
+        .unwrap(),
+        cln::ListinvoicerequestsRequest {
+            invreq_id: Some(bytes(0x66, 32)),
+            active_only: None,
+        }
+    );
+}
+
+#[test]
+fn create_offer_request() {
+    let req = CreateOfferRequest {
+        amount_msat: None,
+        description: String::from("tips"),
+        issuer: None,
+        label: None,
+        quantity_max: None,
+        absolute_expiry: None,
+        single_use: None,
+    };
+    assert_eq!(
+        cln::OfferRequest::from(req),
+        cln::OfferRequest {
+            amount: String::from("any"),
+            description: String::from("tips"),
+            ..Default::default()
+        }
+    );
+
+    let req = CreateOfferRequest {
+        amount_msat: Some(21_000),
+        description: String::from("coffee"),
+        issuer: None,
+        label: Some(String::from("coffee")),
+        quantity_max: None,
+        absolute_expiry: None,
+        single_use: Some(false),
+    };
+    assert_eq!(
+        cln::OfferRequest::from(req).amount,
+        String::from("21000msat")
+    );
+}
+
+#[test]
+fn funder_update_request() {
+    use cln::funderupdate_request::FunderupdatePolicy;
+
+    for (policy, expected) in [
+        (FunderPolicy::Match, FunderupdatePolicy::Match),
+        (FunderPolicy::Available, FunderupdatePolicy::Available),
+        (FunderPolicy::Fixed, FunderupdatePolicy::Fixed),
+    ] {
+        let req = FunderUpdateRequest {
+            policy: Some(policy),
+            policy_mod: Some(100),
+            leases_only: Some(true),
+            min_their_funding_msat: Some(10_000_000),
+            max_their_funding_msat: None,
+            per_channel_min_msat: None,
+            per_channel_max_msat: None,
+            reserve_tank_msat: None,
+            fuzz_percent: Some(0),
+            fund_probability: None,
+            lease_fee_base_msat: Some(2_000_000),
+            lease_fee_basis: Some(65),
+            funding_weight: None,
+            channel_fee_max_base_msat: None,
+            channel_fee_max_proportional_thousandths: None,
+            compact_lease: Some(hex_of(0x77, 4)),
+        };
+        assert_eq!(
+            cln::FunderupdateRequest::try_from(req).unwrap(),
+            cln::FunderupdateRequest {
+                policy: Some(expected as i32),
+                policy_mod: Some(100),
+                leases_only: Some(true),
+                min_their_funding_msat: amount(10_000_000),
+                fuzz_percent: Some(0),
+                lease_fee_base_msat: amount(2_000_000),
+                lease_fee_basis: Some(65),
+                compact_lease: Some(bytes(0x77, 4)),
+                ..Default::default()
+            }
+        );
+    }
+}
+
+#[test]
+fn fetch_invoice_request() {
+    let req = FetchInvoiceRequest {
+        offer: String::from("lno1test"),
+        amount_msat: Some(1_000),
+        payer_note: Some(String::from("thanks")),
+        quantity: None,
+    };
+    assert_eq!(
+        cln::FetchinvoiceRequest::from(req),
+        cln::FetchinvoiceRequest {
+            offer: String::from("lno1test"),
+            amount_msat: amount(1_000),
+            payer_note: Some(String::from("thanks")),
+            ..Default::default()
+        }
+    );
+}
+
+// Properties
+
+proptest! {
+    #[test]
+    fn tlv_value_round_trips(ty: u64, value: Vec<u8>) {
+        let entry = cln::TlvEntry::try_from(TlvEntry { ty, value: hex::encode(&value) }).unwrap();
+        prop_assert_eq!(entry.r#type, ty);
+        prop_assert_eq!(entry.value, value);
+    }
+
+    #[test]
+    fn payment_hash_round_trips(hash: Vec<u8>) {
+        let req = cln::ListpaysRequest::try_from(ListPaymentsRequest {
+            bolt11: None,
+            payment_hash: Some(hex::encode(&hash)),
+            status: None,
+            include_sensitive: false,
+        })
+        .unwrap();
+        prop_assert_eq!(req.payment_hash, Some(hash.clone()));
+
+        let payment = ListPaymentsPayment::from(cln::ListpaysPays {
+            payment_hash: hash.clone(),
+            ..Default::default()
+        });
+        prop_assert_eq!(hex::decode(payment.payment_hash).unwrap(), hash);
+    }
+
+    #[test]
+    fn preimage_round_trips(preimage: Vec<u8>) {
+        let response = KeySendResponse::from(cln::KeysendResponse {
+            payment_preimage: preimage.clone(),
+            ..Default::default()
+        });
+        prop_assert_eq!(hex::decode(response.payment_preimage).unwrap(), preimage);
+    }
+
+    #[test]
+    fn odd_length_hex_is_rejected(value in "([0-9a-f]{2})*[0-9a-f]") {
+        prop_assert!(is_invalid_argument(cln::TlvEntry::try_from(TlvEntry { ty: 1, value })));
+    }
+
+    #[test]
+    fn fee_negotiation_step_in_sats(sat: u64) {
+        let step = String::try_from(FeeNegotiationStep::Sats { sat });
+        if sat == 0 {
+            prop_assert!(is_invalid_argument(step));
+        } else {
+            prop_assert_eq!(step.unwrap(), sat.to_string());
+        }
+    }
+
+    #[test]
+    fn fee_negotiation_step_in_percent(percent: u8) {
+        let step = String::try_from(FeeNegotiationStep::Percent { percent });
+        if (1..=100).contains(&percent) {
+            prop_assert_eq!(step.unwrap(), format!("{}%", percent));
+        } else {
+            prop_assert!(is_invalid_argument(step));
+        }
+    }
+}